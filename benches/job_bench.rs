@@ -6,6 +6,7 @@ use std::path::PathBuf;
 use tokio::io::AsyncWriteExt;
 use tokio::runtime::Runtime;
 use npio::backend::local::LocalBackend;
+use npio::file::local::copy::{copy_file_contents, CopyMethod};
 use npio::{get_file_for_uri, register_backend, CopyFlags};
 use npio::job;
 
@@ -81,6 +82,55 @@ fn bench_copy_job(c: &mut Criterion) {
     std::fs::remove_dir_all(&test_dir).ok();
 }
 
+fn bench_copy_methods(c: &mut Criterion) {
+    let (rt, test_dir) = setup_test_environment();
+    
+    let mut group = c.benchmark_group("copy_methods");
+    
+    let methods = [
+        ("reflink", CopyMethod::Reflink),
+        ("copy_file_range", CopyMethod::CopyFileRange),
+        ("sendfile", CopyMethod::Sendfile),
+        ("read_write", CopyMethod::ReadWrite),
+    ];
+    
+    for size in [1024 * 1024, 1024 * 1024 * 16].iter() {
+        let src_path = test_dir.join(format!("methods_src_{}.bin", size));
+        let dest_path = test_dir.join(format!("methods_dest_{}.bin", size));
+        
+        create_test_file(&rt, &src_path, *size);
+        
+        for (name, method) in methods.iter() {
+            let copy_with_method = || {
+                let mut source = std::fs::File::open(&src_path).unwrap();
+                let mut destination = std::fs::File::create(&dest_path).unwrap();
+                copy_file_contents(&mut source, &mut destination, &[*method], None, None)
+            };
+            
+            // Skip methods the filesystem holding the temp dir does not support
+            if copy_with_method().is_err() {
+                eprintln!("Skipping {} for size {}: not supported here", name, size);
+                continue;
+            }
+            
+            group.bench_with_input(
+                BenchmarkId::new(*name, size),
+                size,
+                |b, _| {
+                    b.iter(|| {
+                        black_box(copy_with_method().unwrap());
+                    });
+                },
+            );
+        }
+    }
+    
+    group.finish();
+    
+    // Cleanup
+    std::fs::remove_dir_all(&test_dir).ok();
+}
+
 fn bench_move_job(c: &mut Criterion) {
     let (rt, test_dir) = setup_test_environment();
     
@@ -246,6 +296,7 @@ fn bench_file_write(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_copy_job,
+    bench_copy_methods,
     bench_move_job,
     bench_delete_job,
     bench_file_read,
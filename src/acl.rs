@@ -0,0 +1,348 @@
+//! POSIX access control lists
+//!
+//! An `Acl` is the list of entries Linux stores in the `system.posix_acl_access`
+//! and `system.posix_acl_default` extended attributes. The owner, owning group
+//! and other entries mirror the permission bits of the file mode; named user and
+//! group entries are limited by the mask entry, which takes the place of the
+//! group bits in the mode once an ACL has named entries.
+//!
+//! The text form used by the `acl::access` and `acl::default` attributes is the
+//! short form of `getfacl -n`: comma separated `tag:qualifier:perms` entries with
+//! numeric IDs, e.g. `user::rw-,user:1000:r--,group::r--,mask::r--,other::---`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitflags::bitflags;
+
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+
+/// Version of the extended attribute encoding, `POSIX_ACL_XATTR_VERSION`.
+const ACL_XATTR_VERSION: u32 = 0x0002;
+
+const ACL_XATTR_HEADER_SIZE: usize = 4;
+const ACL_XATTR_ENTRY_SIZE: usize = 8;
+
+/// Qualifier stored for entries that have none.
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+// Entry tags as stored in the extended attribute
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+/// Which of the two ACLs of a file is meant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AclKind {
+    /// The ACL checked when the file is accessed.
+    Access,
+    /// The ACL new files in a directory inherit; only directories have one.
+    Default,
+}
+
+impl AclKind {
+    /// The extended attribute the ACL is stored in.
+    pub fn xattr_name(&self) -> &'static str {
+        match self {
+            AclKind::Access => "system.posix_acl_access",
+            AclKind::Default => "system.posix_acl_default",
+        }
+    }
+
+    /// The file attribute the ACL is reported as.
+    pub fn attribute(&self) -> &'static str {
+        match self {
+            AclKind::Access => "acl::access",
+            AclKind::Default => "acl::default",
+        }
+    }
+}
+
+/// Who an ACL entry applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AclTag {
+    /// The owner of the file.
+    UserObj,
+    /// The user with the given uid.
+    User(u32),
+    /// The owning group of the file.
+    GroupObj,
+    /// The group with the given gid.
+    Group(u32),
+    /// Upper bound for the permissions of every entry except owner and other.
+    Mask,
+    /// Everyone else.
+    Other,
+}
+
+impl AclTag {
+    fn to_raw(self) -> (u16, u32) {
+        match self {
+            AclTag::UserObj => (ACL_USER_OBJ, ACL_UNDEFINED_ID),
+            AclTag::User(uid) => (ACL_USER, uid),
+            AclTag::GroupObj => (ACL_GROUP_OBJ, ACL_UNDEFINED_ID),
+            AclTag::Group(gid) => (ACL_GROUP, gid),
+            AclTag::Mask => (ACL_MASK, ACL_UNDEFINED_ID),
+            AclTag::Other => (ACL_OTHER, ACL_UNDEFINED_ID),
+        }
+    }
+
+    fn from_raw(tag: u16, id: u32) -> NpioResult<Self> {
+        match tag {
+            ACL_USER_OBJ => Ok(AclTag::UserObj),
+            ACL_USER => Ok(AclTag::User(id)),
+            ACL_GROUP_OBJ => Ok(AclTag::GroupObj),
+            ACL_GROUP => Ok(AclTag::Group(id)),
+            ACL_MASK => Ok(AclTag::Mask),
+            ACL_OTHER => Ok(AclTag::Other),
+            _ => Err(NpioError::new(IOErrorEnum::InvalidData, format!("Unknown ACL entry tag {:#x}", tag))),
+        }
+    }
+
+    /// Named entries and the owning group are limited by the mask.
+    fn is_group_class(&self) -> bool {
+        matches!(self, AclTag::User(_) | AclTag::GroupObj | AclTag::Group(_))
+    }
+}
+
+bitflags! {
+    /// Permissions granted by an ACL entry, with the values of the mode bits.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct AclPermissions: u16 {
+        const NONE = 0;
+        const EXECUTE = 1;
+        const WRITE = 2;
+        const READ = 4;
+    }
+}
+
+impl AclPermissions {
+    fn to_text(self) -> String {
+        [
+            (AclPermissions::READ, 'r'),
+            (AclPermissions::WRITE, 'w'),
+            (AclPermissions::EXECUTE, 'x'),
+        ]
+        .into_iter()
+        .map(|(permission, c)| if self.contains(permission) { c } else { '-' })
+        .collect()
+    }
+
+    fn parse(text: &str) -> NpioResult<Self> {
+        let mut permissions = AclPermissions::NONE;
+        for c in text.chars() {
+            permissions |= match c {
+                'r' => AclPermissions::READ,
+                'w' => AclPermissions::WRITE,
+                'x' => AclPermissions::EXECUTE,
+                '-' => AclPermissions::NONE,
+                _ => {
+                    return Err(NpioError::new(
+                        IOErrorEnum::InvalidArg,
+                        format!("Invalid ACL permissions: {}", text),
+                    ))
+                }
+            };
+        }
+        Ok(permissions)
+    }
+}
+
+/// One entry of an ACL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub permissions: AclPermissions,
+}
+
+impl AclEntry {
+    pub fn new(tag: AclTag, permissions: AclPermissions) -> Self {
+        Self { tag, permissions }
+    }
+}
+
+/// A POSIX ACL, kept with one entry per tag in the order the kernel stores them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Acl {
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    /// An ACL without entries; it needs at least the owner, group and other entries to be valid.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The minimal ACL equivalent to the permission bits of `mode`.
+    pub fn from_mode(mode: u32) -> Self {
+        let permissions = |shift: u32| AclPermissions::from_bits_truncate(((mode >> shift) & 0o7) as u16);
+        let mut acl = Self::new();
+        acl.set(AclTag::UserObj, permissions(6));
+        acl.set(AclTag::GroupObj, permissions(3));
+        acl.set(AclTag::Other, permissions(0));
+        acl
+    }
+
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    /// The permissions of the entry for `tag`, if there is one.
+    pub fn get(&self, tag: AclTag) -> Option<AclPermissions> {
+        self.entries.iter().find(|entry| entry.tag == tag).map(|entry| entry.permissions)
+    }
+
+    /// Adds the entry for `tag` or replaces its permissions.
+    pub fn set(&mut self, tag: AclTag, permissions: AclPermissions) {
+        match self.entries.binary_search_by(|entry| entry.tag.cmp(&tag)) {
+            Ok(index) => self.entries[index].permissions = permissions,
+            Err(index) => self.entries.insert(index, AclEntry::new(tag, permissions)),
+        }
+    }
+
+    /// Removes the entry for `tag`, returning whether there was one.
+    pub fn remove(&mut self, tag: AclTag) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.tag != tag);
+        self.entries.len() != len
+    }
+
+    /// Whether the ACL only has the entries the mode bits can express.
+    pub fn is_minimal(&self) -> bool {
+        self.entries.iter().all(|entry| matches!(entry.tag, AclTag::UserObj | AclTag::GroupObj | AclTag::Other))
+    }
+
+    /// Sets the mask to the union of the permissions it limits, like `setfacl` does.
+    /// Minimal ACLs do not need a mask and are left alone.
+    pub fn calculate_mask(&mut self) {
+        if self.is_minimal() {
+            return;
+        }
+        let mask = self.entries
+            .iter()
+            .filter(|entry| entry.tag.is_group_class())
+            .fold(AclPermissions::NONE, |mask, entry| mask | entry.permissions);
+        self.set(AclTag::Mask, mask);
+    }
+
+    /// The permission bits of a mode with this ACL; the group bits come from the mask if there is one.
+    pub fn to_mode(&self) -> u32 {
+        let bits = |tag: AclTag| self.get(tag).map(|permissions| permissions.bits() as u32).unwrap_or(0);
+        let group = self.get(AclTag::Mask).map(|mask| mask.bits() as u32).unwrap_or_else(|| bits(AclTag::GroupObj));
+        (bits(AclTag::UserObj) << 6) | (group << 3) | bits(AclTag::Other)
+    }
+
+    /// Checks the rules the kernel enforces: owner, group and other entries are required,
+    /// and named entries need a mask.
+    pub fn validate(&self) -> NpioResult<()> {
+        for (tag, name) in [(AclTag::UserObj, "user::"), (AclTag::GroupObj, "group::"), (AclTag::Other, "other::")] {
+            if self.get(tag).is_none() {
+                return Err(NpioError::new(IOErrorEnum::InvalidArg, format!("ACL has no {} entry", name)));
+            }
+        }
+        if !self.is_minimal() && self.get(AclTag::Mask).is_none() {
+            return Err(NpioError::new(IOErrorEnum::InvalidArg, "ACL with named entries has no mask:: entry"));
+        }
+        Ok(())
+    }
+
+    /// Decodes the value of a `system.posix_acl_*` extended attribute.
+    pub fn from_xattr(bytes: &[u8]) -> NpioResult<Self> {
+        let invalid = |message: &str| NpioError::new(IOErrorEnum::InvalidData, message.to_string());
+
+        if bytes.len() < ACL_XATTR_HEADER_SIZE || !(bytes.len() - ACL_XATTR_HEADER_SIZE).is_multiple_of(ACL_XATTR_ENTRY_SIZE) {
+            return Err(invalid("Truncated ACL extended attribute"));
+        }
+        let (header, entries) = bytes.split_at(ACL_XATTR_HEADER_SIZE);
+        if u32::from_le_bytes(header.try_into().unwrap_or_default()) != ACL_XATTR_VERSION {
+            return Err(invalid("Unsupported ACL extended attribute version"));
+        }
+
+        let mut acl = Self::new();
+        for entry in entries.chunks_exact(ACL_XATTR_ENTRY_SIZE) {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let permissions = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            acl.set(AclTag::from_raw(tag, id)?, AclPermissions::from_bits_truncate(permissions));
+        }
+        Ok(acl)
+    }
+
+    /// Encodes the ACL as the value of a `system.posix_acl_*` extended attribute.
+    pub fn to_xattr(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ACL_XATTR_HEADER_SIZE + self.entries.len() * ACL_XATTR_ENTRY_SIZE);
+        bytes.extend_from_slice(&ACL_XATTR_VERSION.to_le_bytes());
+        for entry in &self.entries {
+            let (tag, id) = entry.tag.to_raw();
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            bytes.extend_from_slice(&entry.permissions.bits().to_le_bytes());
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Parses the text form, with entries separated by commas or newlines.
+    /// `u:`, `g:`, `m:` and `o:` are accepted for the tags.
+    pub fn parse(text: &str) -> NpioResult<Self> {
+        let mut acl = Self::new();
+        for entry in text.split([',', '\n']).map(str::trim).filter(|entry| !entry.is_empty()) {
+            let invalid = || NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid ACL entry: {}", entry));
+
+            let mut fields = entry.splitn(3, ':');
+            let (Some(tag), Some(qualifier), Some(permissions)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(invalid());
+            };
+            let id = if qualifier.is_empty() {
+                None
+            } else {
+                Some(qualifier.parse::<u32>().map_err(|_| invalid())?)
+            };
+
+            let tag = match (tag, id) {
+                ("user" | "u", None) => AclTag::UserObj,
+                ("user" | "u", Some(uid)) => AclTag::User(uid),
+                ("group" | "g", None) => AclTag::GroupObj,
+                ("group" | "g", Some(gid)) => AclTag::Group(gid),
+                ("mask" | "m", None) => AclTag::Mask,
+                ("other" | "o", None) => AclTag::Other,
+                _ => return Err(invalid()),
+            };
+            if acl.get(tag).is_some() {
+                return Err(NpioError::new(IOErrorEnum::InvalidArg, format!("Duplicate ACL entry: {}", entry)));
+            }
+            acl.set(tag, AclPermissions::parse(permissions)?);
+        }
+        Ok(acl)
+    }
+}
+
+impl fmt::Display for Acl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self.entries
+            .iter()
+            .map(|entry| {
+                let (tag, qualifier) = match entry.tag {
+                    AclTag::UserObj => ("user", String::new()),
+                    AclTag::User(uid) => ("user", uid.to_string()),
+                    AclTag::GroupObj => ("group", String::new()),
+                    AclTag::Group(gid) => ("group", gid.to_string()),
+                    AclTag::Mask => ("mask", String::new()),
+                    AclTag::Other => ("other", String::new()),
+                };
+                format!("{}:{}:{}", tag, qualifier, entry.permissions.to_text())
+            })
+            .collect();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+impl FromStr for Acl {
+    type Err = NpioError;
+
+    fn from_str(text: &str) -> NpioResult<Self> {
+        Self::parse(text)
+    }
+}
@@ -0,0 +1,439 @@
+//! Applications and the content types they open
+//!
+//! Installed applications are the desktop entries in the `applications`
+//! directory of every XDG data directory. An entry's ID is its path below that
+//! directory with `/` turned into `-` (`kde/okular.desktop` is
+//! `kde-okular.desktop`), and the first directory providing an ID wins, so
+//! users can override or hide (`Hidden=true`) system entries.
+//!
+//! Which application opens what follows the MIME Applications Associations
+//! specification: the `mimeapps.list` files in the XDG config directories
+//! (and, deprecated, the `applications` data directories), with variants for
+//! the desktops in `$XDG_CURRENT_DESKTOP`, give defaults and add or remove
+//! associations on top of the `MimeType` keys of the entries themselves.
+
+mod launch;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::metadata::database::{MimeDatabase, TEXT_PLAIN_TYPE};
+use crate::key_file::KeyFile;
+
+pub use launch::AppLaunchContext;
+
+const APPLICATIONS_DIR: &str = "applications";
+const DESKTOP_FILE_SUFFIX: &str = ".desktop";
+const MIMEAPPS_FILE: &str = "mimeapps.list";
+
+const DESKTOP_ENTRY_GROUP: &str = "Desktop Entry";
+const DEFAULT_APPLICATIONS_GROUP: &str = "Default Applications";
+const ADDED_ASSOCIATIONS_GROUP: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS_GROUP: &str = "Removed Associations";
+
+/// The only `Type` of desktop entry that can be launched with files.
+const APPLICATION_TYPE: &str = "Application";
+
+/// An installed application, described by a desktop entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppInfo {
+    id: String,
+    filename: PathBuf,
+    name: String,
+    generic_name: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    exec: String,
+    mime_types: Vec<String>,
+    no_display: bool,
+    terminal: bool,
+    working_directory: Option<PathBuf>,
+    startup_notify: bool,
+    only_show_in: Vec<String>,
+    not_show_in: Vec<String>,
+}
+
+impl AppInfo {
+    /// Reads a desktop entry; `None` for entries that are hidden, not applications, or whose
+    /// `TryExec` program is missing.
+    fn from_key_file(id: &str, filename: &Path, key_file: &KeyFile) -> Option<Self> {
+        let group = DESKTOP_ENTRY_GROUP;
+        if !key_file.has_group(group)
+            || key_file.get(group, "Type") != Some(APPLICATION_TYPE)
+            || key_file.get_bool(group, "Hidden")
+        {
+            return None;
+        }
+        if let Some(try_exec) = key_file.get_string(group, "TryExec") {
+            find_program_in_path(&try_exec)?;
+        }
+        let locale = crate::locale::environment_locale();
+        let locale = locale.as_deref();
+        Some(Self {
+            id: id.to_string(),
+            filename: filename.to_path_buf(),
+            name: key_file.get_locale_string(group, "Name", locale)?,
+            generic_name: key_file.get_locale_string(group, "GenericName", locale),
+            description: key_file.get_locale_string(group, "Comment", locale),
+            icon: key_file.get_locale_string(group, "Icon", locale),
+            exec: key_file.get_string(group, "Exec")?,
+            mime_types: key_file.get_string_list(group, "MimeType"),
+            no_display: key_file.get_bool(group, "NoDisplay"),
+            terminal: key_file.get_bool(group, "Terminal"),
+            working_directory: key_file.get_string(group, "Path").filter(|path| !path.is_empty()).map(PathBuf::from),
+            startup_notify: key_file.get_bool(group, "StartupNotify"),
+            only_show_in: key_file.get_string_list(group, "OnlyShowIn"),
+            not_show_in: key_file.get_string_list(group, "NotShowIn"),
+        })
+    }
+
+    /// Reads the desktop entry at `filename`, whose file name becomes the ID.
+    pub fn from_filename(filename: &Path) -> NpioResult<Self> {
+        let contents = std::fs::read_to_string(filename)?;
+        let id = filename.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        Self::from_key_file(&id, filename, &KeyFile::parse(&contents)).ok_or_else(|| {
+            NpioError::new(
+                IOErrorEnum::InvalidData,
+                format!("{} is not a valid application desktop entry", filename.display()),
+            )
+        })
+    }
+
+    /// The installed application with the desktop file ID `id`, such as `org.gnome.gedit.desktop`.
+    pub fn new(id: &str) -> Option<Self> {
+        Registry::load().apps.into_iter().find(|app| app.id == id)
+    }
+
+    /// All installed applications, including those not meant to be shown in menus.
+    pub fn get_all() -> Vec<Self> {
+        Registry::load().apps
+    }
+
+    /// The application that opens `content_type` by default: the first installed default of
+    /// `content_type` or else of a type it derives from, then the first recommended application
+    /// of those types. With `must_support_uris`, applications that only take local paths are skipped.
+    pub fn get_default_for_type(content_type: &str, must_support_uris: bool) -> Option<Self> {
+        let registry = Registry::load();
+        let types = registry.type_and_ancestors(content_type);
+        let usable = |app: &&AppInfo| !must_support_uris || app.supports_uris();
+        types
+            .iter()
+            .find_map(|mime_type| registry.defaults(mime_type).into_iter().find(usable))
+            .or_else(|| types.iter().find_map(|mime_type| registry.recommended(mime_type).into_iter().find(usable)))
+            .cloned()
+    }
+
+    /// Applications for `content_type` itself, not for the types it derives from: its defaults,
+    /// the added associations and the entries listing it, without the removed associations.
+    pub fn get_recommended_for_type(content_type: &str) -> Vec<Self> {
+        let registry = Registry::load();
+        let content_type = registry.database.canonicalize(content_type);
+        registry.recommended(&content_type).into_iter().cloned().collect()
+    }
+
+    /// Applications for the types `content_type` derives from that are not recommended for it.
+    pub fn get_fallback_for_type(content_type: &str) -> Vec<Self> {
+        let registry = Registry::load();
+        let types = registry.type_and_ancestors(content_type);
+        let recommended = types.first().map(|mime_type| registry.recommended(mime_type)).unwrap_or_default();
+        let mut fallback: Vec<&AppInfo> = Vec::new();
+        for app in types.iter().skip(1).flat_map(|mime_type| registry.recommended(mime_type)) {
+            if !recommended.contains(&app) && !fallback.contains(&app) {
+                fallback.push(app);
+            }
+        }
+        fallback.into_iter().cloned().collect()
+    }
+
+    /// The recommended applications for `content_type` followed by the fallback ones.
+    pub fn get_all_for_type(content_type: &str) -> Vec<Self> {
+        let mut apps = Self::get_recommended_for_type(content_type);
+        apps.extend(Self::get_fallback_for_type(content_type));
+        apps
+    }
+
+    /// Makes this application the default for `content_type` in the user's `mimeapps.list`,
+    /// adding the association and dropping a removal of it.
+    pub fn set_as_default_for_type(&self, content_type: &str) -> NpioResult<()> {
+        if !content_type.contains('/') {
+            return Err(NpioError::new(
+                IOErrorEnum::InvalidArg,
+                format!("Invalid content type: {}", content_type),
+            ));
+        }
+        let dir = crate::xdg::config_home()
+            .ok_or_else(|| NpioError::new(IOErrorEnum::NotFound, "Could not determine the config directory"))?;
+        let path = dir.join(MIMEAPPS_FILE);
+        let mut list = match std::fs::read_to_string(&path) {
+            Ok(contents) => KeyFile::parse(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => KeyFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let id = self.id.clone();
+        list.set_string_list(DEFAULT_APPLICATIONS_GROUP, content_type, std::slice::from_ref(&id));
+        let mut added = list.get_string_list(ADDED_ASSOCIATIONS_GROUP, content_type);
+        added.retain(|candidate| *candidate != id);
+        added.insert(0, id.clone());
+        list.set_string_list(ADDED_ASSOCIATIONS_GROUP, content_type, &added);
+        let mut removed = list.get_string_list(REMOVED_ASSOCIATIONS_GROUP, content_type);
+        removed.retain(|candidate| *candidate != id);
+        list.set_string_list(REMOVED_ASSOCIATIONS_GROUP, content_type, &removed);
+
+        // Written next to the list and renamed over it, so readers never see half of it
+        std::fs::create_dir_all(&dir)?;
+        let temp_path = dir.join(format!(".{}.{}", MIMEAPPS_FILE, std::process::id()));
+        std::fs::write(&temp_path, list.to_string())?;
+        std::fs::rename(&temp_path, &path).inspect_err(|_| {
+            std::fs::remove_file(&temp_path).ok();
+        })?;
+        Ok(())
+    }
+
+    /// The desktop file ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The desktop entry this application was read from.
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+
+    /// The name in the current locale.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The generic name, such as "Web Browser", in the current locale.
+    pub fn generic_name(&self) -> Option<&str> {
+        self.generic_name.as_deref()
+    }
+
+    /// The `Comment` in the current locale.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The icon name or path.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// The `Exec` command line, field codes included.
+    pub fn commandline(&self) -> &str {
+        &self.exec
+    }
+
+    /// The program the command line runs.
+    pub fn executable(&self) -> &str {
+        self.exec.split_whitespace().next().unwrap_or_default()
+    }
+
+    /// The content types the entry lists in `MimeType`.
+    pub fn supported_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    /// Whether the entry asks not to be shown in menus.
+    pub fn no_display(&self) -> bool {
+        self.no_display
+    }
+
+    /// Whether the application runs in a terminal.
+    pub fn terminal(&self) -> bool {
+        self.terminal
+    }
+
+    /// The directory the application runs in, from `Path`.
+    pub fn working_directory(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+
+    /// Whether the application supports startup notification.
+    pub fn startup_notify(&self) -> bool {
+        self.startup_notify
+    }
+
+    /// Whether the application belongs in menus of the current desktop, given `NoDisplay`,
+    /// `OnlyShowIn` and `NotShowIn`.
+    pub fn should_show(&self) -> bool {
+        if self.no_display {
+            return false;
+        }
+        let desktops = crate::xdg::current_desktops();
+        let listed = |list: &[String]| desktops.iter().any(|desktop| list.contains(desktop));
+        if !self.only_show_in.is_empty() && !listed(&self.only_show_in) {
+            return false;
+        }
+        !listed(&self.not_show_in)
+    }
+
+    /// Whether the command line takes URIs (`%u`, `%U`) rather than only local paths.
+    pub fn supports_uris(&self) -> bool {
+        launch::field_codes(&self.exec).iter().any(|code| matches!(code, 'u' | 'U'))
+    }
+}
+
+/// The installed applications and the `mimeapps.list` files, read for one lookup.
+struct Registry {
+    apps: Vec<AppInfo>,
+    /// Most important first.
+    lists: Vec<KeyFile>,
+    database: std::sync::Arc<MimeDatabase>,
+}
+
+impl Registry {
+    fn load() -> Self {
+        let mut ids = HashSet::new();
+        let mut apps = Vec::new();
+        for dir in applications_dirs() {
+            let mut entries = Vec::new();
+            collect_desktop_files(&dir, "", &mut entries);
+            for (id, path) in entries {
+                // A more important directory had this ID, maybe to hide the entry
+                if !ids.insert(id.clone()) {
+                    continue;
+                }
+                let Ok(contents) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                if let Some(app) = AppInfo::from_key_file(&id, &path, &KeyFile::parse(&contents)) {
+                    apps.push(app);
+                }
+            }
+        }
+        let lists = mimeapps_paths()
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .map(|contents| KeyFile::parse(&contents))
+            .collect();
+        Self { apps, lists, database: MimeDatabase::global() }
+    }
+
+    fn app(&self, id: &str) -> Option<&AppInfo> {
+        self.apps.iter().find(|app| app.id == id)
+    }
+
+    /// The IDs a list gives for `mime_type` in `group`, under its own name or an alias.
+    fn list_ids(&self, list: &KeyFile, group: &str, mime_type: &str) -> Vec<String> {
+        list.keys(group)
+            .into_iter()
+            .filter(|key| self.database.canonicalize(key) == mime_type)
+            .flat_map(|key| list.get_string_list(group, key))
+            .collect()
+    }
+
+    /// The installed defaults of the canonical `mime_type`, most important first.
+    fn defaults(&self, mime_type: &str) -> Vec<&AppInfo> {
+        self.lists
+            .iter()
+            .flat_map(|list| self.list_ids(list, DEFAULT_APPLICATIONS_GROUP, mime_type))
+            .filter_map(|id| self.app(&id))
+            .collect()
+    }
+
+    /// The applications for the canonical `mime_type`. A removal applies to the associations
+    /// of less important lists and to the entries' own `MimeType` keys.
+    fn recommended(&self, mime_type: &str) -> Vec<&AppInfo> {
+        let mut ids: Vec<String> = Vec::new();
+        let mut removed: HashSet<String> = HashSet::new();
+        for list in &self.lists {
+            for id in self
+                .list_ids(list, DEFAULT_APPLICATIONS_GROUP, mime_type)
+                .into_iter()
+                .chain(self.list_ids(list, ADDED_ASSOCIATIONS_GROUP, mime_type))
+            {
+                if !removed.contains(&id) && !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            removed.extend(self.list_ids(list, REMOVED_ASSOCIATIONS_GROUP, mime_type));
+        }
+        let mut apps: Vec<&AppInfo> = ids.iter().filter_map(|id| self.app(id)).collect();
+        for app in &self.apps {
+            let claims = app.mime_types.iter().any(|claimed| self.database.canonicalize(claimed) == mime_type);
+            if claims && !removed.contains(&app.id) && !apps.contains(&app) {
+                apps.push(app);
+            }
+        }
+        apps
+    }
+
+    /// The canonical `content_type` followed by the types it derives from, nearest first.
+    fn type_and_ancestors(&self, content_type: &str) -> Vec<String> {
+        let mut types = vec![self.database.canonicalize(content_type)];
+        let mut index = 0;
+        while index < types.len() {
+            let mut parents = self.database.parents(&types[index]);
+            if types[index].starts_with("text/") && types[index] != TEXT_PLAIN_TYPE {
+                parents.push(TEXT_PLAIN_TYPE.to_string());
+            }
+            for parent in parents {
+                let parent = self.database.canonicalize(&parent);
+                if !types.contains(&parent) {
+                    types.push(parent);
+                }
+            }
+            index += 1;
+        }
+        types
+    }
+}
+
+/// The `applications` directories of the XDG data directories, most important first.
+fn applications_dirs() -> Vec<PathBuf> {
+    crate::xdg::data_search_dirs().into_iter().map(|dir| dir.join(APPLICATIONS_DIR)).collect()
+}
+
+/// The `mimeapps.list` files, most important first; the desktop-specific ones precede the
+/// general one of each directory.
+fn mimeapps_paths() -> Vec<PathBuf> {
+    let desktops: Vec<String> = crate::xdg::current_desktops().iter().map(|desktop| desktop.to_lowercase()).collect();
+    crate::xdg::config_search_dirs()
+        .into_iter()
+        .chain(applications_dirs())
+        .flat_map(|dir| {
+            desktops
+                .iter()
+                .map(|desktop| dir.join(format!("{}-{}", desktop, MIMEAPPS_FILE)))
+                .chain(std::iter::once(dir.join(MIMEAPPS_FILE)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Adds the desktop entries below `dir` with their IDs, `prefix` being the ID part of `dir`.
+fn collect_desktop_files(dir: &Path, prefix: &str, entries: &mut Vec<(String, PathBuf)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut children: Vec<_> = read_dir.filter_map(Result::ok).collect();
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let name = child.file_name().to_string_lossy().into_owned();
+        let path = child.path();
+        if path.is_dir() {
+            collect_desktop_files(&path, &format!("{}{}-", prefix, name), entries);
+        } else if name.ends_with(DESKTOP_FILE_SUFFIX) {
+            entries.push((format!("{}{}", prefix, name), path));
+        }
+    }
+}
+
+/// The executable `program` names, looked up in `$PATH` unless it is a path.
+fn find_program_in_path(program: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let is_executable = |path: &Path| {
+        std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
@@ -0,0 +1,302 @@
+//! Running applications with files
+//!
+//! The `Exec` command line is split with the quoting rules of the Desktop Entry
+//! specification and its field codes are expanded: `%f`/`%F` to local paths,
+//! `%u`/`%U` to URIs, `%i` to `--icon <Icon>`, `%c` to the name and `%k` to the
+//! desktop entry. A command line taking a single file is run once per file.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::AppInfo;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file::File;
+
+/// Variable passing the startup notification ID to the launched application.
+const STARTUP_ID_VARIABLE: &str = "DESKTOP_STARTUP_ID";
+
+/// Terminal emulators tried for `Terminal=true`, with the option after which they take
+/// the command to run. `xdg-terminal-exec` picks the user's preferred terminal.
+const TERMINALS: [(&str, &[&str]); 9] = [
+    ("xdg-terminal-exec", &[]),
+    ("kgx", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("mate-terminal", &["-x"]),
+    ("xfce4-terminal", &["-x"]),
+    ("tilix", &["-e"]),
+    ("konsole", &["-e"]),
+    ("x-terminal-emulator", &["-e"]),
+    ("xterm", &["-e"]),
+];
+
+/// Makes startup notification IDs of this process unique.
+static LAUNCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The environment and user event a launch happens in.
+#[derive(Debug, Clone, Default)]
+pub struct AppLaunchContext {
+    /// Variables to set (`Some`) or remove (`None`) in the child, in order.
+    environment: Vec<(String, Option<String>)>,
+    /// Time of the user event causing the launch, for focus stealing prevention.
+    timestamp: u32,
+}
+
+impl AppLaunchContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `variable` in the environment of launched applications.
+    pub fn setenv(&mut self, variable: &str, value: &str) {
+        self.environment.push((variable.to_string(), Some(value.to_string())));
+    }
+
+    /// Removes `variable` from the environment of launched applications.
+    pub fn unsetenv(&mut self, variable: &str) {
+        self.environment.push((variable.to_string(), None));
+    }
+
+    /// Sets the X11 timestamp of the event that caused the launch.
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        self.timestamp = timestamp;
+    }
+
+    /// A new startup notification ID for launching `app`, in the `<unique>_TIME<timestamp>`
+    /// form of the Startup Notification specification.
+    pub fn get_startup_notify_id(&self, app: &AppInfo) -> String {
+        let id: String = app
+            .id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+            .collect();
+        format!(
+            "npio-{}-{}-{}_TIME{}",
+            std::process::id(),
+            LAUNCH_COUNTER.fetch_add(1, Ordering::Relaxed),
+            id,
+            self.timestamp
+        )
+    }
+}
+
+/// A piece of an `Exec` argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    /// A field code outside quotes, such as `f` for `%f`.
+    Code(char),
+}
+
+/// Splits a command line into arguments. Within double quotes, `\` escapes `"`, `` ` ``, `$`
+/// and `\`; field codes are only recognized outside quotes, where `%%` is a literal `%`.
+fn split_command_line(command_line: &str) -> NpioResult<Vec<Vec<Piece>>> {
+    let mut args = Vec::new();
+    let mut pieces: Vec<Piece> = Vec::new();
+    let mut literal = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                // `""` is an empty argument, not nothing
+                if quoted && literal.is_empty() {
+                    pieces.push(Piece::Literal(String::new()));
+                }
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' if quoted => match chars.next() {
+                Some(escaped) => literal.push(escaped),
+                None => break,
+            },
+            '%' if !quoted => {
+                in_arg = true;
+                match chars.next() {
+                    Some('%') => literal.push('%'),
+                    Some(code) => {
+                        if !literal.is_empty() {
+                            pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                        }
+                        pieces.push(Piece::Code(code));
+                    }
+                    None => literal.push('%'),
+                }
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    args.push(std::mem::take(&mut pieces));
+                    in_arg = false;
+                }
+            }
+            c => {
+                literal.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(NpioError::new(
+            IOErrorEnum::InvalidData,
+            format!("Unterminated quote in command line: {}", command_line),
+        ));
+    }
+    if in_arg {
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        args.push(pieces);
+    }
+    Ok(args)
+}
+
+fn codes_of(args: &[Vec<Piece>]) -> Vec<char> {
+    args.iter()
+        .flatten()
+        .filter_map(|piece| match piece {
+            Piece::Code(code) => Some(*code),
+            Piece::Literal(_) => None,
+        })
+        .collect()
+}
+
+/// The field codes of a command line, none if it cannot be split.
+pub(super) fn field_codes(command_line: &str) -> Vec<char> {
+    split_command_line(command_line).map(|args| codes_of(&args)).unwrap_or_default()
+}
+
+/// What a file is passed as: a local path for `%f`/`%F`, a URI for `%u`/`%U`. Paths are
+/// passed as they are, so names that are not UTF-8 reach the application intact.
+fn file_argument(file: &dyn File, code: char, app: &AppInfo) -> NpioResult<OsString> {
+    if code == 'u' || code == 'U' {
+        return Ok(file.uri().into());
+    }
+    file.path().map(PathBuf::into_os_string).ok_or_else(|| {
+        NpioError::new(
+            IOErrorEnum::NotSupported,
+            format!("{} has no local path and {} does not take URIs", file.uri(), app.name),
+        )
+    })
+}
+
+impl AppInfo {
+    /// Expands the field codes of the command line for `files`, which are all passed to a
+    /// `%F`/`%U` argument, or the first of which goes to a `%f`/`%u` one.
+    fn expand_command_line(&self, args: &[Vec<Piece>], files: &[&dyn File]) -> NpioResult<Vec<OsString>> {
+        let mut argv = Vec::new();
+        for pieces in args {
+            match pieces.as_slice() {
+                [Piece::Code(code @ ('F' | 'U'))] => {
+                    for file in files {
+                        argv.push(file_argument(*file, *code, self)?);
+                    }
+                    continue;
+                }
+                [Piece::Code('i')] => {
+                    if let Some(icon) = &self.icon {
+                        argv.push("--icon".into());
+                        argv.push(icon.into());
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let mut arg = OsString::new();
+            let mut only_codes = true;
+            for piece in pieces {
+                match piece {
+                    Piece::Literal(text) => {
+                        arg.push(text);
+                        only_codes = false;
+                    }
+                    Piece::Code(code @ ('f' | 'u')) => {
+                        if let Some(file) = files.first() {
+                            arg.push(file_argument(*file, *code, self)?);
+                        }
+                    }
+                    Piece::Code('c') => arg.push(&self.name),
+                    Piece::Code('k') => arg.push(&self.filename),
+                    // Deprecated and unknown codes expand to nothing
+                    Piece::Code(_) => {}
+                }
+            }
+            // `%f` without files disappears rather than leaving an empty argument
+            if !(only_codes && arg.is_empty()) {
+                argv.push(arg);
+            }
+        }
+        Ok(argv)
+    }
+
+    /// Launches the application with `files`, returning the processes started: one per file
+    /// for command lines taking a single file, otherwise one. Files without a local path are
+    /// only accepted by applications taking URIs (`%u`, `%U`).
+    pub fn launch(&self, files: &[&dyn File], context: Option<&AppLaunchContext>) -> NpioResult<Vec<tokio::process::Child>> {
+        let args = split_command_line(&self.exec)?;
+        let codes = codes_of(&args);
+        let takes_single_file = codes.iter().any(|code| matches!(code, 'f' | 'u'));
+        let takes_files = takes_single_file || codes.iter().any(|code| matches!(code, 'F' | 'U'));
+
+        // File lists per process
+        let batches: Vec<&[&dyn File]> = if takes_single_file && files.len() > 1 {
+            files.chunks(1).collect()
+        } else if takes_files {
+            vec![files]
+        } else {
+            vec![&[]]
+        };
+
+        let default_context = AppLaunchContext::default();
+        let context = context.unwrap_or(&default_context);
+        let terminal = if self.terminal { Some(find_terminal()?) } else { None };
+        let mut children = Vec::new();
+        for batch in batches {
+            let mut argv = self.expand_command_line(&args, batch)?;
+            if let Some((terminal, options)) = &terminal {
+                let mut prefix = vec![terminal.clone().into_os_string()];
+                prefix.extend(options.iter().map(OsString::from));
+                argv.splice(0..0, prefix);
+            }
+            let Some((program, arguments)) = argv.split_first() else {
+                return Err(NpioError::new(
+                    IOErrorEnum::InvalidData,
+                    format!("{} has an empty command line", self.id),
+                ));
+            };
+
+            let mut command = tokio::process::Command::new(program);
+            command.args(arguments);
+            if let Some(dir) = &self.working_directory {
+                command.current_dir(dir);
+            }
+            // Our own startup ID must not leak into the child
+            command.env_remove(STARTUP_ID_VARIABLE);
+            for (variable, value) in &context.environment {
+                match value {
+                    Some(value) => command.env(variable, value),
+                    None => command.env_remove(variable),
+                };
+            }
+            if self.startup_notify {
+                command.env(STARTUP_ID_VARIABLE, context.get_startup_notify_id(self));
+            }
+            let child = command.spawn().map_err(|e| {
+                NpioError::new(IOErrorEnum::Failed, format!("Failed to launch {}: {}", self.id, e))
+            })?;
+            children.push(child);
+        }
+        Ok(children)
+    }
+}
+
+/// The first installed terminal emulator and the options preceding the command.
+fn find_terminal() -> NpioResult<(PathBuf, &'static [&'static str])> {
+    TERMINALS
+        .iter()
+        .find_map(|(name, options)| super::find_program_in_path(name).map(|path| (path, *options)))
+        .ok_or_else(|| NpioError::new(IOErrorEnum::NotFound, "No terminal emulator found"))
+}
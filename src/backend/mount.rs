@@ -1,11 +1,20 @@
 // Basic mount backend implementation
 // Parses /proc/self/mountinfo to provide mount information
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
 use crate::error::{NpioError, NpioResult, IOErrorEnum};
 use crate::mount::Mount;
 use crate::file::local::LocalFile;
 
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
 /// Represents a mount entry from /proc/self/mountinfo
 #[derive(Debug, Clone)]
 pub(crate) struct MountEntry {
@@ -14,9 +23,7 @@ pub(crate) struct MountEntry {
     mount_id: u32,
     #[allow(dead_code)]
     parent_id: u32,
-    #[allow(dead_code)]
     major_minor: String,
-    #[allow(dead_code)]
     root: PathBuf,
     mount_point: PathBuf,
     mount_options: String,
@@ -24,10 +31,136 @@ pub(crate) struct MountEntry {
     optional_fields: String,
     filesystem_type: String,
     source: String,
-    #[allow(dead_code)]
     super_options: String,
 }
 
+impl MountEntry {
+    /// The filesystem type, including the FUSE subtype, e.g. `fuse.sshfs`.
+    pub(crate) fn filesystem_type(&self) -> &str {
+        &self.filesystem_type
+    }
+
+    /// The mounted device or remote share, e.g. `/dev/sda1` or `server:/export`.
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Where the mount is attached.
+    pub(crate) fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    /// The directory of the filesystem that is mounted, `/` unless it is a bind mount.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Per-mount options followed by the superblock options.
+    pub(crate) fn options(&self) -> String {
+        if self.super_options.is_empty() {
+            self.mount_options.clone()
+        } else {
+            format!("{},{}", self.mount_options, self.super_options)
+        }
+    }
+
+    /// Whether this mount or its superblock is read-only.
+    pub(crate) fn is_read_only(&self) -> bool {
+        [&self.mount_options, &self.super_options]
+            .iter()
+            .any(|options| options.split(',').any(|option| option == "ro"))
+    }
+
+    /// The `major:minor` device number, as `st_dev` of files on the mount.
+    fn device(&self) -> Option<(u32, u32)> {
+        let (major, minor) = self.major_minor.split_once(':')?;
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    }
+}
+
+/// Undoes the octal escapes mountinfo uses for spaces, tabs, newlines and backslashes.
+fn unescape_mountinfo_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            let octal = bytes.get(i + 1..i + 4).and_then(|octal| std::str::from_utf8(octal).ok());
+            if let Some(byte) = octal.and_then(|octal| u8::from_str_radix(octal, 8).ok()) {
+                unescaped.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        unescaped.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// The parsed mount table, indexed by device. The kernel flags `/proc/self/mountinfo`
+/// with `POLLPRI` when the table changes, so it is only parsed again after a change.
+struct MountTable {
+    file: std::fs::File,
+    entries: Vec<MountEntry>,
+    by_device: HashMap<(u32, u32), Vec<usize>>,
+}
+
+static MOUNT_TABLE: Lazy<Mutex<Option<MountTable>>> = Lazy::new(|| Mutex::new(None));
+
+impl MountTable {
+    fn open() -> std::io::Result<Self> {
+        let mut table = Self {
+            file: std::fs::File::open(MOUNTINFO_PATH)?,
+            entries: Vec::new(),
+            by_device: HashMap::new(),
+        };
+        table.reload()?;
+        Ok(table)
+    }
+
+    fn reload(&mut self) -> std::io::Result<()> {
+        // Reading the whole file from the start also clears the change notification
+        let mut content = String::new();
+        self.file.rewind()?;
+        self.file.read_to_string(&mut content)?;
+
+        self.entries = content.lines().filter_map(MountBackend::parse_mountinfo_line).collect();
+        self.by_device.clear();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(device) = entry.device() {
+                self.by_device.entry(device).or_default().push(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn has_changed(&self) -> bool {
+        let mut poll_fd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLPRI, revents: 0 };
+        let ret = unsafe { libc::poll(&mut poll_fd, 1, 0) };
+        ret != 0 && (ret < 0 || poll_fd.revents & (libc::POLLPRI | libc::POLLERR) != 0)
+    }
+
+    /// The mount holding `path`, preferring mounts of its device over mere path prefixes,
+    /// which would also match mounts stacked on top of the same directory.
+    fn find(&self, path: &Path, device: (u32, u32)) -> Option<&MountEntry> {
+        match self.by_device.get(&device) {
+            Some(indices) => {
+                let same_device = || indices.iter().map(|&index| &self.entries[index]);
+                longest_mount_prefix(path, same_device()).or_else(|| same_device().next())
+            }
+            None => longest_mount_prefix(path, self.entries.iter()),
+        }
+    }
+}
+
+/// The entry with the longest mount point that `path` is under.
+fn longest_mount_prefix<'a>(path: &Path, entries: impl Iterator<Item = &'a MountEntry>) -> Option<&'a MountEntry> {
+    entries
+        .filter(|entry| path.starts_with(&entry.mount_point))
+        .max_by_key(|entry| entry.mount_point.components().count())
+}
+
 /// Basic mount implementation
 #[derive(Debug)]
 pub struct UnixMount {
@@ -42,7 +175,7 @@ pub struct UnixMount {
 
 impl UnixMount {
     fn new(entry: &MountEntry) -> Self {
-        let is_read_only = entry.mount_options.contains("ro");
+        let is_read_only = entry.is_read_only();
         let is_system_internal = entry.mount_point.as_path() == std::path::Path::new("/")
             || entry.mount_point.starts_with("/sys")
             || entry.mount_point.starts_with("/proc")
@@ -177,8 +310,8 @@ impl MountBackend {
         let mount_id = parts[0].parse::<u32>().ok()?;
         let parent_id = parts[1].parse::<u32>().ok()?;
         let major_minor = parts[2].to_string();
-        let root = PathBuf::from(parts[3]);
-        let mount_point = PathBuf::from(parts[4]);
+        let root = PathBuf::from(unescape_mountinfo_field(parts[3]));
+        let mount_point = PathBuf::from(unescape_mountinfo_field(parts[4]));
         let mount_options = parts[5].to_string();
 
         // Find the separator "-" that separates optional fields from filesystem info
@@ -200,7 +333,7 @@ impl MountBackend {
         }
 
         let filesystem_type = parts[filesystem_type_idx].to_string();
-        let source = parts[filesystem_type_idx + 1].to_string();
+        let source = unescape_mountinfo_field(parts[filesystem_type_idx + 1]);
         let super_options = if parts.len() > filesystem_type_idx + 2 {
             parts[filesystem_type_idx + 2..].join(" ")
         } else {
@@ -221,9 +354,33 @@ impl MountBackend {
         })
     }
 
+    /// Finds the mount entry holding `path`, whose `st_dev` is `device`. Blocking; the mount
+    /// table is cached and only parsed again when it changed.
+    pub(crate) fn mount_entry_for_path(path: &Path, device: u64) -> NpioResult<Option<MountEntry>> {
+        let mut table = match MOUNT_TABLE.lock() {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on mount table: {}", e);
+                e.into_inner()
+            }
+        };
+
+        let read_error = |e: std::io::Error| {
+            NpioError::new(IOErrorEnum::NotFound, format!("Failed to read {}: {}", MOUNTINFO_PATH, e))
+        };
+        match table.as_mut() {
+            Some(table) if table.has_changed() => table.reload().map_err(read_error)?,
+            Some(_) => {}
+            None => *table = Some(MountTable::open().map_err(read_error)?),
+        }
+
+        let device = (libc::major(device), libc::minor(device));
+        Ok(table.as_ref().and_then(|table| table.find(path, device)).cloned())
+    }
+
     /// Gets all mounts from /proc/self/mountinfo
     pub async fn get_mounts(&self) -> NpioResult<Vec<Box<dyn Mount>>> {
-        let mountinfo_path = "/proc/self/mountinfo";
+        let mountinfo_path = MOUNTINFO_PATH;
         let content = tokio::fs::read_to_string(mountinfo_path).await
             .map_err(|e| NpioError::new(
                 IOErrorEnum::NotFound,
@@ -0,0 +1,139 @@
+//! Checksums of file contents
+//!
+//! Mirrors GIO's GChecksum. `File::compute_checksum` streams a file through
+//! `read()` into a `Checksum`; `query_info` exposes the results as
+//! `checksum::*` attributes and `CopyFlags::VERIFY` uses them to check copies.
+
+use tokio::io::AsyncReadExt;
+
+use crate::cancellable::Cancellable;
+use crate::error::NpioResult;
+use crate::file::File;
+use crate::job::ProgressCallback;
+
+/// Size of the buffer used when streaming a file into a checksum.
+const CHECKSUM_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Supported checksum algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumType {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl ChecksumType {
+    /// All supported algorithms.
+    pub const ALL: &'static [ChecksumType] = &[
+        ChecksumType::Md5,
+        ChecksumType::Sha1,
+        ChecksumType::Sha256,
+        ChecksumType::Blake3,
+    ];
+
+    /// The `checksum::*` attribute under which `query_info` reports this checksum.
+    pub fn attribute_key(&self) -> &'static str {
+        match self {
+            ChecksumType::Md5 => "checksum::md5",
+            ChecksumType::Sha1 => "checksum::sha1",
+            ChecksumType::Sha256 => "checksum::sha256",
+            ChecksumType::Blake3 => "checksum::blake3",
+        }
+    }
+}
+
+enum ChecksumState {
+    Md5(md5::Context),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// An incremental checksum computation.
+pub struct Checksum {
+    state: ChecksumState,
+}
+
+impl Checksum {
+    pub fn new(checksum_type: ChecksumType) -> Self {
+        use sha2::Digest;
+
+        let state = match checksum_type {
+            ChecksumType::Md5 => ChecksumState::Md5(md5::Context::new()),
+            ChecksumType::Sha1 => ChecksumState::Sha1(sha1::Sha1::new()),
+            ChecksumType::Sha256 => ChecksumState::Sha256(sha2::Sha256::new()),
+            ChecksumType::Blake3 => ChecksumState::Blake3(Box::new(blake3::Hasher::new())),
+        };
+        Self { state }
+    }
+
+    /// Feeds more data into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+
+        match &mut self.state {
+            ChecksumState::Md5(context) => context.consume(data),
+            ChecksumState::Sha1(hasher) => hasher.update(data),
+            ChecksumState::Sha256(hasher) => hasher.update(data),
+            ChecksumState::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// Finishes the computation and returns the digest as a lowercase hex string.
+    pub fn finish(self) -> String {
+        use sha2::Digest;
+
+        match self.state {
+            ChecksumState::Md5(context) => format!("{:x}", context.compute()),
+            ChecksumState::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumState::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumState::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Streams the contents of `file` into a checksum of the given type.
+/// Backs the default implementation of `File::compute_checksum`.
+pub async fn compute_file_checksum<F: File + ?Sized>(
+    file: &F,
+    checksum_type: ChecksumType,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<ProgressCallback>,
+) -> NpioResult<String> {
+    if let Some(c) = cancellable {
+        c.check()?;
+    }
+
+    let total_size = file.query_info("standard::size", cancellable).await
+        .map(|info| info.get_size())
+        .unwrap_or(0) as u64;
+
+    let mut input = file.read(cancellable).await?;
+    let mut checksum = Checksum::new(checksum_type);
+    let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+    let mut total_read = 0u64;
+
+    loop {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let n = input.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+
+        checksum.update(&buffer[..n]);
+        total_read += n as u64;
+
+        if let Some(ref cb) = progress_callback {
+            cb(total_read, total_size);
+        }
+    }
+
+    input.close(cancellable)?;
+    Ok(checksum.finish())
+}
@@ -14,8 +14,10 @@ pub enum IOErrorEnum {
     Closed,
     Cancelled,
     NotSupported,
+    WouldRecurse,
     PermissionDenied,
     InvalidArg,
+    InvalidFilename,
     Failed,
     ProxyFailed,
     ProxyAuthFailed,
@@ -30,6 +32,7 @@ pub enum IOErrorEnum {
     AddressInUse,
     PartialInput,
     InvalidData,
+    ChecksumMismatch,
     TimedOut,
     WouldBlock,
     WriteZero,
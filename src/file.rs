@@ -7,10 +7,13 @@ pub mod local;
 
 use async_trait::async_trait;
 use bitflags::bitflags;
+use crate::acl::{Acl, AclKind};
 use crate::cancellable::Cancellable;
-use crate::error::NpioResult;
-use crate::file_info::{FileInfo, FileAttributeType};
+use crate::checksum::ChecksumType;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file_info::{FileInfo, FileAttributeInfo, FileAttributeStatus, FileAttributeType};
 use crate::iostream::{InputStream, OutputStream};
+use crate::tags::Tags;
 
 bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -25,6 +28,11 @@ pub trait File: Send + Sync + std::fmt::Debug {
     /// Gets the URI for this file.
     fn uri(&self) -> String;
 
+    /// Gets the local filesystem path for this file, if it has one.
+    fn path(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+
     /// Gets the base name (filename) of the file.
     fn basename(&self) -> String;
 
@@ -74,7 +82,8 @@ pub trait File: Send + Sync + std::fmt::Debug {
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<Box<dyn crate::file_enumerator::FileEnumerator>>;
 
-    /// Moves the file to a new location.
+    /// Moves the file to a new location. When a move that fell back to copy and delete
+    /// cannot remove the whole source, the copy is kept and `PartialInput` is returned.
     async fn move_to(
         &self,
         destination: &dyn File,
@@ -95,6 +104,17 @@ pub trait File: Send + Sync + std::fmt::Debug {
     /// Checks if the file exists.
     async fn exists(&self, cancellable: Option<&Cancellable>) -> NpioResult<bool>;
 
+    /// Computes a checksum of the file contents by streaming them through `read()`.
+    /// Returns the digest as a lowercase hex string.
+    async fn compute_checksum(
+        &self,
+        checksum_type: ChecksumType,
+        cancellable: Option<&Cancellable>,
+        progress_callback: Option<crate::job::ProgressCallback>,
+    ) -> NpioResult<String> {
+        crate::checksum::compute_file_checksum(self, checksum_type, cancellable, progress_callback).await
+    }
+
     /// Monitors the file or directory for changes.
     async fn monitor(
         &self,
@@ -111,7 +131,57 @@ pub trait File: Send + Sync + std::fmt::Debug {
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<FileInfo>;
 
-    /// Sets file attributes from a FileInfo object
+    /// Lists the attributes that can be set with `set_attribute`.
+    async fn query_settable_attributes(
+        &self,
+        _cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Vec<FileAttributeInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Lists the namespaces in which any attribute can be set, such as `xattr`.
+    async fn query_writable_namespaces(
+        &self,
+        _cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Vec<FileAttributeInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Reads the POSIX ACL of the given kind. Without a stored access ACL, the one
+    /// equivalent to the file mode is returned; `None` means there is no default ACL.
+    async fn query_acl(
+        &self,
+        _kind: AclKind,
+        _cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Option<Acl>> {
+        Err(NpioError::new(IOErrorEnum::NotSupported, "ACLs not supported"))
+    }
+
+    /// Replaces the POSIX ACL of the given kind, or removes it for `None`.
+    async fn set_acl(
+        &self,
+        _kind: AclKind,
+        _acl: Option<&Acl>,
+        _cancellable: Option<&Cancellable>,
+    ) -> NpioResult<()> {
+        Err(NpioError::new(IOErrorEnum::NotSupported, "ACLs not supported"))
+    }
+
+    /// Reads the tags of the file from `tags::list`; a file without tags gives an empty set.
+    async fn query_tags(&self, cancellable: Option<&Cancellable>) -> NpioResult<Tags> {
+        let info = self.query_info("tags::list", cancellable).await?;
+        Ok(Tags::from_info(&info))
+    }
+
+    /// Replaces the tags of the file; an empty set removes them.
+    async fn set_tags(&self, tags: &Tags, cancellable: Option<&Cancellable>) -> NpioResult<()> {
+        let value = if tags.is_empty() { FileAttributeType::Invalid } else { tags.to_attribute() };
+        self.set_attribute("tags::list", &value, FileQueryInfoFlags::NONE, cancellable).await
+    }
+
+    /// Sets file attributes from a FileInfo object. Every attribute is attempted; if any
+    /// failed, the error names all of them. On success, returns the updated standard, unix
+    /// and time attributes.
     async fn set_attributes_from_info(
         &self,
         info: &FileInfo,
@@ -119,6 +189,28 @@ pub trait File: Send + Sync + std::fmt::Debug {
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<FileInfo>;
 
+    /// Like `set_attributes_from_info`, but also records the `FileAttributeStatus` of every
+    /// attribute in `info`. The default implementation cannot tell which attributes failed,
+    /// so on an error it marks all of them `ErrorSetting`.
+    async fn set_attributes_from_info_with_status(
+        &self,
+        info: &mut FileInfo,
+        flags: FileQueryInfoFlags,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<FileInfo> {
+        let result = self.set_attributes_from_info(info, flags, cancellable).await;
+        let status = match result {
+            Ok(_) => FileAttributeStatus::Set,
+            Err(_) => FileAttributeStatus::ErrorSetting,
+        };
+        let keys: Vec<String> = info.get_all_attributes().map(|(key, _)| key.clone()).collect();
+        info.clear_status();
+        for key in keys {
+            info.set_attribute_status(&key, status);
+        }
+        result
+    }
+
     /// Sets a single file attribute
     async fn set_attribute(
         &self,
@@ -1,4 +1,15 @@
-use std::path::PathBuf;
+mod access;
+mod acl;
+pub mod copy;
+mod filesystem;
+mod metadata;
+mod owner;
+pub mod resume;
+mod tags;
+mod xattr;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::os::unix::fs::{PermissionsExt, MetadataExt};
 use std::os::unix::ffi::OsStrExt;
 use async_trait::async_trait;
@@ -6,11 +17,16 @@ use tokio::fs::{self, OpenOptions};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use libc;
 
+use crate::acl::{Acl, AclKind};
 use crate::cancellable::Cancellable;
 use crate::error::{NpioError, NpioResult, IOErrorEnum};
 use crate::file::{File, FileQueryInfoFlags};
 use crate::file_enumerator::FileEnumerator;
-use crate::file_info::{FileInfo, FileType, FileAttributeType};
+use crate::file_info::registry;
+use crate::file_info::{
+    FileInfo, FileAttributeInfo, FileAttributeInfoFlags, FileAttributeKind, FileAttributeMatcher, FileType,
+    FileAttributeStatus, FileAttributeType,
+};
 use crate::iostream::{InputStream, OutputStream};
 
 impl InputStream for fs::File {
@@ -29,6 +45,12 @@ impl OutputStream for fs::File {
     }
 }
 
+/// Size of a `stat` block, the unit of `st_blocks`.
+const STAT_BLOCK_SIZE: u64 = 512;
+
+/// Per-directory list of additional names to hide.
+const HIDDEN_FILE_NAME: &str = ".hidden";
+
 #[derive(Debug)]
 pub struct LocalFile {
     path: PathBuf,
@@ -38,6 +60,285 @@ impl LocalFile {
     pub fn new(path: PathBuf) -> Self {
         Self { path }
     }
+
+    /// Computes the attributes selected by `matcher`, shared by `query_info` and enumeration.
+    /// `hidden_names` are the contents of the parent's `.hidden` file if the caller already read it.
+    async fn query_info_matching(
+        &self,
+        matcher: &FileAttributeMatcher,
+        hidden_names: Option<&HashSet<String>>,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<FileInfo> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let metadata = fs::symlink_metadata(&self.path).await?;
+        let mut info = FileInfo::new();
+
+        let name = self.basename();
+        if matcher.matches("standard::name") {
+            info.set_name(&name);
+        }
+        if matcher.matches("standard::display-name") {
+            info.set_display_name(&name);
+        }
+        if matcher.matches("standard::edit-name") {
+            info.set_edit_name(&name);
+        }
+        if matcher.matches("standard::copy-name") {
+            info.set_attribute("standard::copy-name", FileAttributeType::String(name.clone()));
+        }
+        if matcher.matches("standard::size") {
+            info.set_size(metadata.len());
+        }
+        if matcher.matches("standard::allocated-size") {
+            info.set_attribute(
+                "standard::allocated-size",
+                FileAttributeType::Uint64(metadata.blocks() * STAT_BLOCK_SIZE),
+            );
+        }
+
+        if matcher.enumerate_namespace("time") {
+            // std reads the birth time through statx, filesystems without one report an error
+            let created = metadata.created().ok()
+                .and_then(|created| created.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|created| (created.as_secs() as i64, created.subsec_nanos() as i64));
+            let times = [
+                ("time::modified", Some((metadata.mtime(), metadata.mtime_nsec()))),
+                ("time::access", Some((metadata.atime(), metadata.atime_nsec()))),
+                ("time::changed", Some((metadata.ctime(), metadata.ctime_nsec()))),
+                ("time::created", created),
+            ];
+            for (key, time) in times {
+                if let Some((seconds, nanoseconds)) = time {
+                    set_time_attributes(&mut info, matcher, key, seconds, nanoseconds);
+                }
+            }
+        }
+
+        if matcher.matches("etag::value") {
+            info.set_attribute("etag::value", FileAttributeType::String(etag_for_metadata(&metadata)));
+        }
+
+        if matcher.enumerate_namespace("unix") {
+            let unix_attributes = [
+                ("unix::device", FileAttributeType::Uint32(metadata.dev() as u32)),
+                ("unix::inode", FileAttributeType::Uint64(metadata.ino())),
+                ("unix::mode", FileAttributeType::Uint32(metadata.mode())),
+                ("unix::nlink", FileAttributeType::Uint32(metadata.nlink() as u32)),
+                ("unix::uid", FileAttributeType::Uint32(metadata.uid())),
+                ("unix::gid", FileAttributeType::Uint32(metadata.gid())),
+                ("unix::rdev", FileAttributeType::Uint32(metadata.rdev() as u32)),
+                ("unix::block-size", FileAttributeType::Uint32(metadata.blksize() as u32)),
+                ("unix::blocks", FileAttributeType::Uint64(metadata.blocks())),
+            ];
+            for (key, value) in unix_attributes {
+                if matcher.matches(key) {
+                    info.set_attribute(key, value);
+                }
+            }
+            if matcher.matches("unix::is-mountpoint") {
+                let is_mountpoint = is_mountpoint(&self.path, &metadata).await;
+                info.set_attribute("unix::is-mountpoint", FileAttributeType::Boolean(is_mountpoint));
+            }
+        }
+
+        if matcher.enumerate_namespace("access") {
+            let path = self.path.clone();
+            let access_metadata = metadata.clone();
+            let home_trash = home_trash_dir().ok();
+            let rights = tokio::task::spawn_blocking(move || {
+                access::query_access(&path, &access_metadata, home_trash.as_deref())
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+
+            let access_attributes = [
+                ("access::can-read", rights.can_read),
+                ("access::can-write", rights.can_write),
+                ("access::can-execute", rights.can_execute),
+                ("access::can-delete", rights.can_delete),
+                ("access::can-trash", rights.can_trash),
+                ("access::can-rename", rights.can_rename),
+            ];
+            for (key, value) in access_attributes {
+                if matcher.matches(key) {
+                    info.set_attribute(key, FileAttributeType::Boolean(value));
+                }
+            }
+        }
+
+        if matcher.enumerate_namespace("xattr") || matcher.enumerate_namespace("xattr-sys") {
+            let path = self.path.clone();
+            let xattr_matcher = matcher.clone();
+            let xattr_info = tokio::task::spawn_blocking(move || {
+                let mut xattr_info = FileInfo::new();
+                xattr::query(&path, &xattr_matcher, &mut xattr_info);
+                xattr_info
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+            for (key, value) in xattr_info.get_all_attributes() {
+                info.set_attribute(key, value.clone());
+            }
+        }
+
+        if matcher.enumerate_namespace("acl") {
+            let path = self.path.clone();
+            let acl_metadata = metadata.clone();
+            let acl_matcher = matcher.clone();
+            let acl_info = tokio::task::spawn_blocking(move || {
+                let mut acl_info = FileInfo::new();
+                acl::query(&path, &acl_metadata, &acl_matcher, &mut acl_info);
+                acl_info
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+            for (key, value) in acl_info.get_all_attributes() {
+                info.set_attribute(key, value.clone());
+            }
+        }
+
+        if matcher.enumerate_namespace("tags") {
+            let path = self.path.clone();
+            let tags_metadata = metadata.clone();
+            let tags_matcher = matcher.clone();
+            let tags_info = tokio::task::spawn_blocking(move || {
+                let mut tags_info = FileInfo::new();
+                tags::query(&path, &tags_metadata, &tags_matcher, &mut tags_info);
+                tags_info
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+            for (key, value) in tags_info.get_all_attributes() {
+                info.set_attribute(key, value.clone());
+            }
+        }
+
+        if matcher.enumerate_namespace("metadata") {
+            let path = self.path.clone();
+            let metadata_matcher = matcher.clone();
+            let metadata_info = tokio::task::spawn_blocking(move || {
+                let mut metadata_info = FileInfo::new();
+                metadata::query(&path, &metadata_matcher, &mut metadata_info);
+                metadata_info
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+            for (key, value) in metadata_info.get_all_attributes() {
+                info.set_attribute(key, value.clone());
+            }
+        }
+
+        if matcher.enumerate_namespace("owner") {
+            let uid = metadata.uid();
+            let gid = metadata.gid();
+            let wants_user = matcher.matches("owner::user") || matcher.matches("owner::user-real");
+            let wants_group = matcher.matches("owner::group");
+            // The passwd and group databases may be backed by the network
+            let (user, group) = tokio::task::spawn_blocking(move || {
+                (
+                    if wants_user { owner::user_names(uid) } else { None },
+                    if wants_group { owner::group_name(gid) } else { None },
+                )
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+
+            if let Some(user) = user {
+                if matcher.matches("owner::user") {
+                    info.set_attribute("owner::user", FileAttributeType::String(user.name));
+                }
+                if matcher.matches("owner::user-real") {
+                    info.set_attribute("owner::user-real", FileAttributeType::String(user.real_name));
+                }
+            }
+            if let Some(group) = group {
+                info.set_attribute("owner::group", FileAttributeType::String(group));
+            }
+        }
+
+        let file_type = file_type_for_metadata(&metadata);
+        if matcher.matches("standard::type") {
+            info.set_file_type(file_type);
+        }
+
+        if matcher.matches("standard::is-symlink") {
+            info.set_is_symlink(file_type == FileType::SymbolicLink);
+        }
+        if file_type == FileType::SymbolicLink && matcher.matches("standard::symlink-target") {
+            let target = fs::read_link(&self.path).await?;
+            info.set_symlink_target(target.as_os_str().as_bytes());
+        }
+
+        if matcher.matches("standard::is-hidden") {
+            let is_hidden = if name.starts_with('.') {
+                true
+            } else if let Some(hidden_names) = hidden_names {
+                hidden_names.contains(&name)
+            } else {
+                match self.path.parent() {
+                    Some(parent) => read_hidden_names(parent).await.contains(&name),
+                    None => false,
+                }
+            };
+            info.set_is_hidden(is_hidden);
+        }
+        if matcher.matches("standard::is-backup") {
+            info.set_is_backup(name.ends_with('~'));
+        }
+
+        // Local files have no intrinsic order
+        if matcher.matches("standard::sort-order") {
+            info.set_sort_order(0);
+        }
+
+        // MIME detection: the fast type looks at the name only, the full one also sniffs the contents.
+        // Sniffing opens the file, so listings with standard::* get the fast type as content type
+        let wants_content_type = matcher.matches("standard::content-type");
+        let wants_fast_content_type = matcher.matches("standard::fast-content-type");
+        let wants_icon = matcher.matches("standard::icon");
+        let wants_symbolic_icon = matcher.matches("standard::symbolic-icon");
+        if wants_content_type || wants_fast_content_type || wants_icon || wants_symbolic_icon {
+            let fast_mime_type = match inode_content_type(&metadata) {
+                Some(inode_type) => inode_type.to_string(),
+                None => crate::metadata::MimeResolver::guess_mime_type(&self.path),
+            };
+            let sniff = matcher.matches_by_name("standard::content-type") && file_type == FileType::Regular;
+            let mime_type = if sniff {
+                // Unreadable files keep the type their name suggests
+                let path = self.path.clone();
+                tokio::task::spawn_blocking(move || sniff_content_type_sync(&path))
+                    .await
+                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?
+                    .unwrap_or_else(|_| fast_mime_type.clone())
+            } else {
+                fast_mime_type.clone()
+            };
+            if wants_content_type {
+                info.set_content_type(&mime_type);
+            }
+            if wants_fast_content_type {
+                info.set_attribute("standard::fast-content-type", FileAttributeType::String(fast_mime_type));
+            }
+            if wants_icon {
+                let icon = crate::metadata::MimeResolver::get_icon_name(&mime_type);
+                info.set_attribute("standard::icon", FileAttributeType::String(icon));
+            }
+            if wants_symbolic_icon {
+                let icon = crate::metadata::MimeResolver::get_symbolic_icon_name(&mime_type);
+                info.set_attribute("standard::symbolic-icon", FileAttributeType::String(icon));
+            }
+        }
+
+        // Checksums read the whole file, so they are only computed when asked for by name
+        if file_type == FileType::Regular && matcher.enumerate_namespace("checksum") {
+            for checksum_type in crate::checksum::ChecksumType::ALL {
+                let key = checksum_type.attribute_key();
+                if matcher.matches_explicitly(key) {
+                    let digest = self.compute_checksum(*checksum_type, cancellable, None).await?;
+                    info.set_attribute(key, FileAttributeType::String(digest));
+                }
+            }
+        }
+
+        Ok(info)
+    }
 }
 
 #[async_trait]
@@ -46,6 +347,10 @@ impl File for LocalFile {
         format!("file://{}", self.path.to_string_lossy())
     }
 
+    fn path(&self) -> Option<PathBuf> {
+        Some(self.path.clone())
+    }
+
     fn basename(&self) -> String {
         self.path
             .file_name()
@@ -62,47 +367,7 @@ impl File for LocalFile {
     }
 
     async fn query_info(&self, attributes: &str, cancellable: Option<&Cancellable>) -> NpioResult<FileInfo> {
-        if let Some(c) = cancellable {
-            c.check()?;
-        }
-
-        let metadata = fs::symlink_metadata(&self.path).await?;
-        let mut info = FileInfo::new();
-
-        info.set_name(&self.basename());
-        info.set_size(metadata.len());
-        
-        if let Ok(modified) = metadata.modified() {
-            if let Ok(duration) = modified.duration_since(std::time::UNIX_EPOCH) {
-                info.set_modification_time(duration.as_secs());
-            }
-        }
-
-        let file_type = if metadata.is_dir() {
-            FileType::Directory
-        } else if metadata.is_symlink() {
-            FileType::SymbolicLink
-        } else {
-            FileType::Regular
-        };
-        info.set_file_type(file_type);
-
-        // MIME detection
-        if attributes.contains("standard::content-type") || attributes.contains("standard::*") {
-            let mime_type = if file_type == FileType::Directory {
-                "inode/directory".to_string()
-            } else {
-                crate::metadata::MimeResolver::guess_mime_type(&self.path)
-            };
-            info.set_content_type(&mime_type);
-            
-            if attributes.contains("standard::icon") || attributes.contains("standard::*") {
-                 let icon = crate::metadata::MimeResolver::get_icon_name(&mime_type);
-                 info.set_attribute("standard::icon", crate::file_info::FileAttributeType::String(icon));
-            }
-        }
-        
-        Ok(info)
+        self.query_info_matching(&FileAttributeMatcher::new(attributes), None, cancellable).await
     }
 
     async fn read(&self, cancellable: Option<&Cancellable>) -> NpioResult<Box<dyn InputStream>> {
@@ -116,13 +381,18 @@ impl File for LocalFile {
     async fn replace(
         &self,
         _etag: Option<&str>,
-        _make_backup: bool,
+        make_backup: bool,
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<Box<dyn OutputStream>> {
         if let Some(c) = cancellable {
             c.check()?;
         }
-        // TODO: Handle etag and backup
+        if make_backup {
+            let path = self.path.clone();
+            tokio::task::spawn_blocking(move || make_backup_sync(&path)).await
+                .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))??;
+        }
+        // TODO: Handle etag
         let file = OpenOptions::new()
             .write(true)
             .create(true)
@@ -172,6 +442,14 @@ impl File for LocalFile {
         } else {
             fs::remove_file(&self.path).await?;
         }
+
+        let path = self.path.clone();
+        let result = tokio::task::spawn_blocking(move || metadata::remove_entries(&path)).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))
+            .and_then(|result| result);
+        if let Err(e) = result {
+            eprintln!("Failed to remove metadata: {}", e);
+        }
         Ok(())
     }
 
@@ -185,14 +463,20 @@ impl File for LocalFile {
 
     async fn enumerate_children(
         &self,
-        _attributes: &str,
+        attributes: &str,
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<Box<dyn FileEnumerator>> {
         if let Some(c) = cancellable {
             c.check()?;
         }
         let read_dir = fs::read_dir(&self.path).await?;
-        Ok(Box::new(LocalFileEnumerator { read_dir }))
+        let matcher = FileAttributeMatcher::new(attributes);
+        Ok(Box::new(LocalFileEnumerator {
+            directory: self.path.clone(),
+            read_dir,
+            matcher,
+            hidden_names: None,
+        }))
     }
 
     async fn move_to(
@@ -206,26 +490,66 @@ impl File for LocalFile {
             c.check()?;
         }
         
-        // Basic implementation for now: rename if local, else copy+delete
-        if destination.uri().starts_with("file://") {
-             // Parse destination path
-             let uri = destination.uri();
-             let dest_path_str = uri.trim_start_matches("file://");
-             let dest_path = PathBuf::from(dest_path_str);
-             
-             // Check overwrite
-             if dest_path.exists() && !flags.contains(crate::job::CopyFlags::OVERWRITE) {
-                 return Err(NpioError::new(IOErrorEnum::Exists, "Destination exists"));
-             }
-             
-             tokio::fs::rename(&self.path, dest_path).await
-                .map_err(|e| NpioError::new(IOErrorEnum::Failed, e.to_string()))?;
-                
-             Ok(())
+        if let Some(dest_path) = destination.path() {
+            // Check overwrite
+            if fs::symlink_metadata(&dest_path).await.is_ok() && !flags.contains(crate::job::CopyFlags::OVERWRITE) {
+                return Err(NpioError::new(IOErrorEnum::Exists, "Destination exists"));
+            }
+
+            match fs::rename(&self.path, &dest_path).await {
+                Ok(()) => {
+                    move_metadata(&self.path, &dest_path).await;
+                    return Ok(());
+                }
+                Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // Source and destination are on different filesystems
+            let source_metadata = fs::symlink_metadata(&self.path).await?;
+            if flags.contains(crate::job::CopyFlags::NO_FALLBACK_FOR_MOVE) {
+                return Err(if source_metadata.is_dir() {
+                    NpioError::new(IOErrorEnum::WouldRecurse, "Moving a directory across filesystems requires a recursive copy")
+                } else {
+                    NpioError::new(IOErrorEnum::NotSupported, "Moving across filesystems requires a copy")
+                });
+            }
+
+            // Fall back to copy + delete; only the copy phase reports progress
+            let source_path = self.path.clone();
+            let copy_dest_path = dest_path.clone();
+            let cancellable = cancellable.cloned();
+            tokio::task::spawn_blocking(move || -> NpioResult<()> {
+                copy::copy_local_tree(&source_path, &copy_dest_path, flags, cancellable.as_ref(), progress_callback)?;
+                let removed = if source_metadata.is_dir() {
+                    std::fs::remove_dir_all(&source_path)
+                } else {
+                    std::fs::remove_file(&source_path)
+                };
+                // The destination is complete at this point, so this is not a plain I/O failure
+                removed.map_err(|e| NpioError::new(
+                    IOErrorEnum::PartialInput,
+                    format!(
+                        "Copied {} to {} but could not remove the source, parts of it are left behind: {}",
+                        source_path.display(),
+                        copy_dest_path.display(),
+                        e,
+                    ),
+                ))
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))??;
+            move_metadata(&self.path, &dest_path).await;
+            Ok(())
         } else {
+            if flags.contains(crate::job::CopyFlags::NO_FALLBACK_FOR_MOVE) {
+                return Err(NpioError::new(IOErrorEnum::NotSupported, "Moving to another backend requires a copy"));
+            }
             // Fallback to copy + delete
             self.copy(destination, flags, cancellable, progress_callback).await?;
-            self.delete(cancellable).await?;
+            self.delete(cancellable).await.map_err(|e| NpioError::new(
+                IOErrorEnum::PartialInput,
+                format!("Copied {} but could not remove the source: {}", self.path.display(), e),
+            ))?;
             Ok(())
         }
     }
@@ -241,18 +565,38 @@ impl File for LocalFile {
             c.check()?;
         }
 
+        // Local-to-local copies use reflink/copy_file_range/sendfile where possible
+        if let Some(dest_path) = destination.path() {
+            let source_path = self.path.clone();
+            let cancellable = cancellable.cloned();
+            return tokio::task::spawn_blocking(move || {
+                copy::copy_local_file(
+                    &source_path,
+                    &dest_path,
+                    flags,
+                    cancellable.as_ref(),
+                    progress_callback.as_ref(),
+                )
+            }).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+        }
+
+        if flags.contains(crate::job::CopyFlags::RESUMABLE) {
+            return Err(NpioError::new(IOErrorEnum::NotSupported, "Resumable copies need a local destination"));
+        }
+
         // Open source
         let mut input = self.read(cancellable).await?;
         
         // Open destination
         let mut output = if flags.contains(crate::job::CopyFlags::OVERWRITE) {
-            destination.replace(None, false, cancellable).await?
+            destination.replace(None, flags.contains(crate::job::CopyFlags::BACKUP), cancellable).await?
         } else {
             destination.create_file(cancellable).await?
         };
         
         // Copy loop with progress
-        let mut buffer = [0u8; 8192];
+        let mut buffer = vec![0u8; copy::COPY_BUFFER_SIZE];
         let mut total_written = 0;
         let total_size = self.query_info("standard::size", cancellable).await
             .ok()
@@ -285,7 +629,13 @@ impl File for LocalFile {
         
         output.close(cancellable)?;
         input.close(cancellable)?;
-        
+
+        // Failure to copy metadata is not a hard error
+        let _ = crate::job::copy_attributes(self, destination, flags, cancellable).await;
+
+        if flags.contains(crate::job::CopyFlags::VERIFY) {
+            crate::job::verify_copy(self, destination, cancellable).await?;
+        }
         Ok(())
     }
 
@@ -362,22 +712,11 @@ impl File for LocalFile {
             c.check()?;
         }
 
-        use directories::ProjectDirs;
-        use std::path::Path;
         use chrono::Utc;
 
-        // Get XDG_DATA_HOME, default to ~/.local/share
-        let data_home = std::env::var("XDG_DATA_HOME")
-            .ok()
-            .map(PathBuf::from)
-            .or_else(|| {
-                ProjectDirs::from("", "", "")
-                    .map(|dirs| dirs.data_dir().to_path_buf())
-            })
-            .ok_or_else(|| NpioError::new(IOErrorEnum::Failed, "Could not determine XDG_DATA_HOME"))?;
-
-        let trash_files = data_home.join("Trash").join("files");
-        let trash_info = data_home.join("Trash").join("info");
+        let trash_dir = home_trash_dir()?;
+        let trash_files = trash_dir.join("files");
+        let trash_info = trash_dir.join("info");
 
         // Create trash directories if they don't exist
         fs::create_dir_all(&trash_files).await?;
@@ -412,7 +751,11 @@ impl File for LocalFile {
         // Try to rename atomically - if it fails due to file existing, generate new name and retry
         loop {
             match tokio::fs::rename(&self.path, &trash_file_path).await {
-                Ok(_) => break, // Successfully moved
+                Ok(_) => {
+                    // Keep the metadata so that it comes back when the file is restored
+                    move_metadata(&self.path, &trash_file_path).await;
+                    break;
+                }
                 Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
                     // File exists, generate new name and retry
                     let stem = Path::new(&basename)
@@ -512,65 +855,94 @@ impl File for LocalFile {
         }
 
         let path = self.path.clone();
-        let attrs = attributes.to_string();
-        let result = tokio::task::spawn_blocking(move || {
-            let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
-                .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?;
-            
-            let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
-            let ret = unsafe {
-                libc::statvfs(c_path.as_ptr(), &mut stat)
-            };
-            
-            if ret != 0 {
-                return Err(NpioError::new(
-                    IOErrorEnum::Failed,
-                    format!("statvfs failed: {}", std::io::Error::last_os_error())
-                ));
-            }
+        let matcher = FileAttributeMatcher::new(attributes);
+        tokio::task::spawn_blocking(move || filesystem::query(&path, &matcher)).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?
+    }
 
-            let mut info = FileInfo::new();
-            
-            // Calculate filesystem info
-            let block_size = stat.f_frsize as u64;
-            let total_blocks = stat.f_blocks as u64;
-            let free_blocks = stat.f_bavail as u64; // Available to non-root
-            let used_blocks = total_blocks - stat.f_bfree as u64;
-            
-            let total_size = total_blocks * block_size;
-            let free_size = free_blocks * block_size;
-            let used_size = used_blocks * block_size;
-            
-            if attrs.contains("filesystem::size") || attrs.contains("filesystem::*") {
-                info.set_attribute("filesystem::size", FileAttributeType::Uint64(total_size));
-            }
-            
-            if attrs.contains("filesystem::free") || attrs.contains("filesystem::*") {
-                info.set_attribute("filesystem::free", FileAttributeType::Uint64(free_size));
-            }
-            
-            if attrs.contains("filesystem::used") || attrs.contains("filesystem::*") {
-                info.set_attribute("filesystem::used", FileAttributeType::Uint64(used_size));
-            }
-            
-            if attrs.contains("filesystem::readonly") || attrs.contains("filesystem::*") {
-                let readonly = (stat.f_flag & libc::ST_RDONLY) != 0;
-                info.set_attribute("filesystem::readonly", FileAttributeType::Boolean(readonly));
+    async fn query_settable_attributes(
+        &self,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Vec<FileAttributeInfo>> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        Ok(registry::all()
+            .iter()
+            .filter(|description| description.settable)
+            .map(|description| description.to_info())
+            .collect())
+    }
+
+    async fn query_writable_namespaces(
+        &self,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Vec<FileAttributeInfo>> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let path = self.path.clone();
+        let xattr_supported = tokio::task::spawn_blocking(move || xattr::is_supported(&path)).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+
+        let mut namespaces = vec![FileAttributeInfo::new(
+            "metadata",
+            FileAttributeKind::String,
+            FileAttributeInfoFlags::COPY_WHEN_MOVED,
+        )];
+        if xattr_supported {
+            namespaces.push(FileAttributeInfo::new(
+                "xattr",
+                FileAttributeKind::String,
+                FileAttributeInfoFlags::COPY_WITH_FILE | FileAttributeInfoFlags::COPY_WHEN_MOVED,
+            ));
+            namespaces.push(FileAttributeInfo::new(
+                "xattr-sys",
+                FileAttributeKind::String,
+                FileAttributeInfoFlags::COPY_WHEN_MOVED,
+            ));
+        }
+        Ok(namespaces)
+    }
+
+    async fn query_acl(
+        &self,
+        kind: AclKind,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Option<Acl>> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(acl) = acl::read(&path, kind, true)? {
+                return Ok(Some(acl));
             }
-            
-            // Get filesystem type from /proc/mounts or statfs
-            // For now, we'll use a simplified approach
-            if attrs.contains("filesystem::type") || attrs.contains("filesystem::*") {
-                // Try to get filesystem type from /proc/mounts
-                let fs_type = get_filesystem_type(&path).unwrap_or_else(|| "unknown".to_string());
-                info.set_attribute("filesystem::type", FileAttributeType::String(fs_type));
+            match kind {
+                AclKind::Access => Ok(Some(Acl::from_mode(std::fs::metadata(&path)?.mode()))),
+                AclKind::Default => Ok(None),
             }
-            
-            Ok(info)
         }).await
-        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))??;
-        
-        Ok(result)
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?
+    }
+
+    async fn set_acl(
+        &self,
+        kind: AclKind,
+        acl: Option<&Acl>,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<()> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let path = self.path.clone();
+        let acl = acl.cloned();
+        tokio::task::spawn_blocking(move || acl::write(&path, kind, acl.as_ref(), true)).await
+            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?
     }
 
     async fn set_attributes_from_info(
@@ -578,18 +950,30 @@ impl File for LocalFile {
         info: &FileInfo,
         flags: FileQueryInfoFlags,
         cancellable: Option<&Cancellable>,
+    ) -> NpioResult<FileInfo> {
+        self.set_attributes_from_info_with_status(&mut info.clone(), flags, cancellable).await
+    }
+
+    async fn set_attributes_from_info_with_status(
+        &self,
+        info: &mut FileInfo,
+        flags: FileQueryInfoFlags,
+        cancellable: Option<&Cancellable>,
     ) -> NpioResult<FileInfo> {
         if let Some(c) = cancellable {
             c.check()?;
         }
 
         let path = self.path.clone();
-        let info_clone = info.clone();
+        let mut info_clone = info.clone();
         
-        tokio::task::spawn_blocking(move || {
-            set_attributes_from_info_sync(&path, &info_clone, flags)
+        let (statuses_info, result) = tokio::task::spawn_blocking(move || {
+            let result = set_attributes_from_info_sync(&path, &mut info_clone, flags);
+            (info_clone, result)
         }).await
-        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))??;
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))?;
+        *info = statuses_info;
+        result?;
         
         // Return updated file info
         self.query_info("standard::*,unix::*,time::*", cancellable).await
@@ -689,205 +1073,397 @@ impl File for LocalFile {
     }
 }
 
-// Helper function to get filesystem type
-fn get_filesystem_type(path: &PathBuf) -> Option<String> {
-    use std::fs;
-    use std::io::{BufRead, BufReader};
-    
-    // Read /proc/mounts to find the filesystem type
-    let mounts_file = fs::File::open("/proc/mounts").ok()?;
-    let reader = BufReader::new(mounts_file);
-    
-    // Try to canonicalize the path to get the mount point
-    // If canonicalization fails, use the original path
-    let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
-    
-    // Find the longest matching mount point (most specific)
-    let mut best_match: Option<(usize, String)> = None;
-    
-    for line in reader.lines() {
-        let line = line.ok()?;
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 3 {
-            let mount_point_str = parts[1];
-            let mount_point = PathBuf::from(mount_point_str);
-            // Check if this path is under this mount point
-            if canonical.starts_with(&mount_point) {
-                let mount_len = mount_point.components().count();
-                // Keep the longest (most specific) match
-                let should_update = match &best_match {
-                    Some((len, _)) => mount_len > *len,
-                    None => true,
-                };
-                if should_update {
-                    best_match = Some((mount_len, parts[2].to_string()));
-                }
-            }
-        }
+// Sets a timestamp and its -usec and -nsec parts, as far as they are matched
+fn set_time_attributes(info: &mut FileInfo, matcher: &FileAttributeMatcher, key: &str, seconds: i64, nanoseconds: i64) {
+    if matcher.matches(key) {
+        info.set_attribute(key, FileAttributeType::Uint64(seconds as u64));
+    }
+    let usec_key = format!("{}-usec", key);
+    if matcher.matches(&usec_key) {
+        let microseconds = nanoseconds as u32 / NANOSECONDS_PER_MICROSECOND;
+        info.set_attribute(&usec_key, FileAttributeType::Uint32(microseconds));
+    }
+    let nsec_key = format!("{}-nsec", key);
+    if matcher.matches(&nsec_key) {
+        info.set_attribute(&nsec_key, FileAttributeType::Uint32(nanoseconds as u32));
+    }
+}
+
+// Type of a file as stat reports it, without following symlinks
+fn file_type_for_metadata(metadata: &std::fs::Metadata) -> FileType {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        FileType::Directory
+    } else if file_type.is_symlink() {
+        FileType::SymbolicLink
+    } else if file_type.is_file() {
+        FileType::Regular
+    } else if file_type.is_socket() || file_type.is_fifo() || file_type.is_char_device() || file_type.is_block_device() {
+        FileType::Special
+    } else {
+        FileType::Unknown
     }
-    
-    best_match.map(|(_, fs_type)| fs_type)
+}
+
+// Content type of anything but a regular file, which has no contents to look at
+fn inode_content_type(metadata: &std::fs::Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        Some("inode/directory")
+    } else if file_type.is_symlink() {
+        Some("inode/symlink")
+    } else if file_type.is_char_device() {
+        Some("inode/chardevice")
+    } else if file_type.is_block_device() {
+        Some("inode/blockdevice")
+    } else if file_type.is_fifo() {
+        Some("inode/fifo")
+    } else if file_type.is_socket() {
+        Some("inode/socket")
+    } else {
+        None
+    }
+}
+
+// Content type of a regular file from its name and leading bytes. O_NOATIME keeps
+// queries from touching the access time; only the owner may use it.
+fn sniff_content_type_sync(path: &Path) -> std::io::Result<String> {
+    use std::io::Read;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let file = match std::fs::OpenOptions::new().read(true).custom_flags(libc::O_NOATIME).open(path) {
+        Err(e) if e.raw_os_error() == Some(libc::EPERM) => std::fs::File::open(path)?,
+        result => result?,
+    };
+    let database = crate::metadata::database::MimeDatabase::global();
+    let mut data = Vec::with_capacity(database.sniff_length());
+    file.take(database.sniff_length() as u64).read_to_end(&mut data)?;
+    let name = path.file_name().map(|name| name.to_string_lossy());
+    Ok(database.guess(name.as_deref(), Some(&data)))
+}
+
+// The home trash, $XDG_DATA_HOME/Trash with XDG_DATA_HOME defaulting to ~/.local/share
+fn data_home() -> NpioResult<PathBuf> {
+    crate::xdg::data_home()
+        .ok_or_else(|| NpioError::new(IOErrorEnum::Failed, "Could not determine XDG_DATA_HOME"))
+}
+
+fn home_trash_dir() -> NpioResult<PathBuf> {
+    Ok(data_home()?.join("Trash"))
+}
+
+// Carries metadata:: attributes along with a file that was moved; the move itself already succeeded
+async fn move_metadata(source: &Path, destination: &Path) {
+    let source = source.to_path_buf();
+    let destination = destination.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || metadata::move_entries(&source, &destination)).await
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))
+        .and_then(|result| result);
+    if let Err(e) = result {
+        eprintln!("Failed to move metadata: {}", e);
+    }
+}
+
+// A directory is a mount point if its parent is on another device, or if it is its own parent
+async fn is_mountpoint(path: &Path, metadata: &std::fs::Metadata) -> bool {
+    if !metadata.is_dir() {
+        return false;
+    }
+    match fs::symlink_metadata(path.join("..")).await {
+        Ok(parent) => parent.dev() != metadata.dev() || parent.ino() == metadata.ino(),
+        Err(_) => false,
+    }
+}
+
+// Names listed in a directory's .hidden file, one per line
+async fn read_hidden_names(directory: &Path) -> HashSet<String> {
+    match fs::read_to_string(directory.join(HIDDEN_FILE_NAME)).await {
+        Ok(content) => content
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => HashSet::new(),
+    }
+}
+
+// Entity tag of a local file: modification time as "seconds:microseconds", like GIO
+fn etag_for_metadata(metadata: &std::fs::Metadata) -> String {
+    format!("{}:{}", metadata.mtime(), metadata.mtime_nsec() / 1000)
+}
+
+// Moves an existing file out of the way to "<name>~" before it gets replaced
+fn make_backup_sync(path: &std::path::Path) -> NpioResult<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if metadata.is_dir() {
+        return Err(NpioError::new(IOErrorEnum::IsDirectory, "Cannot make a backup of a directory"));
+    }
+
+    let mut backup_name = path.as_os_str().to_os_string();
+    backup_name.push("~");
+    std::fs::rename(path, &backup_name)
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to create backup file: {}", e)))?;
+    Ok(())
 }
 
 // Synchronous helper to set attributes from FileInfo
 fn set_attributes_from_info_sync(
     path: &PathBuf,
-    info: &FileInfo,
-    _flags: FileQueryInfoFlags,
+    info: &mut FileInfo,
+    flags: FileQueryInfoFlags,
 ) -> NpioResult<()> {
-    // Process each attribute
-    for (key, value) in info.get_all_attributes() {
-        set_attribute_sync(path, key, value, _flags)?;
+    // Seconds and sub-second parts of a timestamp are separate attributes but one utimensat call
+    let mut times = TimestampUpdates::default();
+    let mut time_keys = Vec::new();
+    let mut failures = Vec::new();
+
+    // Renaming goes last, the other attributes are set on the old path
+    let mut attributes: Vec<(String, FileAttributeType)> = info
+        .get_all_attributes()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    attributes.sort_by(|(a, _), (b, _)| {
+        (a == "standard::display-name", a).cmp(&(b == "standard::display-name", b))
+    });
+
+    info.clear_status();
+    for (key, value) in &attributes {
+        if time_attribute_prefix(key).is_some() {
+            times.add(key, value);
+            time_keys.push(key.clone());
+            continue;
+        }
+        match set_attribute_sync(path, key, value, flags) {
+            Ok(()) => {
+                info.set_attribute_status(key, FileAttributeStatus::Set);
+            }
+            Err(e) => {
+                info.set_attribute_status(key, FileAttributeStatus::ErrorSetting);
+                failures.push((key.clone(), e));
+            }
+        }
+    }
+
+    if !times.is_empty() {
+        let status = match set_times_sync(path, &times, flags) {
+            Ok(()) => FileAttributeStatus::Set,
+            Err(e) => {
+                failures.push((time_keys.join(", "), e));
+                FileAttributeStatus::ErrorSetting
+            }
+        };
+        for key in &time_keys {
+            info.set_attribute_status(key, status);
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0).1),
+        _ => {
+            // Like GIO, the first error decides the kind
+            let kind = *failures[0].1.kind();
+            let details: Vec<String> = failures.iter().map(|(key, e)| format!("{}: {}", key, e)).collect();
+            Err(NpioError::new(
+                kind,
+                format!("Failed to set {} attributes: {}", failures.len(), details.join("; ")),
+            ))
+        }
+    }
+}
+
+/// Settable timestamps, as `time::` attribute name prefixes.
+const SETTABLE_TIME_ATTRIBUTES: [&str; 2] = ["time::modified", "time::access"];
+
+const NANOSECONDS_PER_MICROSECOND: u32 = 1000;
+
+/// Older name of `time::access`, still accepted when setting.
+const ACCESS_TIME_ALIAS: &str = "time::accessed";
+
+// The settable timestamp an attribute belongs to, e.g. "time::access" for "time::access-usec"
+fn time_attribute_prefix(attribute: &str) -> Option<&'static str> {
+    if attribute == ACCESS_TIME_ALIAS {
+        return Some("time::access");
+    }
+    SETTABLE_TIME_ATTRIBUTES.into_iter().find(|prefix| {
+        attribute.strip_prefix(prefix).is_some_and(|suffix| matches!(suffix, "" | "-usec" | "-nsec"))
+    })
+}
+
+// New value of a timestamp, any part not given keeps its current value
+#[derive(Debug, Default, Clone, Copy)]
+struct TimestampUpdate {
+    seconds: Option<u64>,
+    microseconds: Option<u32>,
+    nanoseconds: Option<u32>,
+}
+
+impl TimestampUpdate {
+    fn is_empty(&self) -> bool {
+        self.seconds.is_none() && self.microseconds.is_none() && self.nanoseconds.is_none()
+    }
+
+    fn to_timespec(self, current_seconds: i64, current_nanoseconds: i64) -> libc::timespec {
+        if self.is_empty() {
+            return libc::timespec { tv_sec: 0, tv_nsec: libc::UTIME_OMIT };
+        }
+
+        // -nsec is more precise, it wins if both are given
+        let nanoseconds = self.nanoseconds
+            .or(self.microseconds.map(|microseconds| microseconds * NANOSECONDS_PER_MICROSECOND));
+        libc::timespec {
+            tv_sec: self.seconds.map(|s| s as libc::time_t).unwrap_or(current_seconds as libc::time_t),
+            // Setting only the seconds clears the sub-second part, like GIO
+            tv_nsec: match (self.seconds, nanoseconds) {
+                (_, Some(nanoseconds)) => nanoseconds as libc::c_long,
+                (Some(_), None) => 0,
+                (None, None) => current_nanoseconds as libc::c_long,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TimestampUpdates {
+    modified: TimestampUpdate,
+    access: TimestampUpdate,
+}
+
+impl TimestampUpdates {
+    fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.access.is_empty()
+    }
+
+    fn add(&mut self, attribute: &str, value: &FileAttributeType) {
+        let update = match time_attribute_prefix(attribute) {
+            Some("time::modified") => &mut self.modified,
+            Some(_) => &mut self.access,
+            None => return,
+        };
+        match (attribute.rsplit_once('-').map(|(_, suffix)| suffix), value) {
+            (Some("nsec"), FileAttributeType::Uint32(nanoseconds)) => update.nanoseconds = Some(*nanoseconds),
+            (Some("usec"), FileAttributeType::Uint32(microseconds)) => update.microseconds = Some(*microseconds),
+            (_, FileAttributeType::Uint64(seconds)) => update.seconds = Some(*seconds),
+            _ => {}
+        }
+    }
+}
+
+// Applies timestamp updates with a single utimensat call
+fn set_times_sync(path: &Path, times: &TimestampUpdates, flags: FileQueryInfoFlags) -> NpioResult<()> {
+    let metadata = if flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS) {
+        std::fs::symlink_metadata(path)?
+    } else {
+        std::fs::metadata(path)?
+    };
+
+    let timespecs = [
+        times.access.to_timespec(metadata.atime(), metadata.atime_nsec()),
+        times.modified.to_timespec(metadata.mtime(), metadata.mtime_nsec()),
+    ];
+    let at_flags = if flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS) {
+        libc::AT_SYMLINK_NOFOLLOW
+    } else {
+        0
+    };
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid path: {}", e)))?;
+    let ret = unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), timespecs.as_ptr(), at_flags) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
     }
-    
     Ok(())
 }
 
+// The value of an attribute that can only be set to a number, not unset, e.g. unix::mode
+fn required_uint32(attribute: &str, value: &FileAttributeType) -> NpioResult<u32> {
+    match value {
+        FileAttributeType::Uint32(value) => Ok(*value),
+        _ => Err(NpioError::new(
+            IOErrorEnum::InvalidArg,
+            format!("Attribute {} must be a uint32 and cannot be unset", attribute),
+        )),
+    }
+}
+
 // Synchronous helper to set a single attribute
 fn set_attribute_sync(
     path: &PathBuf,
     attribute: &str,
     value: &FileAttributeType,
-    _flags: FileQueryInfoFlags,
+    flags: FileQueryInfoFlags,
 ) -> NpioResult<()> {
     use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH, Duration};
-    
+
+    registry::validate(attribute, value)?;
+
     match attribute {
         // Unix mode (permissions)
         "unix::mode" => {
-            if let FileAttributeType::Uint32(mode) = value {
-                let permissions = fs::Permissions::from_mode(*mode);
-                fs::set_permissions(path, permissions)
-                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to set mode: {}", e)))?;
-            }
+            let mode = required_uint32(attribute, value)?;
+            let permissions = fs::Permissions::from_mode(mode);
+            fs::set_permissions(path, permissions)
+                .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to set mode: {}", e)))?;
         }
         // Unix UID
         "unix::uid" => {
-            if let FileAttributeType::Uint32(uid) = value {
-                let metadata = fs::metadata(path)
-                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
-                let gid = metadata.gid();
-                unsafe {
-                    let ret = libc::chown(
-                        std::ffi::CString::new(path.as_os_str().as_bytes())
-                            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?
-                            .as_ptr(),
-                        *uid as libc::uid_t,
-                        gid as libc::gid_t,
-                    );
-                    if ret != 0 {
-                        return Err(NpioError::new(
-                            IOErrorEnum::Failed,
-                            format!("chown failed: {}", std::io::Error::last_os_error())
-                        ));
-                    }
+            let uid = required_uint32(attribute, value)?;
+            let metadata = fs::metadata(path)
+                .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
+            let gid = metadata.gid();
+            unsafe {
+                let ret = libc::chown(
+                    std::ffi::CString::new(path.as_os_str().as_bytes())
+                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?
+                        .as_ptr(),
+                    uid as libc::uid_t,
+                    gid as libc::gid_t,
+                );
+                if ret != 0 {
+                    return Err(NpioError::new(
+                        IOErrorEnum::Failed,
+                        format!("chown failed: {}", std::io::Error::last_os_error())
+                    ));
                 }
             }
         }
         // Unix GID
         "unix::gid" => {
-            if let FileAttributeType::Uint32(gid) = value {
-                let metadata = fs::metadata(path)
-                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
-                let uid = metadata.uid();
-                unsafe {
-                    let ret = libc::chown(
-                        std::ffi::CString::new(path.as_os_str().as_bytes())
-                            .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?
-                            .as_ptr(),
-                        uid as libc::uid_t,
-                        *gid as libc::gid_t,
-                    );
-                    if ret != 0 {
-                        return Err(NpioError::new(
-                            IOErrorEnum::Failed,
-                            format!("chown failed: {}", std::io::Error::last_os_error())
-                        ));
-                    }
+            let gid = required_uint32(attribute, value)?;
+            let metadata = fs::metadata(path)
+                .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
+            let uid = metadata.uid();
+            unsafe {
+                let ret = libc::chown(
+                    std::ffi::CString::new(path.as_os_str().as_bytes())
+                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?
+                        .as_ptr(),
+                    uid as libc::uid_t,
+                    gid as libc::gid_t,
+                );
+                if ret != 0 {
+                    return Err(NpioError::new(
+                        IOErrorEnum::Failed,
+                        format!("chown failed: {}", std::io::Error::last_os_error())
+                    ));
                 }
             }
         }
-        // Modification time
-        "time::modified" => {
-            if let FileAttributeType::Uint64(timestamp) = value {
-                let metadata = fs::metadata(path)
-                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
-                let accessed = metadata.accessed()
-                    .unwrap_or_else(|_| SystemTime::now());
-                
-                let modified = UNIX_EPOCH + Duration::from_secs(*timestamp);
-                
-                // Use utimes to set both access and modification time
-                unsafe {
-                    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?;
-                    
-                    let accessed_duration = accessed.duration_since(UNIX_EPOCH)
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid access time: {}", e)))?;
-                    let modified_duration = modified.duration_since(UNIX_EPOCH)
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid modified time: {}", e)))?;
-                    
-                    let times = [
-                        libc::timeval {
-                            tv_sec: accessed_duration.as_secs() as i64,
-                            tv_usec: accessed_duration.subsec_micros() as i64,
-                        },
-                        libc::timeval {
-                            tv_sec: modified_duration.as_secs() as i64,
-                            tv_usec: modified_duration.subsec_micros() as i64,
-                        },
-                    ];
-                    
-                    let ret = libc::utimes(c_path.as_ptr(), times.as_ptr());
-                    if ret != 0 {
-                        return Err(NpioError::new(
-                            IOErrorEnum::Failed,
-                            format!("utimes failed: {}", std::io::Error::last_os_error())
-                        ));
-                    }
-                }
-            }
-        }
-        // Access time
-        "time::accessed" => {
-            if let FileAttributeType::Uint64(timestamp) = value {
-                let metadata = fs::metadata(path)
-                    .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to get metadata: {}", e)))?;
-                let modified = metadata.modified()
-                    .unwrap_or_else(|_| SystemTime::now());
-                
-                let accessed = UNIX_EPOCH + Duration::from_secs(*timestamp);
-                
-                unsafe {
-                    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?;
-                    
-                    let accessed_duration = accessed.duration_since(UNIX_EPOCH)
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid access time: {}", e)))?;
-                    let modified_duration = modified.duration_since(UNIX_EPOCH)
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid modified time: {}", e)))?;
-                    
-                    let times = [
-                        libc::timeval {
-                            tv_sec: accessed_duration.as_secs() as i64,
-                            tv_usec: accessed_duration.subsec_micros() as i64,
-                        },
-                        libc::timeval {
-                            tv_sec: modified_duration.as_secs() as i64,
-                            tv_usec: modified_duration.subsec_micros() as i64,
-                        },
-                    ];
-                    
-                    let ret = libc::utimes(c_path.as_ptr(), times.as_ptr());
-                    if ret != 0 {
-                        return Err(NpioError::new(
-                            IOErrorEnum::Failed,
-                            format!("utimes failed: {}", std::io::Error::last_os_error())
-                        ));
-                    }
-                }
-            }
+        // Timestamps, sub-second parts keep the seconds and vice versa
+        attr if time_attribute_prefix(attr).is_some() => {
+            let mut times = TimestampUpdates::default();
+            times.add(attr, value);
+            set_times_sync(path, &times, flags)?;
         }
         // Display name (rename file)
         "standard::display-name" => {
@@ -903,39 +1479,36 @@ fn set_attribute_sync(
                     }
                     fs::rename(path, &new_path)
                         .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Failed to rename: {}", e)))?;
+                    if let Err(e) = metadata::move_entries(path, &new_path) {
+                        eprintln!("Failed to move metadata: {}", e);
+                    }
                 } else {
                     return Err(NpioError::new(IOErrorEnum::Failed, "File has no parent directory"));
                 }
             }
         }
+        // POSIX ACLs
+        "acl::access" => {
+            let follow_symlinks = !flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS);
+            acl::set_attribute(path, AclKind::Access, value, follow_symlinks)?;
+        }
+        "acl::default" => {
+            let follow_symlinks = !flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS);
+            acl::set_attribute(path, AclKind::Default, value, follow_symlinks)?;
+        }
+        // Tags, comment and color label
+        attr if attr.starts_with("tags::") => {
+            let follow_symlinks = !flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS);
+            tags::set_attribute(path, attr, value, follow_symlinks)?;
+        }
+        // Metadata kept in the metadata database
+        attr if attr.starts_with("metadata::") => {
+            metadata::set_attribute(path, attr, value)?;
+        }
         // Extended attributes (xattr)
-        attr if attr.starts_with("xattr::") => {
-            // Extract attribute name
-            let xattr_name = attr.strip_prefix("xattr::").unwrap_or(attr);
-            
-            if let FileAttributeType::ByteString(bytes) = value {
-                unsafe {
-                    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?;
-                    let c_name = std::ffi::CString::new(xattr_name)
-                        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid xattr name: {}", e)))?;
-                    
-                    let ret = libc::setxattr(
-                        c_path.as_ptr(),
-                        c_name.as_ptr(),
-                        bytes.as_ptr() as *const libc::c_void,
-                        bytes.len(),
-                        0, // flags: 0 = create or replace
-                    );
-                    
-                    if ret != 0 {
-                        return Err(NpioError::new(
-                            IOErrorEnum::Failed,
-                            format!("setxattr failed: {}", std::io::Error::last_os_error())
-                        ));
-                    }
-                }
-            }
+        attr if xattr::xattr_name_for_attribute(attr).is_some() => {
+            let follow_symlinks = !flags.contains(FileQueryInfoFlags::NOFOLLOW_SYMLINKS);
+            xattr::set_attribute(path, attr, value, follow_symlinks)?;
         }
         _ => {
             // Unknown or unsupported attribute
@@ -950,7 +1523,11 @@ fn set_attribute_sync(
 }
 
 struct LocalFileEnumerator {
+    directory: PathBuf,
     read_dir: fs::ReadDir,
+    matcher: FileAttributeMatcher,
+    // Read on first use, then shared by all children
+    hidden_names: Option<HashSet<String>>,
 }
 
 #[async_trait]
@@ -959,35 +1536,25 @@ impl FileEnumerator for LocalFileEnumerator {
         &mut self,
         cancellable: Option<&Cancellable>,
     ) -> NpioResult<Option<(FileInfo, Box<dyn File>)>> {
-        if let Some(c) = cancellable {
-            c.check()?;
-        }
-        
-        match self.read_dir.next_entry().await? {
-            Some(entry) => {
-                let path = entry.path();
-                let file = Box::new(LocalFile::new(path.clone()));
-                
-                // We could optimize this by using entry.metadata() if available without extra syscalls
-                // But for consistency let's query the file object (or just basic info here)
-                let mut info = FileInfo::new();
-                info.set_name(&entry.file_name().to_string_lossy());
-                
-                // Populate basic type info from entry if possible
-                if let Ok(file_type) = entry.file_type().await {
-                     let ft = if file_type.is_dir() {
-                        FileType::Directory
-                    } else if file_type.is_symlink() {
-                        FileType::SymbolicLink
-                    } else {
-                        FileType::Regular
-                    };
-                    info.set_file_type(ft);
-                }
+        loop {
+            if let Some(c) = cancellable {
+                c.check()?;
+            }
+
+            let Some(entry) = self.read_dir.next_entry().await? else {
+                return Ok(None);
+            };
+            if self.hidden_names.is_none() && self.matcher.matches("standard::is-hidden") {
+                self.hidden_names = Some(read_hidden_names(&self.directory).await);
+            }
 
-                Ok(Some((info, file)))
+            let file = LocalFile::new(entry.path());
+            match file.query_info_matching(&self.matcher, self.hidden_names.as_ref(), cancellable).await {
+                Ok(info) => return Ok(Some((info, Box::new(file)))),
+                // Deleted between listing the directory and querying it
+                Err(e) if matches!(e.kind(), IOErrorEnum::NotFound) => continue,
+                Err(e) => return Err(e),
             }
-            None => Ok(None),
         }
     }
 
@@ -0,0 +1,93 @@
+//! `access::*` attributes
+//!
+//! Computed the way GIO does: read, write and execute come from `faccessat`
+//! with the effective user and group IDs, delete and rename from write
+//! permission on the parent directory plus the sticky-bit rules, and nothing
+//! that modifies the file is allowed on a read-only mount. Trashing is
+//! possible when the file can be deleted and lives on the same filesystem as
+//! the home trash, which is the only trash `LocalFile::trash` moves files to.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// What the current process may do with a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) struct AccessRights {
+    pub can_read: bool,
+    pub can_write: bool,
+    pub can_execute: bool,
+    pub can_delete: bool,
+    pub can_trash: bool,
+    pub can_rename: bool,
+}
+
+/// Computes the access rights for `path`, whose `lstat` result is `metadata`.
+/// `home_trash` is the home trash directory, if it could be determined.
+pub(super) fn query_access(path: &Path, metadata: &std::fs::Metadata, home_trash: Option<&Path>) -> AccessRights {
+    let read_only = is_read_only_mount(path);
+    let mut rights = AccessRights {
+        can_read: effective_access(path, libc::R_OK),
+        can_write: !read_only && effective_access(path, libc::W_OK),
+        can_execute: effective_access(path, libc::X_OK),
+        ..AccessRights::default()
+    };
+
+    let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) else {
+        return rights;
+    };
+    let parent_metadata = match std::fs::metadata(parent) {
+        Ok(parent_metadata) => parent_metadata,
+        Err(_) => return rights,
+    };
+
+    // Removing an entry needs write and search permission on the directory
+    let mut can_delete = !read_only && effective_access(parent, libc::W_OK | libc::X_OK);
+
+    // In a sticky directory only the owners of the file or the directory may remove it
+    if can_delete && parent_metadata.mode() & libc::S_ISVTX != 0 {
+        let euid = unsafe { libc::geteuid() };
+        can_delete = euid == 0 || euid == metadata.uid() || euid == parent_metadata.uid();
+    }
+
+    rights.can_delete = can_delete;
+    rights.can_rename = can_delete;
+    rights.can_trash = can_delete && home_trash.is_some_and(|trash| can_trash_to(path, metadata, trash));
+    rights
+}
+
+/// `faccessat` with the effective IDs, as the kernel checks them on open.
+fn effective_access(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::faccessat(libc::AT_FDCWD, c_path.as_ptr(), mode, libc::AT_EACCESS) == 0 }
+}
+
+/// Whether the filesystem holding `path` is mounted read-only, like `filesystem::readonly`.
+fn is_read_only_mount(path: &Path) -> bool {
+    let statvfs_flags = |path: &Path| -> Option<u64> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        let ret = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+        (ret == 0).then_some(stat.f_flag)
+    };
+
+    // A dangling symlink can't be followed, its directory is on the same filesystem
+    let flags = statvfs_flags(path).or_else(|| path.parent().and_then(statvfs_flags));
+    flags.is_some_and(|flags| flags & libc::ST_RDONLY != 0)
+}
+
+/// The home trash only takes files it can rename into place, from the same filesystem.
+fn can_trash_to(path: &Path, metadata: &std::fs::Metadata, home_trash: &Path) -> bool {
+    if path.starts_with(home_trash) {
+        return false;
+    }
+
+    // The trash is created on demand, so look at the closest directory that exists
+    let trash_metadata = home_trash
+        .ancestors()
+        .find_map(|ancestor| std::fs::metadata(ancestor).ok());
+    trash_metadata.is_some_and(|trash_metadata| trash_metadata.dev() == metadata.dev())
+}
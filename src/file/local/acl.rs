@@ -0,0 +1,75 @@
+//! POSIX ACLs of local files
+//!
+//! ACLs live in the `system.posix_acl_access` and `system.posix_acl_default`
+//! extended attributes, so they are read and written with the `xattr` helpers.
+//! The kernel does not store an access ACL that the mode bits can express; such
+//! files have no `acl::access` attribute.
+
+use std::path::Path;
+
+use super::xattr;
+use crate::acl::{Acl, AclKind};
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file_info::{FileAttributeMatcher, FileAttributeType, FileInfo};
+
+const ACL_KINDS: [AclKind; 2] = [AclKind::Access, AclKind::Default];
+
+/// The stored ACL of the given kind, `None` if the file has none.
+pub(super) fn read(path: &Path, kind: AclKind, follow_symlinks: bool) -> NpioResult<Option<Acl>> {
+    xattr::get_if_present(path, kind.xattr_name().as_bytes(), follow_symlinks)?
+        .map(|value| Acl::from_xattr(&value))
+        .transpose()
+}
+
+/// Stores `acl` as the ACL of the given kind, or removes that ACL for `None`.
+pub(super) fn write(path: &Path, kind: AclKind, acl: Option<&Acl>, follow_symlinks: bool) -> NpioResult<()> {
+    let name = kind.xattr_name().as_bytes();
+    let Some(acl) = acl else {
+        return xattr::remove(path, name, follow_symlinks);
+    };
+    acl.validate()?;
+
+    // The kernel answers EACCES here, which would suggest a permission problem
+    if kind == AclKind::Default {
+        let metadata = if follow_symlinks { std::fs::metadata(path)? } else { std::fs::symlink_metadata(path)? };
+        if !metadata.is_dir() {
+            return Err(NpioError::new(IOErrorEnum::NotDirectory, "Only directories have a default ACL"));
+        }
+    }
+    xattr::set(path, name, &acl.to_xattr(), follow_symlinks)
+}
+
+/// Adds the `acl::*` attributes selected by `matcher` to `info`.
+pub(super) fn query(path: &Path, metadata: &std::fs::Metadata, matcher: &FileAttributeMatcher, info: &mut FileInfo) {
+    // Symlinks have no ACLs of their own
+    if metadata.file_type().is_symlink() {
+        return;
+    }
+
+    for kind in ACL_KINDS {
+        if !matcher.matches(kind.attribute()) || (kind == AclKind::Default && !metadata.is_dir()) {
+            continue;
+        }
+        // Unreadable ACLs are left out like unreadable xattrs
+        if let Ok(Some(acl)) = read(path, kind, false) {
+            info.set_attribute(kind.attribute(), FileAttributeType::String(acl.to_string()));
+        }
+    }
+}
+
+/// Sets `acl::access` or `acl::default` from its text form; `FileAttributeType::Invalid` removes it.
+pub(super) fn set_attribute(
+    path: &Path,
+    kind: AclKind,
+    value: &FileAttributeType,
+    follow_symlinks: bool,
+) -> NpioResult<()> {
+    match value {
+        FileAttributeType::Invalid => write(path, kind, None, follow_symlinks),
+        FileAttributeType::String(text) => write(path, kind, Some(&Acl::parse(text)?), follow_symlinks),
+        _ => Err(NpioError::new(
+            IOErrorEnum::InvalidArg,
+            format!("{} must be a string", kind.attribute()),
+        )),
+    }
+}
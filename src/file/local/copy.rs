@@ -0,0 +1,781 @@
+//! Kernel-assisted copy paths for local-to-local copies
+//!
+//! `LocalFile::copy` tries, in order:
+//! - `FICLONE` reflink (btrfs, xfs, ...): shares extents, no data is copied
+//! - `copy_file_range`: in-kernel copy, may be offloaded by the filesystem
+//! - `sendfile`: in-kernel copy through the page cache
+//! - a large-buffer read/write loop
+//!
+//! Each method falls through to the next one when the kernel or filesystem
+//! does not support it. Cancellation is checked and progress is reported
+//! between chunks.
+//!
+//! `copy_local_file` additionally handles the `CopyFlags` that affect how the
+//! destination is created: backups, symlinks, sparse files, metadata and
+//! resumable copies (see `resume`).
+
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cancellable::Cancellable;
+use crate::checksum::Checksum;
+use crate::error::{NpioError, NpioResult, IOErrorEnum};
+use crate::job::{CopyFlags, ProgressCallback, VERIFY_CHECKSUM_TYPE};
+
+/// Size of the buffer used by the read/write fallback.
+pub const COPY_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Maximum number of bytes handed to the kernel per `copy_file_range`/`sendfile` call.
+/// Keeps progress reporting and cancellation responsive for large files.
+const KERNEL_COPY_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// A strategy for copying file contents between two local files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CopyMethod {
+    /// `FICLONE` ioctl (copy-on-write clone of the whole file)
+    Reflink,
+    /// `copy_file_range(2)`
+    CopyFileRange,
+    /// `sendfile(2)`
+    Sendfile,
+    /// Plain read/write loop through a user-space buffer
+    ReadWrite,
+}
+
+/// The order in which `LocalFile::copy` tries copy methods.
+pub const DEFAULT_COPY_METHODS: &[CopyMethod] = &[
+    CopyMethod::Reflink,
+    CopyMethod::CopyFileRange,
+    CopyMethod::Sendfile,
+    CopyMethod::ReadWrite,
+];
+
+/// Copies the contents of `source` into `destination`, trying `methods` in order.
+///
+/// Both files are used from their current offsets; `destination` is expected to be empty.
+/// Returns the method that completed the copy.
+pub fn copy_file_contents(
+    source: &mut fs::File,
+    destination: &mut fs::File,
+    methods: &[CopyMethod],
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<CopyMethod> {
+    let total_size = source.metadata()?.len();
+    let mut total_written = 0u64;
+
+    for method in methods {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let completed = match method {
+            CopyMethod::Reflink => {
+                // A clone replaces the whole destination, so it only applies before any data was copied.
+                total_written == 0 && try_reflink(source, destination)
+            }
+            CopyMethod::CopyFileRange | CopyMethod::Sendfile => kernel_copy_loop(
+                *method,
+                source,
+                destination,
+                &mut total_written,
+                total_size,
+                cancellable,
+                progress_callback,
+            )?,
+            CopyMethod::ReadWrite => {
+                read_write_loop(
+                    source,
+                    destination,
+                    &mut total_written,
+                    total_size,
+                    cancellable,
+                    progress_callback,
+                )?;
+                true
+            }
+        };
+
+        if completed {
+            if *method == CopyMethod::Reflink {
+                total_written = total_size;
+                if let Some(cb) = progress_callback {
+                    cb(total_written, total_size);
+                }
+            }
+            return Ok(*method);
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::Unsupported, "No copy method succeeded").into())
+}
+
+/// Copies a local file to a local destination, honouring `OVERWRITE`, `BACKUP`,
+/// `NOFOLLOW_SYMLINKS`, `PRESERVE_SPARSE`, `TARGET_DEFAULT_PERMS`, `ALL_METADATA`,
+/// `RESUMABLE` and `VERIFY`.
+pub fn copy_local_file(
+    source_path: &Path,
+    destination_path: &Path,
+    flags: CopyFlags,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<()> {
+    let source_metadata = if flags.contains(CopyFlags::NOFOLLOW_SYMLINKS) {
+        fs::symlink_metadata(source_path)?
+    } else {
+        fs::metadata(source_path)?
+    };
+
+    if source_metadata.is_dir() {
+        return Err(NpioError::new(IOErrorEnum::IsDirectory, "Cannot copy a directory"));
+    }
+    if !source_metadata.is_file() && !source_metadata.file_type().is_symlink() {
+        return Err(NpioError::new(IOErrorEnum::NotSupported, "Cannot copy special file"));
+    }
+
+    if source_metadata.is_file() && flags.contains(CopyFlags::RESUMABLE) {
+        // Handles backups itself, a partial destination from an earlier attempt must not be moved away
+        super::resume::copy_resumable(
+            source_path,
+            &source_metadata,
+            destination_path,
+            flags,
+            cancellable,
+            progress_callback,
+        )?;
+        if flags.contains(CopyFlags::VERIFY) {
+            verify_local_copy(source_path, destination_path, cancellable)?;
+        }
+        return Ok(());
+    }
+
+    if flags.contains(CopyFlags::OVERWRITE) && flags.contains(CopyFlags::BACKUP) {
+        super::make_backup_sync(destination_path)?;
+    }
+
+    if source_metadata.file_type().is_symlink() {
+        return copy_symlink(source_path, destination_path, &source_metadata, flags);
+    }
+
+    let mut source = fs::File::open(source_path)?;
+    let created = fs::symlink_metadata(destination_path).is_err();
+    let mut destination = open_copy_destination(destination_path, flags)?;
+
+    let result = (|| {
+        if flags.contains(CopyFlags::PRESERVE_SPARSE) && is_sparse(&source_metadata) {
+            copy_sparse_file_contents(&mut source, &mut destination, cancellable, progress_callback)?;
+        } else {
+            copy_file_contents(&mut source, &mut destination, DEFAULT_COPY_METHODS, cancellable, progress_callback)?;
+        }
+
+        copy_file_metadata(&source, &source_metadata, &destination, flags);
+
+        // A copied symlink is not followed, so only regular files have contents to compare
+        if flags.contains(CopyFlags::VERIFY) {
+            verify_local_copy(source_path, destination_path, cancellable)?;
+        }
+        Ok(())
+    })();
+    // An incomplete copy must not pass for the file; one that was overwritten is gone either way
+    if result.is_err() && created {
+        let _ = fs::remove_file(destination_path);
+    }
+    result
+}
+
+/// Checks that `destination_path` has the contents of `source_path`, for `CopyFlags::VERIFY`.
+/// Returns an `IOErrorEnum::ChecksumMismatch` error if they differ.
+fn verify_local_copy(source_path: &Path, destination_path: &Path, cancellable: Option<&Cancellable>) -> NpioResult<()> {
+    let source_digest = file_checksum(&mut fs::File::open(source_path)?, cancellable)?;
+    let mut destination = fs::File::open(destination_path)?;
+    drop_cached_pages(&destination)?;
+    let destination_digest = file_checksum(&mut destination, cancellable)?;
+
+    if source_digest != destination_digest {
+        return Err(NpioError::new(
+            IOErrorEnum::ChecksumMismatch,
+            format!(
+                "Copy of {} differs from source: {} != {}",
+                source_path.display(), destination_digest, source_digest
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Writes `file` to disk and evicts it from the page cache, so reading it back
+/// returns what storage holds rather than the pages a copy just wrote.
+pub(crate) fn drop_cached_pages(file: &fs::File) -> NpioResult<()> {
+    // Dirty pages are not evicted, so they have to be written first
+    file.sync_all()?;
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret).into());
+    }
+    Ok(())
+}
+
+/// Blocking counterpart of `checksum::compute_file_checksum` with the checksum `VERIFY` uses.
+fn file_checksum(file: &mut fs::File, cancellable: Option<&Cancellable>) -> NpioResult<String> {
+    let mut checksum = Checksum::new(VERIFY_CHECKSUM_TYPE);
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    loop {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        checksum.update(&buffer[..n]);
+    }
+    Ok(checksum.finish())
+}
+
+/// Recursively copies `source_path` to `destination_path`, used when a move has to cross filesystems.
+///
+/// Everything is copied with `ALL_METADATA` and without following symlinks. Progress covers
+/// the bytes of the whole tree. Special files are refused before anything is copied, and if
+/// the copy fails, whatever it created is removed again, so the source stays the only copy.
+pub fn copy_local_tree(
+    source_path: &Path,
+    destination_path: &Path,
+    flags: CopyFlags,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<ProgressCallback>,
+) -> NpioResult<()> {
+    let mut tree_copy = TreeCopy {
+        flags: flags | CopyFlags::ALL_METADATA | CopyFlags::NOFOLLOW_SYMLINKS,
+        cancellable,
+        progress_callback: progress_callback.map(Arc::new),
+        total_written: 0,
+        total_size: scan_tree(source_path, cancellable)?,
+        created: Vec::new(),
+    };
+
+    let result = tree_copy.copy_entry(source_path, destination_path);
+    if result.is_err() {
+        // Children come after their directory, so they are removed first
+        for path in tree_copy.created.iter().rev() {
+            let _ = match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+                Ok(_) => fs::remove_file(path),
+                Err(_) => Ok(()),
+            };
+        }
+    }
+    result
+}
+
+/// Sums the sizes of all regular files below `path`, without following symlinks.
+/// Fails on special files, which a tree copy cannot recreate.
+fn scan_tree(path: &Path, cancellable: Option<&Cancellable>) -> NpioResult<u64> {
+    if let Some(c) = cancellable {
+        c.check()?;
+    }
+
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_file() {
+        return Ok(metadata.len());
+    }
+    if metadata.file_type().is_symlink() {
+        return Ok(0);
+    }
+    if !metadata.is_dir() {
+        return Err(NpioError::new(
+            IOErrorEnum::NotSupported,
+            format!("Cannot copy special file {}", path.display()),
+        ));
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += scan_tree(&entry?.path(), cancellable)?;
+    }
+    Ok(size)
+}
+
+/// State of a `copy_local_tree` in progress.
+struct TreeCopy<'a> {
+    flags: CopyFlags,
+    cancellable: Option<&'a Cancellable>,
+    progress_callback: Option<Arc<ProgressCallback>>,
+    total_written: u64,
+    total_size: u64,
+    /// Every destination path made so far, in order.
+    created: Vec<PathBuf>,
+}
+
+impl TreeCopy<'_> {
+    fn copy_entry(&mut self, source_path: &Path, destination_path: &Path) -> NpioResult<()> {
+        if let Some(c) = self.cancellable {
+            c.check()?;
+        }
+
+        // What was there before is not ours to clean up
+        let is_new = fs::symlink_metadata(destination_path).is_err();
+        let metadata = fs::symlink_metadata(source_path)?;
+        if !metadata.is_dir() {
+            // Offsets the per-file progress by what the previous files already copied
+            let file_progress = self.progress_callback.as_ref().map(|outer| {
+                let outer = outer.clone();
+                let base = self.total_written;
+                let total_size = self.total_size;
+                Box::new(move |current, _total| outer(base + current, total_size)) as ProgressCallback
+            });
+            let result =
+                copy_local_file(source_path, destination_path, self.flags, self.cancellable, file_progress.as_ref());
+            if is_new {
+                // Also when the copy failed halfway
+                self.created.push(destination_path.to_path_buf());
+            }
+            result?;
+            if metadata.is_file() {
+                self.total_written += metadata.len();
+            }
+            return Ok(());
+        }
+
+        fs::create_dir(destination_path)?;
+        self.created.push(destination_path.to_path_buf());
+        for entry in fs::read_dir(source_path)? {
+            let entry = entry?;
+            self.copy_entry(&entry.path(), &destination_path.join(entry.file_name()))?;
+        }
+
+        // After the children, so creating them does not bump the directory timestamps
+        copy_directory_metadata(source_path, &metadata, destination_path, self.flags);
+        Ok(())
+    }
+}
+
+/// Path-based counterpart of `copy_file_metadata` for directories.
+fn copy_directory_metadata(
+    source_path: &Path,
+    source_metadata: &fs::Metadata,
+    destination_path: &Path,
+    flags: CopyFlags,
+) {
+    let Ok(c_path) = CString::new(destination_path.as_os_str().as_bytes()) else {
+        return;
+    };
+
+    unsafe {
+        if flags.contains(CopyFlags::ALL_METADATA) {
+            libc::lchown(c_path.as_ptr(), source_metadata.uid(), source_metadata.gid());
+        }
+        if !flags.contains(CopyFlags::TARGET_DEFAULT_PERMS) {
+            libc::chmod(c_path.as_ptr(), source_metadata.mode() & 0o7777);
+        }
+    }
+
+    if flags.contains(CopyFlags::ALL_METADATA) {
+        // Includes the access and default ACLs; the access ACL must come after chmod
+        copy_path_xattrs(source_path, destination_path);
+
+        let times = metadata_timespecs(source_metadata);
+        unsafe {
+            libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), 0);
+        }
+    }
+}
+
+/// Path-based counterpart of `copy_xattrs`, for files that are not opened.
+fn copy_path_xattrs(source_path: &Path, destination_path: &Path) {
+    let Ok(names) = super::xattr::list(source_path, false) else {
+        return;
+    };
+    for name in names {
+        if let Ok(value) = super::xattr::get(source_path, &name, false) {
+            let _ = super::xattr::set(destination_path, &name, &value, false);
+        }
+    }
+}
+
+/// Opens the destination of a local copy, honouring `CopyFlags::OVERWRITE`.
+fn open_copy_destination(path: &Path, flags: CopyFlags) -> NpioResult<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if flags.contains(CopyFlags::OVERWRITE) {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    Ok(options.open(path)?)
+}
+
+/// Recreates the symlink `source_path` at `destination_path` instead of copying its target.
+fn copy_symlink(
+    source_path: &Path,
+    destination_path: &Path,
+    source_metadata: &fs::Metadata,
+    flags: CopyFlags,
+) -> NpioResult<()> {
+    let target = fs::read_link(source_path)?;
+
+    if flags.contains(CopyFlags::OVERWRITE) {
+        match fs::symlink_metadata(destination_path) {
+            Ok(metadata) if metadata.is_dir() => {
+                return Err(NpioError::new(IOErrorEnum::IsDirectory, "Destination is a directory"));
+            }
+            Ok(_) => fs::remove_file(destination_path)?,
+            Err(_) => {}
+        }
+    }
+
+    std::os::unix::fs::symlink(&target, destination_path)?;
+
+    if flags.contains(CopyFlags::ALL_METADATA) {
+        // Failure to copy metadata is not a hard error
+        if let Ok(c_path) = CString::new(destination_path.as_os_str().as_bytes()) {
+            let times = metadata_timespecs(source_metadata);
+            unsafe {
+                libc::lchown(c_path.as_ptr(), source_metadata.uid(), source_metadata.gid());
+                libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if fewer blocks are allocated than the file size needs, i.e. the file has holes.
+pub fn is_sparse(metadata: &fs::Metadata) -> bool {
+    metadata.blocks() * super::STAT_BLOCK_SIZE < metadata.len()
+}
+
+/// Copies only the data segments of `source`, leaving holes in `destination`.
+///
+/// Falls back to `copy_file_contents` when the filesystem does not support `SEEK_DATA`.
+pub fn copy_sparse_file_contents(
+    source: &mut fs::File,
+    destination: &mut fs::File,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<()> {
+    let total_size = source.metadata()?.len();
+
+    // A reflink keeps holes as they are
+    if try_reflink(source, destination) {
+        if let Some(cb) = progress_callback {
+            cb(total_size, total_size);
+        }
+        return Ok(());
+    }
+
+    let source_fd = source.as_raw_fd();
+    let mut offset = 0u64;
+
+    while offset < total_size {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let data_start = unsafe { libc::lseek(source_fd, offset as libc::off_t, libc::SEEK_DATA) };
+        if data_start < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                // No more data after offset: the rest of the file is a hole
+                Some(libc::ENXIO) => break,
+                Some(libc::EINVAL) if offset == 0 => {
+                    copy_file_contents(source, destination, DEFAULT_COPY_METHODS, cancellable, progress_callback)?;
+                    return Ok(());
+                }
+                _ => return Err(err.into()),
+            }
+        }
+
+        let data_end = unsafe { libc::lseek(source_fd, data_start, libc::SEEK_HOLE) };
+        if data_end < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        copy_segment(
+            source,
+            destination,
+            data_start as u64,
+            data_end as u64,
+            total_size,
+            cancellable,
+            progress_callback,
+        )?;
+        offset = data_end as u64;
+    }
+
+    // Extends the destination over a trailing hole
+    destination.set_len(total_size)?;
+    if let Some(cb) = progress_callback {
+        cb(total_size, total_size);
+    }
+    Ok(())
+}
+
+/// Copies the byte range `[start, end)` to the same offsets in `destination`.
+pub(super) fn copy_segment(
+    source: &fs::File,
+    destination: &fs::File,
+    start: u64,
+    end: u64,
+    total_size: u64,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<()> {
+    let mut source_offset = start as libc::loff_t;
+    let mut destination_offset = start as libc::loff_t;
+    let mut use_copy_file_range = true;
+    let mut buffer = Vec::new();
+
+    while (source_offset as u64) < end {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let chunk = ((end - source_offset as u64) as usize).min(KERNEL_COPY_CHUNK_SIZE);
+        let copied = if use_copy_file_range {
+            let ret = unsafe {
+                libc::copy_file_range(
+                    source.as_raw_fd(),
+                    &mut source_offset,
+                    destination.as_raw_fd(),
+                    &mut destination_offset,
+                    chunk,
+                    0,
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                if is_unsupported_error(&err) {
+                    use_copy_file_range = false;
+                    continue;
+                }
+                return Err(err.into());
+            }
+            ret as u64
+        } else {
+            if buffer.is_empty() {
+                buffer = vec![0u8; COPY_BUFFER_SIZE];
+            }
+            let len = chunk.min(buffer.len());
+            let n = source.read_at(&mut buffer[..len], source_offset as u64)?;
+            if n > 0 {
+                destination.write_all_at(&buffer[..n], destination_offset as u64)?;
+            }
+            source_offset += n as libc::loff_t;
+            destination_offset += n as libc::loff_t;
+            n as u64
+        };
+
+        // The source shrank while copying
+        if copied == 0 {
+            break;
+        }
+
+        if let Some(cb) = progress_callback {
+            cb(source_offset as u64, total_size);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies permissions and, with `ALL_METADATA`, ownership, extended attributes and timestamps.
+/// Failure to copy metadata is not a hard error, so problems are ignored.
+pub(super) fn copy_file_metadata(
+    source: &fs::File,
+    source_metadata: &fs::Metadata,
+    destination: &fs::File,
+    flags: CopyFlags,
+) {
+    let destination_fd = destination.as_raw_fd();
+    let all_metadata = flags.contains(CopyFlags::ALL_METADATA);
+
+    // Ownership first: chown clears the setuid/setgid bits
+    if all_metadata {
+        unsafe {
+            libc::fchown(destination_fd, source_metadata.uid(), source_metadata.gid());
+        }
+    }
+
+    if !flags.contains(CopyFlags::TARGET_DEFAULT_PERMS) {
+        unsafe {
+            libc::fchmod(destination_fd, source_metadata.mode() & 0o7777);
+        }
+    }
+
+    if all_metadata {
+        // Includes system.posix_acl_* where present
+        copy_xattrs(source, destination);
+
+        // Timestamps last, after all writes to the destination
+        let times = metadata_timespecs(source_metadata);
+        unsafe {
+            libc::futimens(destination_fd, times.as_ptr());
+        }
+    }
+}
+
+/// Access and modification times of `metadata`, in the order `utimensat` expects.
+fn metadata_timespecs(metadata: &fs::Metadata) -> [libc::timespec; 2] {
+    [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as libc::c_long,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as libc::c_long,
+        },
+    ]
+}
+
+/// Copies every extended attribute that can be read from `source` and written to `destination`.
+fn copy_xattrs(source: &fs::File, destination: &fs::File) {
+    let source_fd = source.as_raw_fd();
+    let destination_fd = destination.as_raw_fd();
+
+    let Ok(names) = super::xattr::read_sized(|buffer, size| unsafe {
+        libc::flistxattr(source_fd, buffer as *mut libc::c_char, size)
+    }) else {
+        return;
+    };
+
+    for name in names.split(|b| *b == 0).filter(|n| !n.is_empty()) {
+        let Ok(c_name) = CString::new(name) else {
+            continue;
+        };
+        let Ok(value) = super::xattr::read_sized(|buffer, size| unsafe {
+            libc::fgetxattr(source_fd, c_name.as_ptr(), buffer, size)
+        }) else {
+            continue;
+        };
+        unsafe {
+            libc::fsetxattr(
+                destination_fd,
+                c_name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            );
+        }
+    }
+}
+
+fn try_reflink(source: &fs::File, destination: &fs::File) -> bool {
+    let ret = unsafe { libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    ret == 0
+}
+
+/// Errors after which a kernel copy method should be abandoned in favour of the next one.
+fn is_unsupported_error(err: &io::Error) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::ENOSYS)
+            | Some(libc::EXDEV)
+            | Some(libc::EINVAL)
+            | Some(libc::EOPNOTSUPP)
+            | Some(libc::EPERM)
+            | Some(libc::EBADF)
+            | Some(libc::ETXTBSY)
+    )
+}
+
+/// Runs `copy_file_range` or `sendfile` until end of file.
+/// Returns `Ok(false)` if the method is not usable for these files.
+fn kernel_copy_loop(
+    method: CopyMethod,
+    source: &fs::File,
+    destination: &fs::File,
+    total_written: &mut u64,
+    total_size: u64,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<bool> {
+    let source_fd = source.as_raw_fd();
+    let destination_fd = destination.as_raw_fd();
+    let mut copied_by_method = 0u64;
+
+    loop {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let ret = unsafe {
+            match method {
+                CopyMethod::CopyFileRange => libc::copy_file_range(
+                    source_fd,
+                    std::ptr::null_mut(),
+                    destination_fd,
+                    std::ptr::null_mut(),
+                    KERNEL_COPY_CHUNK_SIZE,
+                    0,
+                ),
+                _ => libc::sendfile(destination_fd, source_fd, std::ptr::null_mut(), KERNEL_COPY_CHUNK_SIZE),
+            }
+        };
+
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            if copied_by_method == 0 && is_unsupported_error(&err) {
+                return Ok(false);
+            }
+            return Err(err.into());
+        }
+
+        if ret == 0 {
+            // Some pseudo filesystems report a size but return nothing through the kernel
+            // copy paths; let the read/write fallback decide whether this is really EOF.
+            return Ok(copied_by_method > 0 || *total_written >= total_size);
+        }
+
+        copied_by_method += ret as u64;
+        *total_written += ret as u64;
+        if let Some(cb) = progress_callback {
+            cb(*total_written, total_size);
+        }
+    }
+}
+
+fn read_write_loop(
+    source: &mut fs::File,
+    destination: &mut fs::File,
+    total_written: &mut u64,
+    total_size: u64,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<()> {
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+
+    loop {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let n = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        destination.write_all(&buffer[..n])?;
+
+        *total_written += n as u64;
+        if let Some(cb) = progress_callback {
+            cb(*total_written, total_size);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! `filesystem::*` attributes
+//!
+//! Sizes and inode counts come from `statvfs`, the filesystem kind from the
+//! `statfs` magic number, and the source, options and read-only state of the
+//! particular mount from the mount table. A filesystem counts as remote if its
+//! magic number or mount type is that of a network filesystem; for FUSE the
+//! mount type (`fuse.sshfs`, ...) is the only way to tell.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use crate::backend::mount::MountBackend;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file_info::{FileAttributeMatcher, FileAttributeType, FileInfo, FilesystemPreviewType};
+
+/// What a `statfs` magic number says about a filesystem.
+struct FilesystemKind {
+    magic: u32,
+    name: &'static str,
+    remote: bool,
+    /// Kernel interfaces such as procfs, whose files are not worth previewing.
+    virtual_fs: bool,
+}
+
+const fn kind(magic: u32, name: &'static str, remote: bool, virtual_fs: bool) -> FilesystemKind {
+    FilesystemKind { magic, name, remote, virtual_fs }
+}
+
+/// Magic numbers from `linux/magic.h` and the filesystems' own headers.
+const FILESYSTEM_KINDS: &[FilesystemKind] = &[
+    kind(0xEF53, "ext4", false, false),
+    kind(0x9123_683E, "btrfs", false, false),
+    kind(0x5846_5342, "xfs", false, false),
+    kind(0xF2F5_2010, "f2fs", false, false),
+    kind(0x2FC1_2FC1, "zfs", false, false),
+    kind(0x3434, "nilfs2", false, false),
+    kind(0x4D44, "vfat", false, false),
+    kind(0x2011_BAB0, "exfat", false, false),
+    kind(0x5346_544E, "ntfs", false, false),
+    kind(0x9660, "iso9660", false, false),
+    kind(0x1501_3346, "udf", false, false),
+    kind(0x7371_7368, "squashfs", false, false),
+    kind(0x794C_7630, "overlay", false, false),
+    kind(0x0102_1994, "tmpfs", false, false),
+    kind(0x6969, "nfs", true, false),
+    kind(0x517B, "smbfs", true, false),
+    kind(0xFF53_4D42, "cifs", true, false),
+    kind(0xFE53_4D42, "smb3", true, false),
+    kind(0x0102_1997, "9p", true, false),
+    kind(0x00C3_6400, "ceph", true, false),
+    kind(0x5346_414F, "afs", true, false),
+    kind(0x7375_7245, "coda", true, false),
+    kind(0x564C, "ncpfs", true, false),
+    kind(0x0BD0_0BD0, "lustre", true, false),
+    kind(0x4750_4653, "gpfs", true, false),
+    kind(0x6573_5546, "fuse", false, false),
+    kind(0x9FA0, "proc", false, true),
+    kind(0x6265_6572, "sysfs", false, true),
+    kind(0x1CD1, "devpts", false, true),
+    kind(0x2762_7267, "cgroup", false, true),
+    kind(0x6367_7270, "cgroup2", false, true),
+    kind(0x6462_6720, "debugfs", false, true),
+    kind(0x7472_6163, "tracefs", false, true),
+    kind(0x7363_6673, "securityfs", false, true),
+];
+
+/// Mount types of network filesystems, for when the magic number is ambiguous.
+const REMOTE_FILESYSTEM_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "ceph", "afs", "coda", "ncpfs", "davfs", "glusterfs",
+    "lustre", "gpfs", "sshfs", "fuse.sshfs", "fuse.rclone", "fuse.s3fs", "fuse.gcsfuse", "fuse.curlftpfs",
+    "fuse.glusterfs", "fuse.davfs2", "fuse.gvfsd-fuse",
+];
+
+fn filesystem_kind(magic: u32) -> Option<&'static FilesystemKind> {
+    FILESYSTEM_KINDS.iter().find(|kind| kind.magic == magic)
+}
+
+/// Computes the `filesystem::*` attributes selected by `matcher` for the filesystem holding `path`.
+pub(super) fn query(path: &Path, matcher: &FileAttributeMatcher) -> NpioResult<FileInfo> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Invalid path: {}", e)))?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(NpioError::new(
+            IOErrorEnum::Failed,
+            format!("statvfs failed: {}", std::io::Error::last_os_error())
+        ));
+    }
+    let mut stat_fs: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat_fs) } != 0 {
+        return Err(NpioError::new(
+            IOErrorEnum::Failed,
+            format!("statfs failed: {}", std::io::Error::last_os_error())
+        ));
+    }
+    // The magic number is 32 bits wide even where f_type is a signed long
+    let kind = filesystem_kind(stat_fs.f_type as u32);
+
+    let mut info = FileInfo::new();
+    let block_size = stat.f_frsize as u64;
+    let sizes = [
+        ("filesystem::size", stat.f_blocks as u64 * block_size),
+        // Available to non-root
+        ("filesystem::free", stat.f_bavail as u64 * block_size),
+        ("filesystem::used", (stat.f_blocks as u64 - stat.f_bfree as u64) * block_size),
+        ("filesystem::inodes", stat.f_files as u64),
+        ("filesystem::inodes-free", stat.f_favail as u64),
+    ];
+    for (key, value) in sizes {
+        if matcher.matches(key) {
+            info.set_attribute(key, FileAttributeType::Uint64(value));
+        }
+    }
+
+    let metadata = std::fs::metadata(path)?;
+    if matcher.matches("filesystem::id") {
+        info.set_attribute("filesystem::id", FileAttributeType::String(metadata.dev().to_string()));
+    }
+
+    let mount_keys = [
+        "filesystem::type",
+        "filesystem::remote",
+        "filesystem::use-preview",
+        "filesystem::readonly",
+        "filesystem::source",
+        "filesystem::options",
+    ];
+    if !mount_keys.iter().any(|key| matcher.matches(key)) {
+        return Ok(info);
+    }
+
+    // Mount points in the table are canonical paths
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    // Without a mount table the statfs results still answer most questions
+    let mount = MountBackend::mount_entry_for_path(&canonical, metadata.dev()).ok().flatten();
+
+    let filesystem_type = mount
+        .as_ref()
+        .map(|mount| mount.filesystem_type().to_string())
+        .or_else(|| kind.map(|kind| kind.name.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let remote = kind.is_some_and(|kind| kind.remote)
+        || REMOTE_FILESYSTEM_TYPES.contains(&filesystem_type.as_str());
+
+    if matcher.matches("filesystem::type") {
+        info.set_attribute("filesystem::type", FileAttributeType::String(filesystem_type));
+    }
+    if matcher.matches("filesystem::remote") {
+        info.set_attribute("filesystem::remote", FileAttributeType::Boolean(remote));
+    }
+    if matcher.matches("filesystem::use-preview") {
+        let preview = if kind.is_some_and(|kind| kind.virtual_fs) {
+            FilesystemPreviewType::Never
+        } else if remote {
+            FilesystemPreviewType::IfAlways
+        } else {
+            FilesystemPreviewType::IfLocal
+        };
+        info.set_attribute("filesystem::use-preview", FileAttributeType::Uint32(preview as u32));
+    }
+    if matcher.matches("filesystem::readonly") {
+        // Either the mount or the superblock being read-only is enough
+        let readonly = (stat.f_flag & libc::ST_RDONLY) != 0 || mount.as_ref().is_some_and(|mount| mount.is_read_only());
+        info.set_attribute("filesystem::readonly", FileAttributeType::Boolean(readonly));
+    }
+    if let Some(mount) = &mount {
+        if matcher.matches("filesystem::source") {
+            info.set_attribute("filesystem::source", FileAttributeType::String(mount.source().to_string()));
+        }
+        if matcher.matches("filesystem::options") {
+            info.set_attribute("filesystem::options", FileAttributeType::String(mount.options()));
+        }
+    }
+
+    Ok(info)
+}
@@ -0,0 +1,611 @@
+//! `metadata::*` attributes
+//!
+//! Metadata is kept outside the files, in a database under
+//! `$XDG_DATA_HOME/npio/metadata` with one tree per filesystem. A tree is named
+//! after the filesystem UUID where there is one, so removable media keep their
+//! metadata wherever they get mounted, and is keyed by the path of the file
+//! within the filesystem.
+//!
+//! Each tree is a compacted snapshot (`<tree>.db`) plus a journal of the changes
+//! made since (`<tree>.log`). Writers append to the journal while holding an
+//! exclusive `flock` on `<tree>.lock` and fold it into a new snapshot once it
+//! grows past [`JOURNAL_COMPACT_SIZE`]; readers hold a shared lock and replay the
+//! journal onto the snapshot, so other processes only ever see whole changes.
+//! Every journal starts with a generation number, and a snapshot records the
+//! last generation it contains, so a compaction that was interrupted between
+//! replacing the snapshot and starting a new journal is never replayed twice.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::mount::{MountBackend, MountEntry};
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file_info::{FileAttributeMatcher, FileAttributeType, FileInfo};
+
+const METADATA_PREFIX: &str = "metadata::";
+
+/// Directory of the database, relative to `$XDG_DATA_HOME`.
+const METADATA_DIR: &str = "npio/metadata";
+
+const SNAPSHOT_EXTENSION: &str = "db";
+const JOURNAL_EXTENSION: &str = "log";
+const LOCK_EXTENSION: &str = "lock";
+/// Suffix of a snapshot or journal that is being written.
+const TEMPORARY_SUFFIX: &str = ".tmp";
+
+/// Version of the snapshot format.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Journal size past which a writer folds the journal into the snapshot.
+const JOURNAL_COMPACT_SIZE: u64 = 64 * 1024;
+
+/// Tree of the root filesystem when it has no UUID, and of files whose mount is unknown.
+const ROOT_TREE: &str = "root";
+
+/// Symlinks from filesystem UUIDs to their block devices, maintained by udev.
+const UUID_DIR: &str = "/dev/disk/by-uuid";
+
+/// A stored value; GIO keeps strings and string lists only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum MetadataValue {
+    String(String),
+    Stringv(Vec<String>),
+}
+
+impl MetadataValue {
+    fn from_attribute(value: &FileAttributeType) -> Option<Self> {
+        match value {
+            FileAttributeType::String(value) => Some(MetadataValue::String(value.clone())),
+            FileAttributeType::Stringv(values) => Some(MetadataValue::Stringv(values.clone())),
+            _ => None,
+        }
+    }
+
+    fn to_attribute(&self) -> FileAttributeType {
+        match self {
+            MetadataValue::String(value) => FileAttributeType::String(value.clone()),
+            MetadataValue::Stringv(values) => FileAttributeType::Stringv(values.clone()),
+        }
+    }
+}
+
+/// Metadata of one file, by key without the `metadata::` prefix.
+type FileMetadata = BTreeMap<String, MetadataValue>;
+
+/// Metadata of every file in a tree, by path within the filesystem.
+type Files = BTreeMap<String, FileMetadata>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    /// The last journal generation folded into this snapshot.
+    generation: u64,
+    files: Files,
+}
+
+/// One line of a journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum JournalEntry {
+    /// The first line of every journal.
+    Start { generation: u64 },
+    Set { path: String, key: String, value: MetadataValue },
+    Unset { path: String, key: String },
+    /// Drops a file and everything below it.
+    Remove { path: String },
+    /// Renames a file and everything below it, replacing whatever was at `destination`.
+    Move { source: String, destination: String },
+}
+
+/// Trees as this process last read them, by their path without extension.
+static TREES: Lazy<Mutex<HashMap<PathBuf, Tree>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Filesystem UUIDs by block device, `None` for devices without one.
+static FILESYSTEM_UUIDS: Lazy<Mutex<HashMap<String, Option<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Tells whether a snapshot was replaced since it was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    inode: u64,
+    modified: (i64, i64),
+    len: u64,
+}
+
+impl FileIdentity {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            inode: metadata.ino(),
+            modified: (metadata.mtime(), metadata.mtime_nsec()),
+            len: metadata.len(),
+        }
+    }
+}
+
+/// The contents of a tree and how much of its files they cover.
+struct Tree {
+    path: PathBuf,
+    files: Files,
+    snapshot: Option<FileIdentity>,
+    /// The last journal generation contained in the snapshot.
+    generation: u64,
+    journal_inode: Option<u64>,
+    /// Generation of the journal, `None` until its first line was read.
+    journal_generation: Option<u64>,
+    /// Bytes of the journal already applied, always ending at a line break.
+    journal_offset: u64,
+}
+
+impl Tree {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            files: Files::new(),
+            snapshot: None,
+            generation: 0,
+            journal_inode: None,
+            journal_generation: None,
+            journal_offset: 0,
+        }
+    }
+
+    fn file(&self, extension: &str) -> PathBuf {
+        tree_file(&self.path, extension)
+    }
+
+    /// Whether the journal holds changes the snapshot does not have.
+    fn journal_is_current(&self) -> bool {
+        self.journal_generation.is_some_and(|generation| generation > self.generation)
+    }
+
+    /// Catches up with changes made by other processes. Needs the lock.
+    fn refresh(&mut self) -> NpioResult<()> {
+        let snapshot = match std::fs::metadata(self.file(SNAPSHOT_EXTENSION)) {
+            Ok(metadata) => Some(FileIdentity::of(&metadata)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let journal_path = self.file(JOURNAL_EXTENSION);
+        let mut journal = match File::open(&journal_path) {
+            Ok(journal) => Some(journal),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let journal_metadata = journal.as_ref().map(|journal| journal.metadata()).transpose()?;
+        let journal_inode = journal_metadata.as_ref().map(|metadata| metadata.ino());
+        let journal_len = journal_metadata.as_ref().map_or(0, |metadata| metadata.len());
+
+        if snapshot != self.snapshot || journal_inode != self.journal_inode || journal_len < self.journal_offset {
+            let contents = self.read_snapshot()?;
+            self.files = contents.files;
+            self.generation = contents.generation;
+            self.snapshot = snapshot;
+            self.journal_inode = journal_inode;
+            self.journal_generation = None;
+            self.journal_offset = 0;
+        }
+
+        let Some(journal) = journal.as_mut() else {
+            return Ok(());
+        };
+        if journal_len == self.journal_offset {
+            return Ok(());
+        }
+        journal.seek(SeekFrom::Start(self.journal_offset))?;
+        let mut tail = Vec::new();
+        journal.read_to_end(&mut tail)?;
+        // A line without its line break is a write that did not finish
+        let complete = tail.iter().rposition(|&byte| byte == b'\n').map_or(0, |end| end + 1);
+        for line in tail[..complete].split(|&byte| byte == b'\n').filter(|line| !line.is_empty()) {
+            match serde_json::from_slice::<JournalEntry>(line) {
+                Ok(JournalEntry::Start { generation }) => self.journal_generation = Some(generation),
+                Ok(entry) => {
+                    if self.journal_is_current() {
+                        apply(&mut self.files, entry);
+                    }
+                }
+                Err(e) => eprintln!("Ignoring corrupt entry in {}: {}", journal_path.display(), e),
+            }
+        }
+        self.journal_offset += complete as u64;
+        Ok(())
+    }
+
+    fn read_snapshot(&self) -> NpioResult<Snapshot> {
+        let file = match File::open(self.file(SNAPSHOT_EXTENSION)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Snapshot::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot: Snapshot = serde_json::from_reader(BufReader::new(file)).map_err(json_error)?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(NpioError::new(
+                IOErrorEnum::NotSupported,
+                format!("Metadata database version {} is newer than {}", snapshot.version, SNAPSHOT_VERSION),
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    /// Appends `entries` to the journal and applies them. Needs the exclusive lock.
+    fn append(&mut self, entries: Vec<JournalEntry>) -> NpioResult<()> {
+        if !self.journal_is_current() {
+            self.start_journal()?;
+        }
+        let mut buffer = Vec::new();
+        for entry in &entries {
+            serde_json::to_writer(&mut buffer, entry).map_err(json_error)?;
+            buffer.push(b'\n');
+        }
+
+        let mut journal = OpenOptions::new().write(true).open(self.file(JOURNAL_EXTENSION))?;
+        // Drops what is left of an interrupted write
+        journal.set_len(self.journal_offset)?;
+        journal.seek(SeekFrom::Start(self.journal_offset))?;
+        journal.write_all(&buffer)?;
+        self.journal_offset += buffer.len() as u64;
+        for entry in entries {
+            apply(&mut self.files, entry);
+        }
+
+        if self.journal_offset > JOURNAL_COMPACT_SIZE {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the journal by an empty one following the snapshot.
+    fn start_journal(&mut self) -> NpioResult<()> {
+        let generation = self.generation + 1;
+        let mut line = serde_json::to_vec(&JournalEntry::Start { generation }).map_err(json_error)?;
+        line.push(b'\n');
+
+        let journal_path = self.file(JOURNAL_EXTENSION);
+        let temporary = temporary_file(&journal_path);
+        std::fs::write(&temporary, &line)?;
+        std::fs::rename(&temporary, &journal_path)?;
+        self.journal_inode = Some(std::fs::metadata(&journal_path)?.ino());
+        self.journal_generation = Some(generation);
+        self.journal_offset = line.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the current contents as the snapshot and starts a new journal.
+    fn compact(&mut self) -> NpioResult<()> {
+        let generation = self.journal_generation.unwrap_or(self.generation);
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            generation,
+            files: std::mem::take(&mut self.files),
+        };
+        let written = self.write_snapshot(&snapshot);
+        self.files = snapshot.files;
+        written?;
+
+        self.generation = generation;
+        self.snapshot = Some(FileIdentity::of(&std::fs::metadata(self.file(SNAPSHOT_EXTENSION))?));
+        self.start_journal()
+    }
+
+    fn write_snapshot(&self, snapshot: &Snapshot) -> NpioResult<()> {
+        let snapshot_path = self.file(SNAPSHOT_EXTENSION);
+        let temporary = temporary_file(&snapshot_path);
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer(&mut writer, snapshot).map_err(json_error)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        // The snapshot must be complete before it replaces the old one
+        file.sync_all()?;
+        std::fs::rename(&temporary, &snapshot_path)?;
+        Ok(())
+    }
+}
+
+fn json_error(e: serde_json::Error) -> NpioError {
+    NpioError::new(IOErrorEnum::InvalidData, format!("Invalid metadata database: {}", e))
+}
+
+fn tree_file(tree: &Path, extension: &str) -> PathBuf {
+    let mut name = tree.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+fn temporary_file(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(TEMPORARY_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Paths in `files` at or below `path`.
+fn subtree_paths(files: &Files, path: &str) -> Vec<String> {
+    let prefix = if path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+    let mut paths: Vec<String> = files
+        .range(prefix.clone()..)
+        .map(|(path, _)| path)
+        .take_while(|path| path.starts_with(&prefix))
+        .cloned()
+        .collect();
+    if path != prefix && files.contains_key(path) {
+        paths.push(path.to_string());
+    }
+    paths
+}
+
+fn apply(files: &mut Files, entry: JournalEntry) {
+    match entry {
+        JournalEntry::Start { .. } => {}
+        JournalEntry::Set { path, key, value } => {
+            files.entry(path).or_default().insert(key, value);
+        }
+        JournalEntry::Unset { path, key } => {
+            if let Some(metadata) = files.get_mut(&path) {
+                metadata.remove(&key);
+                if metadata.is_empty() {
+                    files.remove(&path);
+                }
+            }
+        }
+        JournalEntry::Remove { path } => {
+            for path in subtree_paths(files, &path) {
+                files.remove(&path);
+            }
+        }
+        JournalEntry::Move { source, destination } => {
+            let moved: Vec<(String, FileMetadata)> = subtree_paths(files, &source)
+                .into_iter()
+                .filter_map(|path| files.remove_entry(&path))
+                .collect();
+            for path in subtree_paths(files, &destination) {
+                files.remove(&path);
+            }
+            for (path, metadata) in moved {
+                files.insert(format!("{}{}", destination, &path[source.len()..]), metadata);
+            }
+        }
+    }
+}
+
+/// Whether `entry` would change `files`.
+fn changes(files: &Files, entry: &JournalEntry) -> bool {
+    match entry {
+        JournalEntry::Start { .. } => false,
+        JournalEntry::Set { path, key, value } => files.get(path).and_then(|metadata| metadata.get(key)) != Some(value),
+        JournalEntry::Unset { path, key } => files.get(path).is_some_and(|metadata| metadata.contains_key(key)),
+        JournalEntry::Remove { path } => !subtree_paths(files, path).is_empty(),
+        JournalEntry::Move { source, destination } => {
+            !subtree_paths(files, source).is_empty() || !subtree_paths(files, destination).is_empty()
+        }
+    }
+}
+
+/// Holds a `flock` on a tree until dropped.
+struct TreeLock {
+    _file: File,
+}
+
+fn lock_tree(tree: &Path, exclusive: bool) -> NpioResult<TreeLock> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(tree_file(tree, LOCK_EXTENSION))?;
+    let operation = if exclusive { libc::LOCK_EX } else { libc::LOCK_SH };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(TreeLock { _file: file });
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error.into());
+        }
+    }
+}
+
+/// Runs `f` on the current contents of `tree` while holding its lock.
+fn with_tree<T>(tree_path: &Path, exclusive: bool, f: impl FnOnce(&mut Tree) -> NpioResult<T>) -> NpioResult<T> {
+    let mut trees = match TREES.lock() {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Failed to acquire lock on metadata trees: {}", e);
+            e.into_inner()
+        }
+    };
+    if exclusive {
+        if let Some(parent) = tree_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let _lock = lock_tree(tree_path, exclusive)?;
+    let tree = trees.entry(tree_path.to_path_buf()).or_insert_with(|| Tree::new(tree_path));
+    tree.refresh()?;
+    f(tree)
+}
+
+/// Writes `entries` to `tree` as one change, unless none of them changes anything.
+fn update(tree_path: &Path, entries: Vec<JournalEntry>) -> NpioResult<()> {
+    // Removing and moving never need a tree that does not exist yet
+    let creates = entries.iter().any(|entry| matches!(entry, JournalEntry::Set { .. }));
+    if !creates && !tree_file(tree_path, LOCK_EXTENSION).exists() {
+        return Ok(());
+    }
+    with_tree(tree_path, true, |tree| {
+        if !entries.iter().any(|entry| changes(&tree.files, entry)) {
+            return Ok(());
+        }
+        tree.append(entries)
+    })
+}
+
+/// Where the metadata of a file is kept.
+struct Location {
+    /// The tree, without extension.
+    tree: PathBuf,
+    /// The path of the file within its filesystem.
+    key: String,
+}
+
+fn locate(path: &Path) -> NpioResult<Location> {
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { std::env::current_dir()?.join(path) };
+    // The file itself may be a symlink or already gone, so only its directory is resolved
+    let canonical = match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize()?.join(name),
+        _ => absolute.canonicalize()?,
+    };
+    let device = match std::fs::symlink_metadata(&canonical) {
+        Ok(metadata) => metadata.dev(),
+        Err(_) => std::fs::metadata(canonical.parent().unwrap_or(&canonical))?.dev(),
+    };
+
+    let mount = MountBackend::mount_entry_for_path(&canonical, device).ok().flatten();
+    let within_mount = mount
+        .as_ref()
+        .and_then(|mount| Some((mount, canonical.strip_prefix(mount.mount_point()).ok()?)));
+    let (tree_name, key) = match within_mount {
+        Some((mount, relative)) if relative.as_os_str().is_empty() => (tree_name(mount), mount.root().to_path_buf()),
+        Some((mount, relative)) => (tree_name(mount), mount.root().join(relative)),
+        None => (ROOT_TREE.to_string(), canonical.clone()),
+    };
+    let key = key
+        .into_os_string()
+        .into_string()
+        .map_err(|_| NpioError::new(IOErrorEnum::InvalidFilename, "Metadata is only kept for UTF-8 paths"))?;
+
+    Ok(Location {
+        tree: super::data_home()?.join(METADATA_DIR).join(tree_name),
+        key,
+    })
+}
+
+fn tree_name(mount: &MountEntry) -> String {
+    if let Some(uuid) = filesystem_uuid(mount.source()) {
+        return format!("uuid-{}", uuid);
+    }
+    if mount.mount_point() == Path::new("/") {
+        return ROOT_TREE.to_string();
+    }
+    format!("mount-{}", urlencoding::encode(&mount.mount_point().to_string_lossy()))
+}
+
+fn filesystem_uuid(source: &str) -> Option<String> {
+    if !source.starts_with("/dev/") {
+        return None;
+    }
+    let mut uuids = match FILESYSTEM_UUIDS.lock() {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Failed to acquire lock on filesystem UUIDs: {}", e);
+            e.into_inner()
+        }
+    };
+    uuids
+        .entry(source.to_string())
+        .or_insert_with(|| {
+            let device = std::fs::canonicalize(source).ok()?;
+            std::fs::read_dir(UUID_DIR)
+                .ok()?
+                .flatten()
+                .find(|entry| std::fs::canonicalize(entry.path()).is_ok_and(|target| target == device))
+                .and_then(|entry| entry.file_name().into_string().ok())
+        })
+        .clone()
+}
+
+/// All stored metadata of `path`.
+fn read(path: &Path) -> NpioResult<FileMetadata> {
+    let location = locate(path)?;
+    if !tree_file(&location.tree, LOCK_EXTENSION).exists() {
+        return Ok(FileMetadata::new());
+    }
+    with_tree(&location.tree, false, |tree| Ok(tree.files.get(&location.key).cloned().unwrap_or_default()))
+}
+
+/// Adds the `metadata::*` attributes selected by `matcher` to `info`.
+pub(super) fn query(path: &Path, matcher: &FileAttributeMatcher, info: &mut FileInfo) {
+    // An unreadable database is left out like unreadable xattrs
+    let Ok(metadata) = read(path) else {
+        return;
+    };
+    for (key, value) in metadata {
+        let attribute = format!("{}{}", METADATA_PREFIX, key);
+        if matcher.matches(&attribute) {
+            info.set_attribute(&attribute, value.to_attribute());
+        }
+    }
+}
+
+/// Reads one `metadata::*` attribute.
+pub(super) fn get_attribute(path: &Path, attribute: &str) -> NpioResult<Option<FileAttributeType>> {
+    let Some(key) = attribute.strip_prefix(METADATA_PREFIX) else {
+        return Ok(None);
+    };
+    Ok(read(path)?.get(key).map(MetadataValue::to_attribute))
+}
+
+/// Stores a `metadata::*` attribute; `FileAttributeType::Invalid` removes it.
+pub(super) fn set_attribute(path: &Path, attribute: &str, value: &FileAttributeType) -> NpioResult<()> {
+    let key = attribute
+        .strip_prefix(METADATA_PREFIX)
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid metadata attribute: {}", attribute)))?;
+    // Metadata belongs to a file, so there has to be one
+    std::fs::symlink_metadata(path)?;
+
+    let location = locate(path)?;
+    let entry = match value {
+        FileAttributeType::Invalid => JournalEntry::Unset { path: location.key, key: key.to_string() },
+        value => JournalEntry::Set {
+            path: location.key,
+            key: key.to_string(),
+            value: MetadataValue::from_attribute(value).ok_or_else(|| {
+                NpioError::new(IOErrorEnum::InvalidArg, format!("{} must be a string or a string list", attribute))
+            })?,
+        },
+    };
+    update(&location.tree, vec![entry])
+}
+
+/// Carries the metadata of `source` and everything below it over to `destination`,
+/// once the file was moved there.
+pub(super) fn move_entries(source: &Path, destination: &Path) -> NpioResult<()> {
+    let source = locate(source)?;
+    let destination = locate(destination)?;
+    if source.tree == destination.tree {
+        return update(&source.tree, vec![JournalEntry::Move { source: source.key, destination: destination.key }]);
+    }
+
+    if !tree_file(&source.tree, LOCK_EXTENSION).exists() {
+        return Ok(());
+    }
+    let moved = with_tree(&source.tree, false, |tree| {
+        Ok(subtree_paths(&tree.files, &source.key)
+            .into_iter()
+            .filter_map(|path| {
+                let metadata = tree.files.get(&path)?.clone();
+                Some((path[source.key.len()..].to_string(), metadata))
+            })
+            .collect::<Vec<_>>())
+    })?;
+    let mut entries = vec![JournalEntry::Remove { path: destination.key.clone() }];
+    for (suffix, metadata) in moved {
+        let path = format!("{}{}", destination.key, suffix);
+        entries.extend(metadata.into_iter().map(|(key, value)| JournalEntry::Set { path: path.clone(), key, value }));
+    }
+    update(&destination.tree, entries)?;
+    update(&source.tree, vec![JournalEntry::Remove { path: source.key }])
+}
+
+/// Forgets the metadata of `path` and everything below it, once the file was deleted.
+pub(super) fn remove_entries(path: &Path) -> NpioResult<()> {
+    let location = locate(path)?;
+    update(&location.tree, vec![JournalEntry::Remove { path: location.key }])
+}
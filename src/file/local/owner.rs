@@ -0,0 +1,115 @@
+//! User and group names for `owner::*` attributes
+//!
+//! Lookups go through the passwd and group databases (`getpwuid_r` and
+//! `getgrgid_r`), which may involve NSS modules talking to the network, so
+//! results are cached for the lifetime of the process like GIO does.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+/// Initial size of the buffer for passwd/group entries when sysconf has no hint.
+const DEFAULT_ENTRY_BUFFER_SIZE: usize = 1024;
+
+/// Upper bound for the entry buffer, entries larger than this are treated as missing.
+const MAX_ENTRY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Names of a user as reported by `owner::user` and `owner::user-real`.
+#[derive(Debug, Clone)]
+pub(super) struct UserNames {
+    pub name: String,
+    /// The full name from the GECOS field, falling back to the login name.
+    pub real_name: String,
+}
+
+static USER_CACHE: Lazy<Mutex<HashMap<u32, Option<UserNames>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static GROUP_CACHE: Lazy<Mutex<HashMap<u32, Option<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Looks up the user with the given uid.
+pub(super) fn user_names(uid: u32) -> Option<UserNames> {
+    let mut cache = match USER_CACHE.lock() {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Failed to acquire lock on user name cache: {}", e);
+            e.into_inner()
+        }
+    };
+    cache.entry(uid).or_insert_with(|| lookup_user(uid)).clone()
+}
+
+/// Looks up the name of the group with the given gid.
+pub(super) fn group_name(gid: u32) -> Option<String> {
+    let mut cache = match GROUP_CACHE.lock() {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Failed to acquire lock on group name cache: {}", e);
+            e.into_inner()
+        }
+    };
+    cache.entry(gid).or_insert_with(|| lookup_group(gid)).clone()
+}
+
+fn initial_buffer_size(name: libc::c_int) -> usize {
+    let size = unsafe { libc::sysconf(name) };
+    if size > 0 {
+        size as usize
+    } else {
+        DEFAULT_ENTRY_BUFFER_SIZE
+    }
+}
+
+fn lookup_user(uid: u32) -> Option<UserNames> {
+    let mut buffer = vec![0 as libc::c_char; initial_buffer_size(libc::_SC_GETPW_R_SIZE_MAX)];
+    loop {
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let ret = unsafe {
+            libc::getpwuid_r(uid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result)
+        };
+
+        if ret == libc::ERANGE && buffer.len() < MAX_ENTRY_BUFFER_SIZE {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if ret != 0 || result.is_null() || entry.pw_name.is_null() {
+            return None;
+        }
+
+        let name = unsafe { CStr::from_ptr(entry.pw_name) }.to_string_lossy().to_string();
+        // GECOS is "Full Name,Room,Work phone,Home phone"
+        let real_name = if entry.pw_gecos.is_null() {
+            None
+        } else {
+            let gecos = unsafe { CStr::from_ptr(entry.pw_gecos) }.to_string_lossy().to_string();
+            gecos.split(',').next().filter(|full_name| !full_name.is_empty()).map(str::to_string)
+        };
+
+        return Some(UserNames {
+            real_name: real_name.unwrap_or_else(|| name.clone()),
+            name,
+        });
+    }
+}
+
+fn lookup_group(gid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; initial_buffer_size(libc::_SC_GETGR_R_SIZE_MAX)];
+    loop {
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        let ret = unsafe {
+            libc::getgrgid_r(gid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result)
+        };
+
+        if ret == libc::ERANGE && buffer.len() < MAX_ENTRY_BUFFER_SIZE {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if ret != 0 || result.is_null() || entry.gr_name.is_null() {
+            return None;
+        }
+
+        return Some(unsafe { CStr::from_ptr(entry.gr_name) }.to_string_lossy().to_string());
+    }
+}
@@ -0,0 +1,214 @@
+//! Resumable local copies
+//!
+//! With `CopyFlags::RESUMABLE`, `LocalFile::copy` keeps a small journal next
+//! to the destination (`.<name>.npio-journal`) recording the source, its etag
+//! and size, and how many bytes of the destination are known to be on disk.
+//! An interrupted or cancelled copy leaves the journal behind; the next copy
+//! to the same destination continues from the committed offset, provided the
+//! source has not changed in the meantime and the destination is still a
+//! regular file holding at least the committed bytes. The journal is removed
+//! once the copy completes.
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::cancellable::Cancellable;
+use crate::error::{NpioError, NpioResult, IOErrorEnum};
+use crate::job::{CopyFlags, ProgressCallback};
+
+/// Bytes copied between two journal commits.
+const JOURNAL_COMMIT_INTERVAL: u64 = 16 * 1024 * 1024;
+
+/// Journal format version, bumped on incompatible changes.
+const JOURNAL_VERSION: u32 = 1;
+
+const JOURNAL_HEADER: &str = "[npio Copy Journal]";
+
+/// Progress of a resumable copy, as stored in its journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyJournal {
+    pub source_uri: String,
+    pub source_etag: String,
+    pub source_size: u64,
+    pub committed: u64,
+}
+
+impl CopyJournal {
+    /// Path of the journal belonging to `destination_path`.
+    pub fn path_for(destination_path: &Path) -> PathBuf {
+        let name = destination_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        destination_path.with_file_name(format!(".{}.npio-journal", name))
+    }
+
+    /// Reads a journal, returning `None` if it is missing or unreadable.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut lines = content.lines();
+        if lines.next()? != JOURNAL_HEADER {
+            return None;
+        }
+
+        let mut version = None;
+        let mut source_uri = None;
+        let mut source_etag = None;
+        let mut source_size = None;
+        let mut committed = None;
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key {
+                "Version" => version = value.parse::<u32>().ok(),
+                "Source" => source_uri = Some(value.to_string()),
+                "Etag" => source_etag = Some(value.to_string()),
+                "Size" => source_size = value.parse::<u64>().ok(),
+                "Committed" => committed = value.parse::<u64>().ok(),
+                _ => {}
+            }
+        }
+
+        if version? != JOURNAL_VERSION {
+            return None;
+        }
+        Some(Self {
+            source_uri: source_uri?,
+            source_etag: source_etag?,
+            source_size: source_size?,
+            committed: committed?,
+        })
+    }
+
+    /// Writes the journal atomically, so an interruption never leaves a torn journal behind.
+    pub fn store(&self, path: &Path) -> io::Result<()> {
+        let content = format!(
+            "{}\nVersion={}\nSource={}\nEtag={}\nSize={}\nCommitted={}\n",
+            JOURNAL_HEADER,
+            JOURNAL_VERSION,
+            self.source_uri,
+            self.source_etag,
+            self.source_size,
+            self.committed
+        );
+
+        let mut temp_name = path.as_os_str().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+
+        let file = fs::File::create(&temp_path)?;
+        io::Write::write_all(&mut &file, content.as_bytes())?;
+        file.sync_data()?;
+        fs::rename(&temp_path, path)
+    }
+}
+
+/// Copies `source_path` to `destination_path`, resuming a previous attempt if its journal is still valid.
+pub(super) fn copy_resumable(
+    source_path: &Path,
+    source_metadata: &fs::Metadata,
+    destination_path: &Path,
+    flags: CopyFlags,
+    cancellable: Option<&Cancellable>,
+    progress_callback: Option<&ProgressCallback>,
+) -> NpioResult<()> {
+    let journal_path = CopyJournal::path_for(destination_path);
+    let mut journal = CopyJournal {
+        source_uri: format!("file://{}", source_path.to_string_lossy()),
+        source_etag: super::etag_for_metadata(source_metadata),
+        source_size: source_metadata.len(),
+        committed: 0,
+    };
+
+    let destination_metadata = fs::symlink_metadata(destination_path).ok();
+    let destination_exists = destination_metadata.is_some();
+    // A symlink is replaced rather than written through
+    let destination_is_symlink = destination_metadata.as_ref().is_some_and(|m| m.file_type().is_symlink());
+    let destination_len = destination_metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len());
+    let previous = CopyJournal::load(&journal_path);
+
+    // Only continue if the source is still exactly what the previous attempt was copying, and
+    // the destination still holds everything committed; extending a shorter one would leave a hole
+    let resumable = previous.as_ref().filter(|previous| {
+        destination_len.is_some_and(|len| len >= previous.committed)
+            && previous.source_uri == journal.source_uri
+            && previous.source_etag == journal.source_etag
+            && previous.source_size == journal.source_size
+    });
+    let resume_from = resumable.map_or(0, |previous| previous.committed.min(journal.source_size));
+
+    // Unless it is resumed, the existing destination is replaced, which needs OVERWRITE
+    if destination_exists && resumable.is_none() {
+        if !flags.contains(CopyFlags::OVERWRITE) {
+            let message = match previous {
+                Some(_) => "Destination exists and its journal no longer matches it or the source",
+                None => "Destination exists",
+            };
+            return Err(NpioError::new(IOErrorEnum::Exists, message));
+        }
+        // Without a journal the destination belongs to someone else
+        if previous.is_none() && flags.contains(CopyFlags::BACKUP) {
+            super::make_backup_sync(destination_path)?;
+        }
+        if destination_is_symlink {
+            match fs::remove_file(destination_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    let source = fs::File::open(source_path)?;
+    let destination = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(destination_path)?;
+
+    // Anything past the committed offset may not have reached the disk
+    destination.set_len(resume_from)?;
+    journal.committed = resume_from;
+    journal.store(&journal_path)?;
+
+    let total_size = journal.source_size;
+    while journal.committed < total_size {
+        let end = (journal.committed + JOURNAL_COMMIT_INTERVAL).min(total_size);
+        super::copy::copy_segment(
+            &source,
+            &destination,
+            journal.committed,
+            end,
+            total_size,
+            cancellable,
+            progress_callback,
+        )?;
+
+        let written = destination.metadata()?.len();
+        if written < end {
+            return Err(NpioError::new(IOErrorEnum::Failed, "Source changed while copying"));
+        }
+
+        let ret = unsafe { libc::fdatasync(destination.as_raw_fd()) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        journal.committed = end;
+        journal.store(&journal_path)?;
+    }
+
+    if total_size == 0 {
+        if let Some(cb) = progress_callback {
+            cb(0, 0);
+        }
+    }
+
+    super::copy::copy_file_metadata(&source, source_metadata, &destination, flags);
+    fs::remove_file(&journal_path)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! `tags::*` attributes of local files
+//!
+//! Tags and the comment live in the `user.xdg.tags` and `user.xdg.comment`
+//! extended attributes. On filesystems without xattrs they are kept in the
+//! metadata database instead, as `metadata::xdg-tags` and `metadata::xdg-comment`.
+
+use std::path::Path;
+
+use super::{metadata, xattr};
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file_info::{FileAttributeMatcher, FileAttributeType, FileInfo};
+use crate::tags::{TagColor, Tags};
+
+const TAGS_XATTR: &[u8] = b"user.xdg.tags";
+const COMMENT_XATTR: &[u8] = b"user.xdg.comment";
+const TAGS_METADATA: &str = "metadata::xdg-tags";
+const COMMENT_METADATA: &str = "metadata::xdg-comment";
+
+const TAGS_ATTRIBUTE: &str = "tags::list";
+const COMMENT_ATTRIBUTE: &str = "tags::comment";
+const COLOR_LABEL_ATTRIBUTE: &str = "tags::color-label";
+
+fn read_tags(path: &Path, follow_symlinks: bool) -> NpioResult<Tags> {
+    if !xattr::is_supported(path) {
+        return Ok(match metadata::get_attribute(path, TAGS_METADATA)? {
+            Some(FileAttributeType::Stringv(tags)) => Tags::from_list(tags).unwrap_or_default(),
+            _ => Tags::new(),
+        });
+    }
+    Ok(xattr::get_if_present(path, TAGS_XATTR, follow_symlinks)?
+        .map(|value| Tags::parse(&String::from_utf8_lossy(&value)))
+        .unwrap_or_default())
+}
+
+/// Stores `tags`; an empty set removes the attribute.
+fn write_tags(path: &Path, tags: &Tags, follow_symlinks: bool) -> NpioResult<()> {
+    if !xattr::is_supported(path) {
+        let value = if tags.is_empty() { FileAttributeType::Invalid } else { tags.to_attribute() };
+        return metadata::set_attribute(path, TAGS_METADATA, &value);
+    }
+    if tags.is_empty() {
+        return xattr::remove(path, TAGS_XATTR, follow_symlinks);
+    }
+    xattr::set(path, TAGS_XATTR, tags.to_string().as_bytes(), follow_symlinks)
+}
+
+fn read_comment(path: &Path, follow_symlinks: bool) -> NpioResult<Option<String>> {
+    if !xattr::is_supported(path) {
+        return Ok(match metadata::get_attribute(path, COMMENT_METADATA)? {
+            Some(FileAttributeType::String(comment)) => Some(comment),
+            _ => None,
+        });
+    }
+    Ok(xattr::get_if_present(path, COMMENT_XATTR, follow_symlinks)?
+        .map(|value| String::from_utf8_lossy(&value).into_owned()))
+}
+
+fn write_comment(path: &Path, comment: Option<&str>, follow_symlinks: bool) -> NpioResult<()> {
+    let comment = comment.filter(|comment| !comment.is_empty());
+    if !xattr::is_supported(path) {
+        let value = comment.map_or(FileAttributeType::Invalid, |comment| FileAttributeType::String(comment.to_string()));
+        return metadata::set_attribute(path, COMMENT_METADATA, &value);
+    }
+    match comment {
+        Some(comment) => xattr::set(path, COMMENT_XATTR, comment.as_bytes(), follow_symlinks),
+        None => xattr::remove(path, COMMENT_XATTR, follow_symlinks),
+    }
+}
+
+/// Adds the `tags::*` attributes selected by `matcher` to `info`.
+pub(super) fn query(path: &Path, metadata: &std::fs::Metadata, matcher: &FileAttributeMatcher, info: &mut FileInfo) {
+    // User xattrs cannot be set on symlinks, so a symlink has no tags of its own
+    if metadata.file_type().is_symlink() {
+        return;
+    }
+
+    if matcher.matches(TAGS_ATTRIBUTE) || matcher.matches(COLOR_LABEL_ATTRIBUTE) {
+        // Unreadable tags are left out like unreadable xattrs
+        if let Ok(tags) = read_tags(path, false) {
+            if let Some(color) = tags.color_label().filter(|_| matcher.matches(COLOR_LABEL_ATTRIBUTE)) {
+                info.set_attribute(COLOR_LABEL_ATTRIBUTE, FileAttributeType::String(color.name().to_string()));
+            }
+            if !tags.is_empty() && matcher.matches(TAGS_ATTRIBUTE) {
+                info.set_attribute(TAGS_ATTRIBUTE, tags.to_attribute());
+            }
+        }
+    }
+    if matcher.matches(COMMENT_ATTRIBUTE) {
+        if let Ok(Some(comment)) = read_comment(path, false) {
+            info.set_attribute(COMMENT_ATTRIBUTE, FileAttributeType::String(comment));
+        }
+    }
+}
+
+/// Sets `tags::list`, `tags::comment` or `tags::color-label`; `FileAttributeType::Invalid` removes it.
+pub(super) fn set_attribute(
+    path: &Path,
+    attribute: &str,
+    value: &FileAttributeType,
+    follow_symlinks: bool,
+) -> NpioResult<()> {
+    match (attribute, value) {
+        (TAGS_ATTRIBUTE, FileAttributeType::Invalid) => write_tags(path, &Tags::new(), follow_symlinks),
+        (TAGS_ATTRIBUTE, FileAttributeType::Stringv(tags)) => write_tags(path, &Tags::from_list(tags)?, follow_symlinks),
+        (COMMENT_ATTRIBUTE, FileAttributeType::Invalid) => write_comment(path, None, follow_symlinks),
+        (COMMENT_ATTRIBUTE, FileAttributeType::String(comment)) => write_comment(path, Some(comment), follow_symlinks),
+        (COLOR_LABEL_ATTRIBUTE, FileAttributeType::Invalid | FileAttributeType::String(_)) => {
+            let color = match value {
+                FileAttributeType::String(name) => Some(TagColor::from_name(name).ok_or_else(|| {
+                    NpioError::new(IOErrorEnum::InvalidArg, format!("Unknown color label: {}", name))
+                })?),
+                _ => None,
+            };
+            let mut tags = read_tags(path, follow_symlinks)?;
+            tags.set_color_label(color);
+            write_tags(path, &tags, follow_symlinks)
+        }
+        _ => Err(NpioError::new(
+            IOErrorEnum::InvalidArg,
+            format!("Invalid value for {}", attribute),
+        )),
+    }
+}
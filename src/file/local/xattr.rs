@@ -0,0 +1,245 @@
+//! Extended attributes in the `xattr::` and `xattr-sys::` namespaces
+//!
+//! Like GIO, `xattr::name` is the `user.name` extended attribute and
+//! `xattr-sys::name` is any other, e.g. `xattr-sys::security.selinux`.
+//! Names and values are reported as strings; bytes outside printable ASCII
+//! and backslashes are escaped as `\xNN`. Setting accepts such escaped
+//! strings or raw byte strings.
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::error::{NpioError, NpioResult, IOErrorEnum};
+use crate::file_info::{FileAttributeMatcher, FileAttributeType, FileInfo};
+
+/// Kernel namespace the `xattr::` attribute namespace maps to.
+const USER_NAMESPACE_PREFIX: &[u8] = b"user.";
+
+const XATTR_ATTRIBUTE_PREFIX: &str = "xattr::";
+const XATTR_SYS_ATTRIBUTE_PREFIX: &str = "xattr-sys::";
+
+/// Characters left unescaped: printable ASCII except the escape character itself.
+fn is_plain_byte(byte: u8) -> bool {
+    (32..=126).contains(&byte) && byte != b'\\'
+}
+
+/// Escapes bytes the way GIO reports extended attribute names and values.
+pub(super) fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if is_plain_byte(byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape`; backslashes not followed by two hex digits are kept as they are.
+pub(super) fn unescape(escaped: &str) -> Vec<u8> {
+    let bytes = escaped.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x') {
+            let hex = bytes.get(i + 2..i + 4).and_then(|hex| std::str::from_utf8(hex).ok());
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                unescaped.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        unescaped.push(bytes[i]);
+        i += 1;
+    }
+    unescaped
+}
+
+/// The kernel name of the extended attribute behind an `xattr::` or `xattr-sys::` attribute,
+/// the inverse of `attribute_for_xattr_name`. The prefix is always added, so
+/// `xattr::user.name` is `user.user.name`, as in GIO; the kernel `user.name` is `xattr::name`.
+pub(super) fn xattr_name_for_attribute(attribute: &str) -> Option<Vec<u8>> {
+    if let Some(name) = attribute.strip_prefix(XATTR_ATTRIBUTE_PREFIX) {
+        let mut full_name = USER_NAMESPACE_PREFIX.to_vec();
+        full_name.extend(unescape(name));
+        Some(full_name)
+    } else {
+        attribute.strip_prefix(XATTR_SYS_ATTRIBUTE_PREFIX).map(unescape)
+    }
+}
+
+/// The attribute an extended attribute is reported as.
+fn attribute_for_xattr_name(name: &[u8]) -> String {
+    match name.strip_prefix(USER_NAMESPACE_PREFIX) {
+        Some(user_name) => format!("{}{}", XATTR_ATTRIBUTE_PREFIX, escape(user_name)),
+        None => format!("{}{}", XATTR_SYS_ATTRIBUTE_PREFIX, escape(name)),
+    }
+}
+
+fn c_path(path: &Path) -> NpioResult<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid path: {}", e)))
+}
+
+fn c_name(name: &[u8]) -> NpioResult<CString> {
+    CString::new(name)
+        .map_err(|e| NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid xattr name: {}", e)))
+}
+
+/// Runs a size-probing xattr call: first with an empty buffer for the size, then for real,
+/// retrying if the value grew in between.
+pub(super) fn read_sized(mut call: impl FnMut(*mut libc::c_void, usize) -> libc::ssize_t) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; size as usize];
+        let read = call(buffer.as_mut_ptr() as *mut libc::c_void, buffer.len());
+        if read < 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(error);
+        }
+        buffer.truncate(read as usize);
+        return Ok(buffer);
+    }
+}
+
+/// Lists the names of the extended attributes of `path`.
+pub(super) fn list(path: &Path, follow_symlinks: bool) -> NpioResult<Vec<Vec<u8>>> {
+    let c_path = c_path(path)?;
+    let names = read_sized(|buffer, size| unsafe {
+        if follow_symlinks {
+            libc::listxattr(c_path.as_ptr(), buffer as *mut libc::c_char, size)
+        } else {
+            libc::llistxattr(c_path.as_ptr(), buffer as *mut libc::c_char, size)
+        }
+    })?;
+
+    Ok(names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(<[u8]>::to_vec)
+        .collect())
+}
+
+/// Reads one extended attribute.
+pub(super) fn get(path: &Path, name: &[u8], follow_symlinks: bool) -> NpioResult<Vec<u8>> {
+    Ok(read_value(&c_path(path)?, &c_name(name)?, follow_symlinks)?)
+}
+
+/// Like `get`, but a missing attribute or a filesystem without xattrs gives `None`.
+pub(super) fn get_if_present(path: &Path, name: &[u8], follow_symlinks: bool) -> NpioResult<Option<Vec<u8>>> {
+    match read_value(&c_path(path)?, &c_name(name)?, follow_symlinks) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENODATA) | Some(libc::ENOTSUP)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_value(c_path: &CStr, c_name: &CStr, follow_symlinks: bool) -> io::Result<Vec<u8>> {
+    read_sized(|buffer, size| unsafe {
+        if follow_symlinks {
+            libc::getxattr(c_path.as_ptr(), c_name.as_ptr(), buffer, size)
+        } else {
+            libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buffer, size)
+        }
+    })
+}
+
+/// Creates or replaces one extended attribute.
+pub(super) fn set(path: &Path, name: &[u8], value: &[u8], follow_symlinks: bool) -> NpioResult<()> {
+    let c_path = c_path(path)?;
+    let c_name = c_name(name)?;
+    let value_pointer = value.as_ptr() as *const libc::c_void;
+    let ret = unsafe {
+        if follow_symlinks {
+            libc::setxattr(c_path.as_ptr(), c_name.as_ptr(), value_pointer, value.len(), 0)
+        } else {
+            libc::lsetxattr(c_path.as_ptr(), c_name.as_ptr(), value_pointer, value.len(), 0)
+        }
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Removes one extended attribute; removing a missing attribute is not an error.
+pub(super) fn remove(path: &Path, name: &[u8], follow_symlinks: bool) -> NpioResult<()> {
+    let c_path = c_path(path)?;
+    let c_name = c_name(name)?;
+    let ret = unsafe {
+        if follow_symlinks {
+            libc::removexattr(c_path.as_ptr(), c_name.as_ptr())
+        } else {
+            libc::lremovexattr(c_path.as_ptr(), c_name.as_ptr())
+        }
+    };
+    if ret != 0 {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ENODATA) {
+            return Err(error.into());
+        }
+    }
+    Ok(())
+}
+
+/// Whether the filesystem holding `path` supports extended attributes at all.
+pub(super) fn is_supported(path: &Path) -> bool {
+    // Probing a name that is never set tells "no such attribute" from "not supported"
+    let Ok(c_path) = c_path(path) else {
+        return false;
+    };
+    let probe: &CStr = c"user.npio-xattr-probe";
+    let ret = unsafe { libc::lgetxattr(c_path.as_ptr(), probe.as_ptr(), std::ptr::null_mut(), 0) };
+    ret >= 0 || io::Error::last_os_error().raw_os_error() != Some(libc::ENOTSUP)
+}
+
+/// Adds the extended attributes of `path` selected by `matcher` to `info`.
+pub(super) fn query(path: &Path, matcher: &FileAttributeMatcher, info: &mut FileInfo) {
+    // Filesystems without xattrs, or files we may not read them from, just have none
+    let Ok(names) = list(path, false) else {
+        return;
+    };
+
+    for name in names {
+        let attribute = attribute_for_xattr_name(&name);
+        if !matcher.matches(&attribute) {
+            continue;
+        }
+        if let Ok(value) = get(path, &name, false) {
+            info.set_attribute(&attribute, FileAttributeType::String(escape(&value)));
+        }
+    }
+}
+
+/// Sets or, for `FileAttributeType::Invalid`, removes the extended attribute behind `attribute`.
+pub(super) fn set_attribute(
+    path: &Path,
+    attribute: &str,
+    value: &FileAttributeType,
+    follow_symlinks: bool,
+) -> NpioResult<()> {
+    let name = xattr_name_for_attribute(attribute).ok_or_else(|| NpioError::new(
+        IOErrorEnum::InvalidArg,
+        format!("Not an extended attribute: {}", attribute),
+    ))?;
+
+    match value {
+        FileAttributeType::Invalid => remove(path, &name, follow_symlinks),
+        FileAttributeType::ByteString(bytes) => set(path, &name, bytes, follow_symlinks),
+        FileAttributeType::String(escaped) => set(path, &name, &unescape(escaped), follow_symlinks),
+        _ => Err(NpioError::new(
+            IOErrorEnum::InvalidArg,
+            format!("Extended attribute {} must be a string or byte string", attribute),
+        )),
+    }
+}
@@ -1,7 +1,25 @@
-use std::collections::HashMap;
+pub mod registry;
 
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, PartialEq)]
+use bitflags::bitflags;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::icon::Icon;
+use crate::metadata::MimeResolver;
+
+const NANOSECONDS_PER_MICROSECOND: u32 = 1000;
+
+/// Version of the serialized `FileInfo` format. Readers reject newer versions;
+/// it only changes when old readers would misread the data.
+pub const FILE_INFO_FORMAT_VERSION: u32 = 1;
+
+/// Values are serialized with their type as the key, e.g. `{"uint64": 42}` in JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum FileAttributeType {
     String(String),
     Stringv(Vec<String>),
@@ -15,6 +33,99 @@ pub enum FileAttributeType {
     Invalid,
 }
 
+impl FileAttributeType {
+    /// The kind of value this is.
+    pub fn kind(&self) -> FileAttributeKind {
+        match self {
+            FileAttributeType::String(_) => FileAttributeKind::String,
+            FileAttributeType::Stringv(_) => FileAttributeKind::Stringv,
+            FileAttributeType::ByteString(_) => FileAttributeKind::ByteString,
+            FileAttributeType::Boolean(_) => FileAttributeKind::Boolean,
+            FileAttributeType::Uint32(_) => FileAttributeKind::Uint32,
+            FileAttributeType::Int32(_) => FileAttributeKind::Int32,
+            FileAttributeType::Uint64(_) => FileAttributeKind::Uint64,
+            FileAttributeType::Int64(_) => FileAttributeKind::Int64,
+            FileAttributeType::Object(_) => FileAttributeKind::Object,
+            FileAttributeType::Invalid => FileAttributeKind::Invalid,
+        }
+    }
+}
+
+/// The type of an attribute without a value, as GIO's GFileAttributeType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileAttributeKind {
+    String,
+    Stringv,
+    ByteString,
+    Boolean,
+    Uint32,
+    Int32,
+    Uint64,
+    Int64,
+    Object,
+    Invalid,
+}
+
+bitflags! {
+    /// How an attribute behaves when its file is copied or moved.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct FileAttributeInfoFlags: u32 {
+        const NONE = 0;
+        const COPY_WITH_FILE = 1;
+        const COPY_WHEN_MOVED = 2;
+    }
+}
+
+/// Describes an attribute or attribute namespace that can be set on a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileAttributeInfo {
+    pub name: String,
+    pub kind: FileAttributeKind,
+    pub flags: FileAttributeInfoFlags,
+}
+
+impl FileAttributeInfo {
+    pub fn new(name: &str, kind: FileAttributeKind, flags: FileAttributeInfoFlags) -> Self {
+        Self { name: name.to_string(), kind, flags }
+    }
+}
+
+/// Outcome of setting an attribute with `File::set_attributes_from_info_with_status`, as GIO's GFileAttributeStatus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FileAttributeStatus {
+    /// Not set yet.
+    #[default]
+    Unset,
+    /// Set on the file.
+    Set,
+    /// Setting it failed.
+    ErrorSetting,
+}
+
+/// Value of `filesystem::use-preview`: which user preference decides whether files on
+/// the filesystem get previews such as thumbnails, as GIO's GFilesystemPreviewType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilesystemPreviewType {
+    /// Only if previews are enabled everywhere, e.g. for remote filesystems.
+    IfAlways = 0,
+    /// If previews are enabled for local files.
+    IfLocal = 1,
+    /// Never, e.g. for kernel pseudo filesystems.
+    Never = 2,
+}
+
+impl FilesystemPreviewType {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(FilesystemPreviewType::IfAlways),
+            1 => Some(FilesystemPreviewType::IfLocal),
+            2 => Some(FilesystemPreviewType::Never),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     Unknown,
@@ -26,20 +137,69 @@ pub enum FileType {
     Mountable,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "SerializedFileInfo", try_from = "SerializedFileInfo")]
 pub struct FileInfo {
     attributes: HashMap<String, FileAttributeType>,
+    /// Per-attribute outcome of the last `set_attributes_from_info_with_status`; not serialized.
+    statuses: HashMap<String, FileAttributeStatus>,
+}
+
+/// Wire format of `FileInfo`; attributes are sorted so equal infos serialize identically.
+#[derive(Serialize, Deserialize)]
+struct SerializedFileInfo {
+    version: u32,
+    attributes: BTreeMap<String, FileAttributeType>,
+}
+
+impl From<FileInfo> for SerializedFileInfo {
+    fn from(info: FileInfo) -> Self {
+        Self {
+            version: FILE_INFO_FORMAT_VERSION,
+            attributes: info.attributes.into_iter().collect(),
+        }
+    }
+}
+
+impl TryFrom<SerializedFileInfo> for FileInfo {
+    type Error = NpioError;
+
+    fn try_from(serialized: SerializedFileInfo) -> NpioResult<Self> {
+        if serialized.version > FILE_INFO_FORMAT_VERSION {
+            return Err(NpioError::new(
+                IOErrorEnum::NotSupported,
+                format!("Unsupported FileInfo format version {}", serialized.version),
+            ));
+        }
+
+        let mut info = FileInfo::new();
+        for (key, value) in serialized.attributes {
+            info.try_set_attribute(&key, value)?;
+        }
+        Ok(info)
+    }
 }
 
 impl FileInfo {
     pub fn new() -> Self {
         Self {
             attributes: HashMap::new(),
+            statuses: HashMap::new(),
         }
     }
 
+    /// Sets an attribute. A value of the wrong type for a known attribute (see `registry`)
+    /// is dropped and the attribute keeps its previous value; use `try_set_attribute` when
+    /// the value may be of the wrong type and the caller needs to know.
     pub fn set_attribute(&mut self, key: &str, value: FileAttributeType) {
+        let _ = self.try_set_attribute(key, value);
+    }
+
+    /// Sets an attribute, failing with `InvalidArg` if a known attribute gets a value of the wrong type.
+    pub fn try_set_attribute(&mut self, key: &str, value: FileAttributeType) -> NpioResult<()> {
+        registry::validate(key, &value)?;
         self.attributes.insert(key.to_string(), value);
+        Ok(())
     }
 
     pub fn get_attribute(&self, key: &str) -> Option<&FileAttributeType> {
@@ -52,6 +212,27 @@ impl FileInfo {
 
     pub fn remove_attribute(&mut self, key: &str) {
         self.attributes.remove(key);
+        self.statuses.remove(key);
+    }
+
+    /// The status of `key`; `Unset` for attributes that were never set on a file.
+    pub fn get_attribute_status(&self, key: &str) -> FileAttributeStatus {
+        self.statuses.get(key).copied().unwrap_or_default()
+    }
+
+    /// Records the status of `key`. Returns false, without recording anything, if the info
+    /// has no such attribute.
+    pub fn set_attribute_status(&mut self, key: &str, status: FileAttributeStatus) -> bool {
+        if !self.attributes.contains_key(key) {
+            return false;
+        }
+        self.statuses.insert(key.to_string(), status);
+        true
+    }
+
+    /// Resets the status of every attribute to `Unset`.
+    pub fn clear_status(&mut self) {
+        self.statuses.clear();
     }
 
     /// Get all attributes as an iterator
@@ -83,6 +264,64 @@ impl FileInfo {
         }
     }
 
+    pub fn set_edit_name(&mut self, name: &str) {
+        self.set_attribute("standard::edit-name", FileAttributeType::String(name.to_string()));
+    }
+
+    pub fn get_edit_name(&self) -> Option<&str> {
+        match self.get_attribute("standard::edit-name") {
+            Some(FileAttributeType::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn set_is_hidden(&mut self, is_hidden: bool) {
+        self.set_attribute("standard::is-hidden", FileAttributeType::Boolean(is_hidden));
+    }
+
+    pub fn get_is_hidden(&self) -> bool {
+        matches!(self.get_attribute("standard::is-hidden"), Some(FileAttributeType::Boolean(true)))
+    }
+
+    pub fn set_is_backup(&mut self, is_backup: bool) {
+        self.set_attribute("standard::is-backup", FileAttributeType::Boolean(is_backup));
+    }
+
+    pub fn get_is_backup(&self) -> bool {
+        matches!(self.get_attribute("standard::is-backup"), Some(FileAttributeType::Boolean(true)))
+    }
+
+    pub fn set_is_symlink(&mut self, is_symlink: bool) {
+        self.set_attribute("standard::is-symlink", FileAttributeType::Boolean(is_symlink));
+    }
+
+    pub fn get_is_symlink(&self) -> bool {
+        matches!(self.get_attribute("standard::is-symlink"), Some(FileAttributeType::Boolean(true)))
+    }
+
+    /// Sets the raw target of a symlink, which need not be valid UTF-8.
+    pub fn set_symlink_target(&mut self, target: &[u8]) {
+        self.set_attribute("standard::symlink-target", FileAttributeType::ByteString(target.to_vec()));
+    }
+
+    pub fn get_symlink_target(&self) -> Option<&[u8]> {
+        match self.get_attribute("standard::symlink-target") {
+            Some(FileAttributeType::ByteString(target)) => Some(target),
+            _ => None,
+        }
+    }
+
+    pub fn set_sort_order(&mut self, sort_order: i32) {
+        self.set_attribute("standard::sort-order", FileAttributeType::Int32(sort_order));
+    }
+
+    pub fn get_sort_order(&self) -> i32 {
+        match self.get_attribute("standard::sort-order") {
+            Some(FileAttributeType::Int32(sort_order)) => *sort_order,
+            _ => 0,
+        }
+    }
+
     pub fn set_file_type(&mut self, file_type: FileType) {
         let val = match file_type {
             FileType::Unknown => 0,
@@ -118,7 +357,6 @@ impl FileInfo {
     pub fn get_size(&self) -> i64 {
         match self.get_attribute("standard::size") {
             Some(FileAttributeType::Uint64(s)) => *s as i64,
-            Some(FileAttributeType::Int64(s)) => *s,
             _ => 0,
         }
     }
@@ -134,10 +372,125 @@ impl FileInfo {
         }
     }
 
+    /// The `standard::icon` attribute as an `Icon`. When it is the content type's icon, the
+    /// content type's fallbacks come with it.
+    pub fn get_icon(&self) -> Option<Icon> {
+        self.icon_attribute("standard::icon", MimeResolver::get_icon)
+    }
+
+    /// The `standard::symbolic-icon` attribute as an `Icon`, like `get_icon`.
+    pub fn get_symbolic_icon(&self) -> Option<Icon> {
+        self.icon_attribute("standard::symbolic-icon", MimeResolver::get_symbolic_icon)
+    }
+
+    fn icon_attribute(&self, key: &str, for_content_type: fn(&str) -> Icon) -> Option<Icon> {
+        let Some(FileAttributeType::String(stored)) = self.get_attribute(key) else {
+            return None;
+        };
+        if let Some(content_type) = self.get_content_type() {
+            let icon = for_content_type(content_type);
+            if icon.names().first() == Some(stored) {
+                return Some(icon);
+            }
+        }
+        Icon::from_string(stored).ok()
+    }
+
     pub fn set_modification_time(&mut self, time: u64) {
         self.set_attribute("time::modified", FileAttributeType::Uint64(time));
     }
 
+    // Typed timestamps, stored as the seconds, -usec and -nsec attributes of a time:: key
+
+    /// Reads the timestamp stored under `key`, including its sub-second part if present.
+    fn get_timestamp(&self, key: &str) -> Option<SystemTime> {
+        let seconds = match self.get_attribute(key) {
+            Some(FileAttributeType::Uint64(seconds)) => *seconds,
+            _ => return None,
+        };
+        let nanoseconds = match (
+            self.get_attribute(&format!("{}-nsec", key)),
+            self.get_attribute(&format!("{}-usec", key)),
+        ) {
+            (Some(FileAttributeType::Uint32(nanoseconds)), _) => *nanoseconds,
+            (_, Some(FileAttributeType::Uint32(microseconds))) => microseconds * NANOSECONDS_PER_MICROSECOND,
+            _ => 0,
+        };
+        Some(UNIX_EPOCH + Duration::new(seconds, nanoseconds))
+    }
+
+    /// Stores `time` under `key` and its -usec and -nsec attributes. Times before the epoch are clamped to it.
+    fn set_timestamp(&mut self, key: &str, time: SystemTime) {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.set_attribute(key, FileAttributeType::Uint64(since_epoch.as_secs()));
+        self.set_attribute(
+            &format!("{}-usec", key),
+            FileAttributeType::Uint32(since_epoch.subsec_micros()),
+        );
+        self.set_attribute(
+            &format!("{}-nsec", key),
+            FileAttributeType::Uint32(since_epoch.subsec_nanos()),
+        );
+    }
+
+    pub fn get_modification_system_time(&self) -> Option<SystemTime> {
+        self.get_timestamp("time::modified")
+    }
+
+    pub fn set_modification_system_time(&mut self, time: SystemTime) {
+        self.set_timestamp("time::modified", time);
+    }
+
+    pub fn get_modification_date_time(&self) -> Option<DateTime<Utc>> {
+        self.get_modification_system_time().map(DateTime::from)
+    }
+
+    pub fn set_modification_date_time(&mut self, time: DateTime<Utc>) {
+        self.set_modification_system_time(time.into());
+    }
+
+    pub fn get_access_system_time(&self) -> Option<SystemTime> {
+        self.get_timestamp("time::access")
+    }
+
+    pub fn set_access_system_time(&mut self, time: SystemTime) {
+        self.set_timestamp("time::access", time);
+    }
+
+    pub fn get_access_date_time(&self) -> Option<DateTime<Utc>> {
+        self.get_access_system_time().map(DateTime::from)
+    }
+
+    pub fn set_access_date_time(&mut self, time: DateTime<Utc>) {
+        self.set_access_system_time(time.into());
+    }
+
+    /// Time of the last status change (ctime); reported by backends, not settable on files.
+    pub fn get_changed_system_time(&self) -> Option<SystemTime> {
+        self.get_timestamp("time::changed")
+    }
+
+    pub fn get_changed_date_time(&self) -> Option<DateTime<Utc>> {
+        self.get_changed_system_time().map(DateTime::from)
+    }
+
+    /// Birth time; only present if the filesystem records it.
+    pub fn get_creation_system_time(&self) -> Option<SystemTime> {
+        self.get_timestamp("time::created")
+    }
+
+    pub fn set_creation_system_time(&mut self, time: SystemTime) {
+        self.set_timestamp("time::created", time);
+    }
+
+    pub fn get_creation_date_time(&self) -> Option<DateTime<Utc>> {
+        self.get_creation_system_time().map(DateTime::from)
+    }
+
+    pub fn set_creation_date_time(&mut self, time: DateTime<Utc>) {
+        self.set_creation_system_time(time.into());
+    }
+
     // Thumbnail attributes (freedesktop.org spec)
     
     pub fn set_thumbnail_path(&mut self, path: &str, size: Option<&str>) {
@@ -209,3 +562,182 @@ impl Default for FileInfo {
         Self::new()
     }
 }
+
+/// One entry of an attribute string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum AttributePattern {
+    /// `*`
+    All,
+    /// `ns::*`
+    Namespace(String),
+    /// `ns::key`
+    Key(String),
+}
+
+impl AttributePattern {
+    fn parse(entry: &str) -> Self {
+        if entry == "*" {
+            return AttributePattern::All;
+        }
+        match entry.split_once("::") {
+            Some((namespace, "*")) => AttributePattern::Namespace(namespace.to_string()),
+            Some(_) => AttributePattern::Key(entry.to_string()),
+            // A bare name stands for the whole namespace
+            None => AttributePattern::Namespace(entry.to_string()),
+        }
+    }
+
+    fn matches(&self, key: &str) -> bool {
+        match self {
+            AttributePattern::All => true,
+            AttributePattern::Namespace(namespace) => attribute_namespace(key) == namespace,
+            AttributePattern::Key(pattern_key) => pattern_key == key,
+        }
+    }
+
+    /// Whether some key of `namespace` matches.
+    fn overlaps_namespace(&self, namespace: &str) -> bool {
+        match self {
+            AttributePattern::All => true,
+            AttributePattern::Namespace(pattern_namespace) => pattern_namespace == namespace,
+            AttributePattern::Key(key) => attribute_namespace(key) == namespace,
+        }
+    }
+
+    /// Whether every key of `namespace` matches.
+    fn covers_namespace(&self, namespace: &str) -> bool {
+        match self {
+            AttributePattern::All => true,
+            AttributePattern::Namespace(pattern_namespace) => pattern_namespace == namespace,
+            AttributePattern::Key(_) => false,
+        }
+    }
+
+    /// The pattern matching exactly the keys both patterns match, if there are any.
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (AttributePattern::All, pattern) | (pattern, AttributePattern::All) => Some(pattern.clone()),
+            (AttributePattern::Namespace(a), AttributePattern::Namespace(b)) => {
+                (a == b).then(|| self.clone())
+            }
+            (AttributePattern::Namespace(namespace), AttributePattern::Key(key))
+            | (AttributePattern::Key(key), AttributePattern::Namespace(namespace)) => {
+                (attribute_namespace(key) == namespace).then(|| AttributePattern::Key(key.clone()))
+            }
+            (AttributePattern::Key(a), AttributePattern::Key(b)) => (a == b).then(|| self.clone()),
+        }
+    }
+}
+
+/// Keys matched by any of `included` and none of `excluded`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MatcherTerm {
+    included: Vec<AttributePattern>,
+    excluded: Vec<AttributePattern>,
+}
+
+impl MatcherTerm {
+    fn matches(&self, key: &str) -> bool {
+        self.included.iter().any(|p| p.matches(key)) && !self.excluded.iter().any(|p| p.matches(key))
+    }
+
+    fn enumerates_namespace(&self, namespace: &str) -> bool {
+        self.included.iter().any(|p| p.overlaps_namespace(namespace))
+            && !self.excluded.iter().any(|p| p.covers_namespace(namespace))
+    }
+}
+
+/// Decides which attributes a query asked for.
+///
+/// Mirrors GIO's GFileAttributeMatcher. An attribute string is a
+/// comma-separated list of `*` (everything), `ns::*` (a whole namespace) and
+/// `ns::key` (a single attribute). Entries prefixed with `-` are excluded, so
+/// `"standard::*,-standard::icon"` matches every standard attribute but the icon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileAttributeMatcher {
+    terms: Vec<MatcherTerm>,
+}
+
+impl FileAttributeMatcher {
+    /// Parses an attribute string.
+    pub fn new(attributes: &str) -> Self {
+        let mut term = MatcherTerm { included: Vec::new(), excluded: Vec::new() };
+        for entry in attributes.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.strip_prefix('-') {
+                Some(excluded) => term.excluded.push(AttributePattern::parse(excluded.trim())),
+                None => term.included.push(AttributePattern::parse(entry)),
+            }
+        }
+
+        let terms = if term.included.is_empty() { Vec::new() } else { vec![term] };
+        Self { terms }
+    }
+
+    /// Checks if `attribute` was asked for.
+    pub fn matches(&self, attribute: &str) -> bool {
+        self.terms.iter().any(|term| term.matches(attribute))
+    }
+
+    /// Like `matches`, but ignores `*`; for attributes too expensive to compute unless named.
+    pub(crate) fn matches_explicitly(&self, attribute: &str) -> bool {
+        self.terms.iter().any(|term| {
+            term.included.iter().any(|p| *p != AttributePattern::All && p.matches(attribute))
+                && !term.excluded.iter().any(|p| p.matches(attribute))
+        })
+    }
+
+    /// Like `matches`, but only for attributes named in full; `standard::*` does not count.
+    pub(crate) fn matches_by_name(&self, attribute: &str) -> bool {
+        self.terms.iter().any(|term| {
+            term.included.iter().any(|p| matches!(p, AttributePattern::Key(_)) && p.matches(attribute))
+                && !term.excluded.iter().any(|p| p.matches(attribute))
+        })
+    }
+
+    /// Checks if any attribute of `namespace` may have been asked for, so a
+    /// backend can skip the work of computing a namespace nobody wants.
+    pub fn enumerate_namespace(&self, namespace: &str) -> bool {
+        self.terms.iter().any(|term| term.enumerates_namespace(namespace))
+    }
+
+    /// Returns a matcher matching the attributes matched by either matcher.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut terms = self.terms.clone();
+        for term in &other.terms {
+            if !terms.contains(term) {
+                terms.push(term.clone());
+            }
+        }
+        Self { terms }
+    }
+
+    /// Returns a matcher matching the attributes matched by `self` but not by `other`.
+    pub fn subtract(&self, other: &Self) -> Self {
+        let mut terms = self.terms.clone();
+        for subtracted in &other.terms {
+            let mut remaining = Vec::new();
+            for term in &terms {
+                // Keys not included by the subtracted term
+                let mut outside = term.clone();
+                outside.excluded.extend(subtracted.included.iter().cloned());
+                remaining.push(outside);
+
+                // Keys the subtracted term includes but then excludes again
+                let inside: Vec<AttributePattern> = term.included
+                    .iter()
+                    .flat_map(|a| subtracted.excluded.iter().filter_map(move |b| a.intersect(b)))
+                    .collect();
+                if !inside.is_empty() {
+                    remaining.push(MatcherTerm { included: inside, excluded: term.excluded.clone() });
+                }
+            }
+            terms = remaining;
+        }
+        Self { terms }
+    }
+}
+
+/// The namespace of an attribute key, e.g. `standard` for `standard::name`.
+fn attribute_namespace(key: &str) -> &str {
+    key.split_once("::").map(|(namespace, _)| namespace).unwrap_or(key)
+}
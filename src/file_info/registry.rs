@@ -0,0 +1,150 @@
+//! Registry of known attributes
+//!
+//! Describes the type of every attribute npio reports, whether `LocalFile`
+//! can set it and whether it travels with its file when copied or moved.
+//! `FileInfo` checks values of known attributes against it; attributes that
+//! are not listed, such as `xattr::*`, take any type.
+
+use super::{attribute_namespace, FileAttributeInfo, FileAttributeInfoFlags, FileAttributeKind, FileAttributeType};
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+
+use FileAttributeKind as Kind;
+
+const NOT_COPIED: FileAttributeInfoFlags = FileAttributeInfoFlags::NONE;
+const COPIED: FileAttributeInfoFlags =
+    FileAttributeInfoFlags::COPY_WITH_FILE.union(FileAttributeInfoFlags::COPY_WHEN_MOVED);
+const MOVED: FileAttributeInfoFlags = FileAttributeInfoFlags::COPY_WHEN_MOVED;
+
+/// Thumbnail attributes that also exist per size, e.g. `thumbnail::path-large`.
+const SIZED_THUMBNAIL_ATTRIBUTES: [&str; 3] = ["thumbnail::path", "thumbnail::is-valid", "thumbnail::failed"];
+
+/// A known attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileAttributeDescription {
+    pub name: &'static str,
+    pub kind: FileAttributeKind,
+    /// Whether `set_attribute` accepts it on local files.
+    pub settable: bool,
+    pub flags: FileAttributeInfoFlags,
+}
+
+impl FileAttributeDescription {
+    const fn new(name: &'static str, kind: FileAttributeKind, settable: bool, flags: FileAttributeInfoFlags) -> Self {
+        Self { name, kind, settable, flags }
+    }
+
+    /// The namespace, e.g. `standard` for `standard::size`.
+    pub fn namespace(&self) -> &'static str {
+        attribute_namespace(self.name)
+    }
+
+    pub fn to_info(&self) -> FileAttributeInfo {
+        FileAttributeInfo::new(self.name, self.kind, self.flags)
+    }
+}
+
+static ATTRIBUTES: &[FileAttributeDescription] = &[
+    FileAttributeDescription::new("standard::type", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::is-hidden", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::is-backup", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::is-symlink", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::name", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::display-name", Kind::String, true, NOT_COPIED),
+    FileAttributeDescription::new("standard::edit-name", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::copy-name", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::icon", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::symbolic-icon", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::content-type", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::fast-content-type", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::size", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::allocated-size", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::symlink-target", Kind::ByteString, false, NOT_COPIED),
+    FileAttributeDescription::new("standard::sort-order", Kind::Int32, false, NOT_COPIED),
+    FileAttributeDescription::new("etag::value", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("time::modified", Kind::Uint64, true, COPIED),
+    FileAttributeDescription::new("time::modified-usec", Kind::Uint32, true, COPIED),
+    FileAttributeDescription::new("time::modified-nsec", Kind::Uint32, true, COPIED),
+    FileAttributeDescription::new("time::access", Kind::Uint64, true, COPIED),
+    FileAttributeDescription::new("time::access-usec", Kind::Uint32, true, COPIED),
+    FileAttributeDescription::new("time::access-nsec", Kind::Uint32, true, COPIED),
+    // Older name of time::access, settable but never reported or copied
+    FileAttributeDescription::new("time::accessed", Kind::Uint64, true, NOT_COPIED),
+    FileAttributeDescription::new("time::changed", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("time::changed-usec", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("time::changed-nsec", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("time::created", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("time::created-usec", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("time::created-nsec", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::device", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::inode", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::mode", Kind::Uint32, true, COPIED),
+    FileAttributeDescription::new("unix::nlink", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::uid", Kind::Uint32, true, MOVED),
+    FileAttributeDescription::new("unix::gid", Kind::Uint32, true, MOVED),
+    FileAttributeDescription::new("unix::rdev", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::block-size", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::blocks", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("unix::is-mountpoint", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("owner::user", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("owner::user-real", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("owner::group", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-read", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-write", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-execute", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-delete", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-trash", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("access::can-rename", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("acl::access", Kind::String, true, COPIED),
+    FileAttributeDescription::new("acl::default", Kind::String, true, COPIED),
+    FileAttributeDescription::new("tags::list", Kind::Stringv, true, COPIED),
+    FileAttributeDescription::new("tags::comment", Kind::String, true, COPIED),
+    FileAttributeDescription::new("tags::color-label", Kind::String, true, COPIED),
+    FileAttributeDescription::new("thumbnail::path", Kind::ByteString, false, NOT_COPIED),
+    FileAttributeDescription::new("thumbnail::is-valid", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("thumbnail::failed", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("checksum::md5", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("checksum::sha1", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("checksum::sha256", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("checksum::blake3", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::size", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::free", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::used", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::readonly", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::type", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::remote", Kind::Boolean, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::use-preview", Kind::Uint32, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::id", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::inodes", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::inodes-free", Kind::Uint64, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::source", Kind::String, false, NOT_COPIED),
+    FileAttributeDescription::new("filesystem::options", Kind::String, false, NOT_COPIED),
+];
+
+/// Every known attribute.
+pub fn all() -> &'static [FileAttributeDescription] {
+    ATTRIBUTES
+}
+
+/// The description of `name`, if it is a known attribute.
+pub fn lookup(name: &str) -> Option<&'static FileAttributeDescription> {
+    let name = SIZED_THUMBNAIL_ATTRIBUTES
+        .into_iter()
+        .find(|base| name.strip_prefix(base).is_some_and(|size| size.starts_with('-')))
+        .unwrap_or(name);
+    ATTRIBUTES.iter().find(|description| description.name == name)
+}
+
+/// Checks that `value` has the type registered for `name`. `FileAttributeType::Invalid`,
+/// which unsets an attribute, is accepted for every attribute.
+pub fn validate(name: &str, value: &FileAttributeType) -> NpioResult<()> {
+    let Some(description) = lookup(name) else {
+        return Ok(());
+    };
+    if *value == FileAttributeType::Invalid || value.kind() == description.kind {
+        return Ok(());
+    }
+    Err(NpioError::new(
+        IOErrorEnum::InvalidArg,
+        format!("Attribute {} is {:?}, not {:?}", name, description.kind, value.kind()),
+    ))
+}
@@ -0,0 +1,145 @@
+//! Icons
+//!
+//! An `Icon` says what to show without being an image: names looked up in the
+//! icon theme (best first, so a theme lacking `drive-harddisk-usb` can still
+//! show `drive-harddisk`), an image file, or an icon with emblems. `IconTheme`
+//! turns one into a file for a size and scale.
+//!
+//! Icons serialize to strings like GIO's `g_icon_to_string`: a single theme
+//! name or an absolute path as is, anything else as `. Kind arg...` with the
+//! arguments percent-encoded.
+
+pub mod theme;
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::metadata::database::MimeDatabase;
+
+/// Prefix of the serialized form of icons that are not a single name or a path.
+const SERIALIZED_PREFIX: &str = ". ";
+const THEMED_KIND: &str = "ThemedIcon";
+const FILE_KIND: &str = "FileIcon";
+const EMBLEMED_KIND: &str = "EmblemedIcon";
+const SYMBOLIC_SUFFIX: &str = "-symbolic";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Icon {
+    /// Names looked up in the icon theme, best first.
+    Themed(Vec<String>),
+    /// An image file.
+    File(PathBuf),
+    /// An icon with emblems drawn over it, such as a folder with a lock.
+    Emblemed { icon: Box<Icon>, emblems: Vec<Icon> },
+}
+
+impl Icon {
+    /// A theme icon without fallbacks.
+    pub fn themed(name: &str) -> Self {
+        Icon::Themed(vec![name.to_string()])
+    }
+
+    /// A theme icon that falls back to the shorter names made by dropping `-` parts from the
+    /// end, like GIO's `g_themed_icon_new_with_default_fallbacks`:
+    /// `drive-harddisk-usb`, `drive-harddisk`, `drive`. A `-symbolic` suffix is kept on each.
+    pub fn themed_with_default_fallbacks(name: &str) -> Self {
+        let (base, suffix) = match name.strip_suffix(SYMBOLIC_SUFFIX) {
+            Some(base) => (base, SYMBOLIC_SUFFIX),
+            None => (name, ""),
+        };
+        let mut names = Vec::new();
+        let mut current = base;
+        loop {
+            names.push(format!("{}{}", current, suffix));
+            match current.rfind('-') {
+                Some(position) => current = &current[..position],
+                None => break,
+            }
+        }
+        Icon::Themed(names)
+    }
+
+    /// The icon of a content type, with its generic icon as fallback.
+    pub fn for_content_type(content_type: &str) -> Self {
+        Icon::Themed(MimeDatabase::global().icon_names(content_type))
+    }
+
+    /// The symbolic icon of a content type, with its generic icon as fallback.
+    pub fn symbolic_for_content_type(content_type: &str) -> Self {
+        Icon::Themed(MimeDatabase::global().symbolic_icon_names(content_type))
+    }
+
+    /// `icon` with `emblems` over it; without emblems, `icon` itself.
+    pub fn emblemed(icon: Icon, emblems: Vec<Icon>) -> Self {
+        if emblems.is_empty() {
+            return icon;
+        }
+        Icon::Emblemed { icon: Box::new(icon), emblems }
+    }
+
+    /// The theme names of a themed icon, best first; none for other icons.
+    pub fn names(&self) -> &[String] {
+        match self {
+            Icon::Themed(names) => names,
+            _ => &[],
+        }
+    }
+
+    /// Parses the form `to_string` gives.
+    pub fn from_string(string: &str) -> NpioResult<Self> {
+        let invalid = || NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid icon: {}", string));
+        let Some(serialized) = string.strip_prefix(SERIALIZED_PREFIX) else {
+            if string.is_empty() || string.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            if string.starts_with('/') {
+                return Ok(Icon::File(PathBuf::from(string)));
+            }
+            return Ok(Icon::themed(string));
+        };
+        let mut parts = serialized.split(' ');
+        let kind = parts.next().ok_or_else(invalid)?;
+        let args = parts
+            .map(|part| urlencoding::decode(part).map(|arg| arg.into_owned()).map_err(|_| invalid()))
+            .collect::<NpioResult<Vec<String>>>()?;
+        match kind {
+            THEMED_KIND if !args.is_empty() => Ok(Icon::Themed(args)),
+            FILE_KIND if args.len() == 1 => Ok(Icon::File(PathBuf::from(&args[0]))),
+            EMBLEMED_KIND if args.len() >= 2 => {
+                let mut icons = args.iter().map(|arg| Icon::from_string(arg));
+                let icon = icons.next().ok_or_else(invalid)??;
+                let emblems = icons.collect::<NpioResult<Vec<Icon>>>()?;
+                Ok(Icon::emblemed(icon, emblems))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Whether `string` can be written without the `. Kind` form.
+fn is_plain(string: &str) -> bool {
+    !string.is_empty() && !string.starts_with(SERIALIZED_PREFIX) && !string.contains(char::is_whitespace)
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, args) = match self {
+            Icon::Themed(names) if names.len() == 1 && is_plain(&names[0]) => return write!(f, "{}", names[0]),
+            Icon::File(path) if path.is_absolute() && is_plain(&path.to_string_lossy()) => {
+                return write!(f, "{}", path.display())
+            }
+            Icon::Themed(names) => (THEMED_KIND, names.clone()),
+            // A relative path cannot be told apart from a name, so it goes the long way
+            Icon::File(path) => (FILE_KIND, vec![path.to_string_lossy().into_owned()]),
+            Icon::Emblemed { icon, emblems } => {
+                (EMBLEMED_KIND, std::iter::once(icon.as_ref()).chain(emblems).map(Icon::to_string).collect())
+            }
+        };
+        write!(f, "{}{}", SERIALIZED_PREFIX, kind)?;
+        for arg in args {
+            write!(f, " {}", urlencoding::encode(&arg))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Icon themes
+//!
+//! Finds icon files as the freedesktop.org Icon Theme Specification describes:
+//! a theme lives in a directory of that name below any of the base directories
+//! (`~/.icons`, the `icons` directory of every XDG data directory and
+//! `/usr/share/pixmaps`), its `index.theme` lists subdirectories by size, scale
+//! and kind, and lookups continue through the themes named in `Inherits=` and
+//! finally `hicolor`. Directory listings and lookups are cached; the global
+//! themes are reloaded when their directories change.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use once_cell::sync::Lazy;
+
+use super::Icon;
+use crate::key_file::KeyFile;
+
+const INDEX_FILE: &str = "index.theme";
+const ICON_THEME_GROUP: &str = "Icon Theme";
+/// The theme every theme implicitly inherits from.
+const FALLBACK_THEME: &str = "hicolor";
+/// Extensions tried for each icon name, in order of preference.
+const ICON_EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+const DEFAULT_THRESHOLD: u32 = 2;
+
+const ICONS_DIR: &str = "icons";
+const HOME_ICONS_DIR: &str = ".icons";
+const PIXMAPS_DIR: &str = "/usr/share/pixmaps";
+
+/// Where GTK keeps the user's choice of theme.
+const GTK_SETTINGS_FILE: &str = "gtk-3.0/settings.ini";
+const GTK_SETTINGS_GROUP: &str = "Settings";
+const GTK_ICON_THEME_KEY: &str = "gtk-icon-theme-name";
+
+/// How often a global theme checks whether its directories changed.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+struct GlobalTheme {
+    theme: Arc<IconTheme>,
+    checked: Instant,
+}
+
+static GLOBAL_THEMES: Lazy<Mutex<HashMap<String, GlobalTheme>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirectoryKind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// A subdirectory of a theme, as its `index.theme` describes it.
+#[derive(Debug, Clone)]
+struct ThemeDirectory {
+    path: String,
+    kind: DirectoryKind,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDirectory {
+    fn from_index(index: &KeyFile, path: &str) -> Option<Self> {
+        let size = index.get_u32(path, "Size")?;
+        let kind = match index.get(path, "Type") {
+            Some("Fixed") => DirectoryKind::Fixed,
+            Some("Scalable") => DirectoryKind::Scalable,
+            _ => DirectoryKind::Threshold,
+        };
+        Some(Self {
+            path: path.to_string(),
+            kind,
+            size,
+            scale: index.get_u32(path, "Scale").unwrap_or(1),
+            min_size: index.get_u32(path, "MinSize").unwrap_or(size),
+            max_size: index.get_u32(path, "MaxSize").unwrap_or(size),
+            threshold: index.get_u32(path, "Threshold").unwrap_or(DEFAULT_THRESHOLD),
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirectoryKind::Fixed => self.size == size,
+            DirectoryKind::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirectoryKind::Threshold => {
+                (self.size.saturating_sub(self.threshold)..=self.size + self.threshold).contains(&size)
+            }
+        }
+    }
+
+    /// How far the directory's icons are from `size` at `scale`, in pixels.
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirectoryKind::Fixed => (self.size, self.size),
+            DirectoryKind::Scalable => (self.min_size, self.max_size),
+            DirectoryKind::Threshold => (self.size.saturating_sub(self.threshold), self.size + self.threshold),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+        if wanted < min {
+            min - wanted
+        } else {
+            wanted.saturating_sub(max)
+        }
+    }
+}
+
+/// One theme of the lookup chain.
+#[derive(Debug, Clone)]
+struct ThemeIndex {
+    name: String,
+    directories: Vec<ThemeDirectory>,
+}
+
+/// An icon theme with the themes it inherits from, resolving icons to files.
+#[derive(Debug)]
+pub struct IconTheme {
+    name: String,
+    base_dirs: Vec<PathBuf>,
+    /// The theme, then what it inherits from depth-first, `hicolor` last.
+    chain: Vec<ThemeIndex>,
+    /// Modification times of the theme directories, to notice updates.
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+    /// File names in each directory looked into.
+    listings: Mutex<HashMap<PathBuf, Arc<HashSet<String>>>>,
+    lookups: Mutex<HashMap<(String, u32, u32), Option<PathBuf>>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl IconTheme {
+    /// Loads the theme `name` from the default base directories.
+    pub fn new(name: &str) -> Self {
+        Self::with_search_path(name, Self::default_search_path())
+    }
+
+    /// Loads the theme `name` from `base_dirs`, most important first.
+    pub fn with_search_path(name: &str, base_dirs: Vec<PathBuf>) -> Self {
+        let mut theme = Self {
+            name: name.to_string(),
+            base_dirs,
+            chain: Vec::new(),
+            stamps: Vec::new(),
+            listings: Mutex::new(HashMap::new()),
+            lookups: Mutex::new(HashMap::new()),
+        };
+        let mut visited = HashSet::new();
+        theme.load_chain(name, &mut visited);
+        if !visited.contains(FALLBACK_THEME) {
+            theme.load_chain(FALLBACK_THEME, &mut visited);
+        }
+        theme
+    }
+
+    /// Adds `name` and, depth-first, the themes it inherits from.
+    fn load_chain(&mut self, name: &str, visited: &mut HashSet<String>) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        for base in &self.base_dirs {
+            let dir = base.join(name);
+            self.stamps.push((dir.clone(), modified(&dir)));
+        }
+        let Some(index) = self
+            .base_dirs
+            .iter()
+            .find_map(|base| std::fs::read_to_string(base.join(name).join(INDEX_FILE)).ok())
+            .map(|contents| KeyFile::parse(&contents))
+        else {
+            return;
+        };
+        let directories = index
+            .get_comma_list(ICON_THEME_GROUP, "Directories")
+            .into_iter()
+            .chain(index.get_comma_list(ICON_THEME_GROUP, "ScaledDirectories"))
+            .filter_map(|path| ThemeDirectory::from_index(&index, &path))
+            .collect();
+        self.chain.push(ThemeIndex { name: name.to_string(), directories });
+        for parent in index.get_comma_list(ICON_THEME_GROUP, "Inherits") {
+            self.load_chain(&parent, visited);
+        }
+    }
+
+    /// `~/.icons`, the `icons` directories of the XDG data directories and `/usr/share/pixmaps`.
+    pub fn default_search_path() -> Vec<PathBuf> {
+        let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(HOME_ICONS_DIR));
+        home.into_iter()
+            .chain(crate::xdg::data_search_dirs().into_iter().map(|dir| dir.join(ICONS_DIR)))
+            .chain(std::iter::once(PathBuf::from(PIXMAPS_DIR)))
+            .collect()
+    }
+
+    /// The theme chosen in GTK's settings, `hicolor` if none is.
+    pub fn current_theme_name() -> String {
+        crate::xdg::config_search_dirs()
+            .iter()
+            .filter_map(|dir| std::fs::read_to_string(dir.join(GTK_SETTINGS_FILE)).ok())
+            .find_map(|contents| KeyFile::parse(&contents).get_string(GTK_SETTINGS_GROUP, GTK_ICON_THEME_KEY))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| FALLBACK_THEME.to_string())
+    }
+
+    /// The theme chosen in GTK's settings, shared and reloaded when its directories change.
+    pub fn global() -> Arc<IconTheme> {
+        Self::shared(&Self::current_theme_name())
+    }
+
+    /// The theme `name` from the default base directories, shared and reloaded when its
+    /// directories change.
+    pub fn shared(name: &str) -> Arc<IconTheme> {
+        let mut themes = match GLOBAL_THEMES.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on icon themes: {}", e);
+                e.into_inner()
+            }
+        };
+        let needs_load = match themes.get_mut(name) {
+            None => true,
+            Some(global) if global.checked.elapsed() >= RELOAD_CHECK_INTERVAL => {
+                global.checked = Instant::now();
+                global.theme.is_outdated()
+            }
+            Some(_) => false,
+        };
+        if needs_load {
+            let theme = GlobalTheme { theme: Arc::new(IconTheme::new(name)), checked: Instant::now() };
+            themes.insert(name.to_string(), theme);
+        }
+        themes[name].theme.clone()
+    }
+
+    fn is_outdated(&self) -> bool {
+        self.base_dirs != Self::default_search_path()
+            || self.stamps.iter().any(|(path, stamp)| modified(path) != *stamp)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The themes searched, in order: this one, those it inherits from and `hicolor`.
+    /// Themes without an `index.theme` are left out.
+    pub fn themes(&self) -> Vec<&str> {
+        self.chain.iter().map(|theme| theme.name.as_str()).collect()
+    }
+
+    /// The names of the files in `dir`, read once.
+    fn listing(&self, dir: &Path) -> Arc<HashSet<String>> {
+        let mut listings = match self.listings.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on icon directory listings: {}", e);
+                e.into_inner()
+            }
+        };
+        listings
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let names = std::fs::read_dir(dir)
+                    .map(|entries| {
+                        entries.filter_map(Result::ok).map(|entry| entry.file_name().to_string_lossy().into_owned()).collect()
+                    })
+                    .unwrap_or_default();
+                Arc::new(names)
+            })
+            .clone()
+    }
+
+    /// The file for `name` in `dir`, trying each extension.
+    fn find_in(&self, dir: &Path, name: &str) -> Option<PathBuf> {
+        let listing = self.listing(dir);
+        ICON_EXTENSIONS
+            .iter()
+            .map(|extension| format!("{}.{}", name, extension))
+            .find(|file_name| listing.contains(file_name))
+            .map(|file_name| dir.join(file_name))
+    }
+
+    /// The spec's `LookupIcon`: a directory of the right size, else the closest one.
+    fn lookup_in_theme(&self, theme: &ThemeIndex, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let candidates = |directory: &ThemeDirectory| {
+            self.base_dirs
+                .iter()
+                .find_map(|base| self.find_in(&base.join(&theme.name).join(&directory.path), name))
+        };
+        if let Some(path) = theme
+            .directories
+            .iter()
+            .filter(|directory| directory.matches_size(size, scale))
+            .find_map(candidates)
+        {
+            return Some(path);
+        }
+        let mut best: Option<(u32, PathBuf)> = None;
+        for directory in &theme.directories {
+            let distance = directory.size_distance(size, scale);
+            if best.as_ref().is_some_and(|(best_distance, _)| distance >= *best_distance) {
+                continue;
+            }
+            if let Some(path) = candidates(directory) {
+                best = Some((distance, path));
+            }
+        }
+        best.map(|(_, path)| path)
+    }
+
+    /// The file for the icon `name` at `size` pixels and `scale`, from this theme, the themes it
+    /// inherits from, or unthemed icons directly in a base directory.
+    pub fn lookup(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let key = (name.to_string(), size, scale);
+        let mut lookups = match self.lookups.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on icon lookups: {}", e);
+                e.into_inner()
+            }
+        };
+        if let Some(cached) = lookups.get(&key) {
+            return cached.clone();
+        }
+        let scale = scale.max(1);
+        let path = self
+            .chain
+            .iter()
+            .find_map(|theme| self.lookup_in_theme(theme, name, size, scale))
+            .or_else(|| self.base_dirs.iter().find_map(|base| self.find_in(base, name)));
+        lookups.insert(key, path.clone());
+        path
+    }
+
+    /// Whether the theme or its parents have an icon called `name` in any size.
+    pub fn has_icon(&self, name: &str) -> bool {
+        self.chain.iter().any(|theme| {
+            theme.directories.iter().any(|directory| {
+                self.base_dirs
+                    .iter()
+                    .any(|base| self.find_in(&base.join(&theme.name).join(&directory.path), name).is_some())
+            })
+        })
+    }
+
+    /// The file to show for `icon`: the first of its names the theme has, the file itself, or
+    /// for an emblemed icon the file of the icon below the emblems.
+    pub fn lookup_icon(&self, icon: &Icon, size: u32, scale: u32) -> Option<PathBuf> {
+        match icon {
+            Icon::Themed(names) => names.iter().find_map(|name| self.lookup(name, size, scale)),
+            Icon::File(path) => path.exists().then(|| path.clone()),
+            Icon::Emblemed { icon, .. } => self.lookup_icon(icon, size, scale),
+        }
+    }
+}
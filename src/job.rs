@@ -1,10 +1,14 @@
 //! Async job operations
 //!
 //! Provides high-level async operations for file manipulation:
-//! - Copy: Copy files with progress reporting
+//! - Copy: Copy files with progress reporting and optional verification
+//! - Resumable copy: Copy that survives interruptions and pauses while a mount is gone
 //! - Move: Move/rename files
 //! - Delete: Delete files
 //! - Trash: Move files to trash (freedesktop.org spec)
+//! - Batch rename: Rename many files after a template (see `batch_rename`)
+
+pub mod batch_rename;
 
 use bitflags::bitflags;
 
@@ -16,14 +20,44 @@ bitflags! {
         const BACKUP = 2;
         const NO_FALLBACK_FOR_MOVE = 4;
         const TARGET_DEFAULT_PERMS = 8;
+        const NOFOLLOW_SYMLINKS = 16;
+        const ALL_METADATA = 32;
+        const PRESERVE_SPARSE = 64;
+        const VERIFY = 128;
+        const RESUMABLE = 256;
     }
 }
 
 pub type ProgressCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
 
-use crate::file::File;
+use crate::file::{File, FileQueryInfoFlags};
+use crate::file_info::FileInfo;
 use crate::cancellable::Cancellable;
-use crate::error::NpioResult;
+use crate::checksum::ChecksumType;
+use crate::error::{NpioError, NpioResult, IOErrorEnum};
+use crate::service::volumemonitor::{VolumeMonitor, VolumeMonitorEvent};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Checksum used to compare source and destination for `CopyFlags::VERIFY`.
+pub(crate) const VERIFY_CHECKSUM_TYPE: ChecksumType = ChecksumType::Blake3;
+
+/// Attributes copied along with the file contents unless `TARGET_DEFAULT_PERMS` is set.
+const DEFAULT_COPY_ATTRIBUTES: &[&str] = &["unix::mode"];
+
+/// Additional attributes copied when `ALL_METADATA` is set.
+const ALL_METADATA_COPY_ATTRIBUTES: &[&str] = &[
+    "unix::uid",
+    "unix::gid",
+    "time::modified",
+    "time::modified-nsec",
+    "time::access",
+    "time::access-nsec",
+    "acl::access",
+    "acl::default",
+];
 
 pub async fn copy(
     source: &dyn File,
@@ -32,9 +66,218 @@ pub async fn copy(
     progress: Option<ProgressCallback>,
     cancellable: Option<&Cancellable>,
 ) -> NpioResult<()> {
+    // `File::copy` verifies the copy itself when asked to
     source.copy(destination, flags, cancellable, progress).await
 }
 
+/// Copies `source` to `destination` with `CopyFlags::RESUMABLE`.
+///
+/// If a mount holding the source or destination disappears, the copy is paused
+/// rather than failed: it waits for `monitor` to report the mount again and then
+/// resumes from the last committed offset. The monitor has to be started for
+/// mount events to arrive. Cancelling `cancellable` stops the copy for good,
+/// leaving the journal behind so a later call can still continue it.
+pub async fn copy_resumable(
+    source: &dyn File,
+    destination: &dyn File,
+    flags: CopyFlags,
+    progress: Option<ProgressCallback>,
+    cancellable: Option<&Cancellable>,
+    monitor: &VolumeMonitor,
+) -> NpioResult<()> {
+    let flags = flags | CopyFlags::RESUMABLE;
+    let mut events = monitor.subscribe();
+    let affected_mounts = find_affected_mounts(source, destination, monitor).await;
+    let progress = progress.map(Arc::new);
+
+    loop {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let attempt = Cancellable::new();
+        let attempt_progress = progress.clone().map(|cb| {
+            Box::new(move |current, total| cb(current, total)) as ProgressCallback
+        });
+        let copy_future = source.copy(destination, flags, Some(&attempt), attempt_progress);
+        tokio::pin!(copy_future);
+
+        let mut paused = false;
+        let mut events_open = true;
+        let result = loop {
+            tokio::select! {
+                result = &mut copy_future => break result,
+                event = events.recv(), if events_open => match event {
+                    Ok(VolumeMonitorEvent::MountRemoved { mount }) if affected_mounts.contains(&mount) => {
+                        paused = true;
+                        attempt.cancel();
+                    }
+                    Err(broadcast::error::RecvError::Closed) => events_open = false,
+                    _ => {}
+                },
+                _ = wait_cancelled(cancellable), if !attempt.is_cancelled() => attempt.cancel(),
+            }
+        };
+
+        match result {
+            Ok(()) => break,
+            Err(e) => {
+                if cancellable.is_some_and(|c| c.is_cancelled()) {
+                    return Err(e);
+                }
+                // The copy may fail with an I/O error before the monitor notices the mount is gone
+                if !paused && missing_mounts(&affected_mounts, monitor).await.is_empty() {
+                    return Err(e);
+                }
+            }
+        }
+
+        wait_for_mounts(&affected_mounts, monitor, &mut events, cancellable).await?;
+    }
+
+    Ok(())
+}
+
+/// Mount points (as keyed by `VolumeMonitor`) holding `source` and `destination`.
+async fn find_affected_mounts(
+    source: &dyn File,
+    destination: &dyn File,
+    monitor: &VolumeMonitor,
+) -> HashSet<String> {
+    let mount_points: Vec<PathBuf> = monitor
+        .get_mounts()
+        .await
+        .iter()
+        .filter_map(|mount| mount.get_root().path())
+        .collect();
+
+    let mut affected = HashSet::new();
+    for path in [source.path(), destination.path()].into_iter().flatten() {
+        // The most specific mount point is the one the file lives on
+        let mount_point = mount_points
+            .iter()
+            .filter(|mount_point| path.starts_with(mount_point))
+            .max_by_key(|mount_point| mount_point.as_os_str().len());
+        if let Some(mount_point) = mount_point {
+            affected.insert(mount_point.to_string_lossy().to_string());
+        }
+    }
+    affected
+}
+
+/// The entries of `mounts` that are currently not mounted.
+async fn missing_mounts(mounts: &HashSet<String>, monitor: &VolumeMonitor) -> HashSet<String> {
+    let present: HashSet<String> = monitor
+        .get_mounts()
+        .await
+        .iter()
+        .filter_map(|mount| mount.get_root().path())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    mounts.difference(&present).cloned().collect()
+}
+
+/// Waits until all of `mounts` are mounted again.
+async fn wait_for_mounts(
+    mounts: &HashSet<String>,
+    monitor: &VolumeMonitor,
+    events: &mut broadcast::Receiver<VolumeMonitorEvent>,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<()> {
+    while !missing_mounts(mounts, monitor).await.is_empty() {
+        tokio::select! {
+            event = events.recv() => {
+                if let Err(broadcast::error::RecvError::Closed) = event {
+                    return Err(NpioError::new(IOErrorEnum::Closed, "Volume monitor closed while copy was paused"));
+                }
+            }
+            _ = wait_cancelled(cancellable) => {
+                return Err(NpioError::new(IOErrorEnum::Cancelled, "Operation cancelled"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolves once `cancellable` is cancelled, never without one.
+async fn wait_cancelled(cancellable: Option<&Cancellable>) {
+    match cancellable {
+        Some(c) => c.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Re-reads `source` and `destination` and checks that their contents are identical.
+/// Returns an `IOErrorEnum::ChecksumMismatch` error if they differ.
+///
+/// A local destination is written to disk and evicted from the page cache first,
+/// so it is read back from storage.
+pub async fn verify_copy(
+    source: &dyn File,
+    destination: &dyn File,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<()> {
+    if let Some(path) = destination.path() {
+        tokio::task::spawn_blocking(move || {
+            crate::file::local::copy::drop_cached_pages(&std::fs::File::open(path)?)
+        }).await
+        .map_err(|e| NpioError::new(IOErrorEnum::Failed, format!("Join error: {}", e)))??;
+    }
+
+    let source_digest = source.compute_checksum(VERIFY_CHECKSUM_TYPE, cancellable, None).await?;
+    let destination_digest = destination.compute_checksum(VERIFY_CHECKSUM_TYPE, cancellable, None).await?;
+
+    if source_digest != destination_digest {
+        return Err(NpioError::new(
+            IOErrorEnum::ChecksumMismatch,
+            format!(
+                "Copy of {} differs from source: {} != {}",
+                source.uri(), destination_digest, source_digest
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Copies file metadata from `source` to `destination` according to `flags`.
+/// Backends without a native metadata-preserving copy use this after copying contents.
+pub async fn copy_attributes(
+    source: &dyn File,
+    destination: &dyn File,
+    flags: CopyFlags,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<()> {
+    let mut keys: Vec<&str> = Vec::new();
+    if !flags.contains(CopyFlags::TARGET_DEFAULT_PERMS) {
+        keys.extend_from_slice(DEFAULT_COPY_ATTRIBUTES);
+    }
+    if flags.contains(CopyFlags::ALL_METADATA) {
+        keys.extend_from_slice(ALL_METADATA_COPY_ATTRIBUTES);
+    }
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let source_info = source.query_info(&keys.join(","), cancellable).await?;
+
+    // Only pass on the requested keys, the source may report more than it was asked for
+    let mut info = FileInfo::new();
+    for key in keys {
+        if let Some(value) = source_info.get_attribute(key) {
+            info.set_attribute(key, value.clone());
+        }
+    }
+
+    let set_flags = if flags.contains(CopyFlags::NOFOLLOW_SYMLINKS) {
+        FileQueryInfoFlags::NOFOLLOW_SYMLINKS
+    } else {
+        FileQueryInfoFlags::NONE
+    };
+    destination.set_attributes_from_info(&info, set_flags, cancellable).await?;
+    Ok(())
+}
+
 pub async fn move_(
     source: &dyn File,
     destination: &dyn File,
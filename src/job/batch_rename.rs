@@ -0,0 +1,520 @@
+//! Batch rename job
+//!
+//! Renames a list of files after a template. A template is literal text with
+//! placeholders in braces:
+//!
+//! - `{name}` - original name without its extension
+//! - `{ext}` - original extension including the dot, empty if there is none
+//! - `{n}` / `{n:WIDTH}` - counter, optionally zero-padded to `WIDTH` digits
+//! - `{mtime}` / `{mtime:FORMAT}` - modification time, formatted with chrono's
+//!   strftime syntax (default `%Y-%m-%d`)
+//!
+//! `{{` and `}}` produce literal braces. After rendering, regex replacements
+//! are applied in order, followed by the case change.
+//!
+//! `preview` reports the new names along with any conflicts. `apply` only runs
+//! if there are none, and renames through `standard::display-name`; if any
+//! rename fails, the files renamed so far are renamed back.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone};
+use regex::Regex;
+
+use crate::cancellable::Cancellable;
+use crate::error::{NpioError, NpioResult, IOErrorEnum};
+use crate::file::{File, FileQueryInfoFlags};
+use crate::file_info::FileAttributeType;
+use crate::job::ProgressCallback;
+
+/// Format used for `{mtime}` without an explicit format.
+const DEFAULT_MTIME_FORMAT: &str = "%Y-%m-%d";
+
+/// Largest width accepted in `{n:WIDTH}`.
+const MAX_COUNTER_WIDTH: usize = 255;
+
+/// Prefix of the temporary names used while files swap names.
+const TEMPORARY_NAME_PREFIX: &str = ".npio-rename";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateToken {
+    Literal(String),
+    Stem,
+    Extension,
+    Counter { width: usize },
+    ModificationTime { format: String },
+}
+
+/// A parsed rename template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameTemplate {
+    tokens: Vec<TemplateToken>,
+}
+
+impl RenameTemplate {
+    /// Parses a template, see the module documentation for the syntax.
+    pub fn parse(template: &str) -> NpioResult<Self> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(NpioError::new(
+                                    IOErrorEnum::InvalidArg,
+                                    format!("Unterminated placeholder in template: {}", template),
+                                ));
+                            }
+                        }
+                    }
+                    if !literal.is_empty() {
+                        tokens.push(TemplateToken::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(Self::parse_placeholder(&placeholder)?);
+                }
+                '}' => {
+                    return Err(NpioError::new(
+                        IOErrorEnum::InvalidArg,
+                        format!("Unmatched '}}' in template: {}", template),
+                    ));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            tokens.push(TemplateToken::Literal(literal));
+        }
+
+        Ok(Self { tokens })
+    }
+
+    fn parse_placeholder(placeholder: &str) -> NpioResult<TemplateToken> {
+        let (name, argument) = match placeholder.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (placeholder, None),
+        };
+
+        match (name, argument) {
+            ("name", None) => Ok(TemplateToken::Stem),
+            ("ext", None) => Ok(TemplateToken::Extension),
+            ("n", None) => Ok(TemplateToken::Counter { width: 0 }),
+            ("n", Some(width)) => match width.parse::<usize>() {
+                Ok(width) if width <= MAX_COUNTER_WIDTH => Ok(TemplateToken::Counter { width }),
+                _ => Err(NpioError::new(
+                    IOErrorEnum::InvalidArg,
+                    format!("Invalid counter width: {}", width),
+                )),
+            },
+            ("mtime", format) => {
+                let format = format.unwrap_or(DEFAULT_MTIME_FORMAT);
+                // chrono only reports a bad format when it is rendered, and then by panicking
+                if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                    return Err(NpioError::new(
+                        IOErrorEnum::InvalidArg,
+                        format!("Invalid time format: {}", format),
+                    ));
+                }
+                Ok(TemplateToken::ModificationTime { format: format.to_string() })
+            }
+            _ => Err(NpioError::new(
+                IOErrorEnum::InvalidArg,
+                format!("Unknown placeholder: {{{}}}", placeholder),
+            )),
+        }
+    }
+
+    fn uses_modification_time(&self) -> bool {
+        self.tokens.iter().any(|token| matches!(token, TemplateToken::ModificationTime { .. }))
+    }
+}
+
+/// A regex find/replace step; `replacement` may refer to groups as `$1` or `${name}`.
+#[derive(Debug, Clone)]
+pub struct RenameReplacement {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RenameReplacement {
+    pub fn new(pattern: &str, replacement: &str) -> NpioResult<Self> {
+        let pattern = Regex::new(pattern).map_err(|e| NpioError::new(
+            IOErrorEnum::InvalidArg,
+            format!("Invalid pattern {}: {}", pattern, e),
+        ))?;
+        Ok(Self { pattern, replacement: replacement.to_string() })
+    }
+}
+
+/// Case change applied to the whole new name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseChange {
+    #[default]
+    Unchanged,
+    Lower,
+    Upper,
+    /// Capitalizes the first letter of every word and lowercases the rest.
+    Title,
+}
+
+impl CaseChange {
+    fn apply(&self, name: &str) -> String {
+        match self {
+            CaseChange::Unchanged => name.to_string(),
+            CaseChange::Lower => name.to_lowercase(),
+            CaseChange::Upper => name.to_uppercase(),
+            CaseChange::Title => {
+                let mut result = String::with_capacity(name.len());
+                let mut at_word_start = true;
+                for c in name.chars() {
+                    if c.is_alphanumeric() {
+                        if at_word_start {
+                            result.extend(c.to_uppercase());
+                        } else {
+                            result.extend(c.to_lowercase());
+                        }
+                        at_word_start = false;
+                    } else {
+                        result.push(c);
+                        at_word_start = true;
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Everything describing how a batch of files is renamed.
+#[derive(Debug, Clone)]
+pub struct BatchRename {
+    pub template: RenameTemplate,
+    /// Value of `{n}` for the first file.
+    pub counter_start: u64,
+    /// Increment of `{n}` from one file to the next.
+    pub counter_step: u64,
+    pub replacements: Vec<RenameReplacement>,
+    pub case_change: CaseChange,
+}
+
+impl BatchRename {
+    /// Creates a batch rename counting from 1, without replacements or case change.
+    pub fn new(template: RenameTemplate) -> Self {
+        Self {
+            template,
+            counter_start: 1,
+            counter_step: 1,
+            replacements: Vec::new(),
+            case_change: CaseChange::Unchanged,
+        }
+    }
+
+    /// Computes the new name of the file at `index` in the batch.
+    pub fn render(&self, old_name: &str, index: usize, modified: Option<DateTime<Local>>) -> String {
+        let (stem, extension) = split_extension(old_name);
+        let counter = self.counter_start + self.counter_step * index as u64;
+
+        let mut name = String::new();
+        for token in &self.template.tokens {
+            match token {
+                TemplateToken::Literal(text) => name.push_str(text),
+                TemplateToken::Stem => name.push_str(stem),
+                TemplateToken::Extension => name.push_str(extension),
+                TemplateToken::Counter { width } => {
+                    name.push_str(&format!("{:0width$}", counter, width = *width));
+                }
+                TemplateToken::ModificationTime { format } => {
+                    if let Some(modified) = modified {
+                        name.push_str(&modified.format(format).to_string());
+                    }
+                }
+            }
+        }
+
+        for replacement in &self.replacements {
+            name = replacement.pattern.replace_all(&name, replacement.replacement.as_str()).into_owned();
+        }
+
+        self.case_change.apply(&name)
+    }
+}
+
+/// Why a file cannot be renamed to its new name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameConflict {
+    /// The new name is empty, `.`, `..` or contains a `/`.
+    InvalidName,
+    /// Another file in the batch gets the same name in the same directory.
+    Duplicate,
+    /// A file that is not part of the batch already has the new name.
+    Exists,
+}
+
+/// Old and new name of one file of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePreview {
+    pub old_name: String,
+    pub new_name: String,
+    pub conflict: Option<RenameConflict>,
+}
+
+impl RenamePreview {
+    pub fn is_unchanged(&self) -> bool {
+        self.old_name == self.new_name
+    }
+}
+
+/// Computes the new names of `files`, in order, and detects conflicts between them.
+pub async fn preview(
+    files: &[Box<dyn File>],
+    rename: &BatchRename,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<Vec<RenamePreview>> {
+    let mut previews = Vec::with_capacity(files.len());
+    let mut directories = Vec::with_capacity(files.len());
+
+    for (index, file) in files.iter().enumerate() {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let modified = if rename.template.uses_modification_time() {
+            let info = file.query_info("time::modified", cancellable).await?;
+            match info.get_attribute("time::modified") {
+                Some(FileAttributeType::Uint64(seconds)) => {
+                    Local.timestamp_opt(*seconds as i64, 0).single()
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let old_name = file.basename();
+        let new_name = rename.render(&old_name, index, modified);
+        directories.push(file.parent().map(|parent| parent.uri()));
+        previews.push(RenamePreview { old_name, new_name, conflict: None });
+    }
+
+    for index in 0..previews.len() {
+        let new_name = &previews[index].new_name;
+        let directory = &directories[index];
+
+        let conflict = if directory.is_none() || !is_valid_name(new_name) {
+            Some(RenameConflict::InvalidName)
+        } else if previews.iter().enumerate().any(|(other, preview)| {
+            other != index && directories[other] == *directory && preview.new_name == *new_name
+        }) {
+            Some(RenameConflict::Duplicate)
+        } else if previews[index].is_unchanged() {
+            None
+        } else {
+            // A name that is only taken by a file of the batch is freed up by renaming that file
+            let taken_by_batch = previews.iter().enumerate().any(|(other, preview)| {
+                directories[other] == *directory && preview.old_name == *new_name
+            });
+            let parent = files[index].parent();
+            let taken = match parent {
+                Some(parent) => parent.child(new_name).exists(cancellable).await?,
+                None => false,
+            };
+            if taken && !taken_by_batch {
+                Some(RenameConflict::Exists)
+            } else {
+                None
+            }
+        };
+        previews[index].conflict = conflict;
+    }
+
+    Ok(previews)
+}
+
+/// Renames `files` as described by `rename`, returning the renamed files in order.
+///
+/// Nothing is renamed if `preview` reports a conflict. Files that swap names
+/// go through temporary names first. If a rename fails or the operation is
+/// cancelled, the files renamed so far get their old names back; files that
+/// cannot be renamed back are listed in a `PartialInput` error.
+pub async fn apply(
+    files: &[Box<dyn File>],
+    rename: &BatchRename,
+    progress: Option<ProgressCallback>,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<Vec<Box<dyn File>>> {
+    let previews = preview(files, rename, cancellable).await?;
+
+    for preview in &previews {
+        match preview.conflict {
+            Some(RenameConflict::InvalidName) => {
+                return Err(NpioError::new(
+                    IOErrorEnum::InvalidFilename,
+                    format!("Cannot rename {} to {}", preview.old_name, preview.new_name),
+                ));
+            }
+            Some(RenameConflict::Duplicate) | Some(RenameConflict::Exists) => {
+                return Err(NpioError::new(
+                    IOErrorEnum::Exists,
+                    format!("Cannot rename {} to {}: name is taken", preview.old_name, preview.new_name),
+                ));
+            }
+            None => {}
+        }
+    }
+
+    let mut parents = Vec::with_capacity(files.len());
+    for file in files {
+        let parent = file.parent().ok_or_else(|| NpioError::new(
+            IOErrorEnum::InvalidFilename,
+            format!("Cannot rename {}", file.uri()),
+        ))?;
+        parents.push(parent);
+    }
+
+    let changed: Vec<usize> = (0..previews.len())
+        .filter(|&index| !previews[index].is_unchanged())
+        .collect();
+
+    // Only needed when a new name is the old name of another file in the batch
+    let needs_temporary_names = changed.iter().any(|&index| {
+        changed.iter().any(|&other| {
+            other != index
+                && parents[other].uri() == parents[index].uri()
+                && previews[other].old_name == previews[index].new_name
+        })
+    });
+
+    let mut completed: Vec<RenameStep> = Vec::new();
+    let result = async {
+        let mut current_names: Vec<String> = previews.iter().map(|p| p.old_name.clone()).collect();
+
+        if needs_temporary_names {
+            for &index in &changed {
+                let temporary_name = unused_temporary_name(&*parents[index], index, cancellable).await?;
+                let step = RenameStep { index, from: current_names[index].clone(), to: temporary_name };
+                rename_step(&parents, &step, cancellable).await?;
+                current_names[index] = step.to.clone();
+                completed.push(step);
+            }
+        }
+
+        for (done, &index) in changed.iter().enumerate() {
+            let step = RenameStep {
+                index,
+                from: current_names[index].clone(),
+                to: previews[index].new_name.clone(),
+            };
+            rename_step(&parents, &step, cancellable).await?;
+            current_names[index] = step.to.clone();
+            completed.push(step);
+
+            if let Some(ref cb) = progress {
+                cb(done as u64 + 1, changed.len() as u64);
+            }
+        }
+        Ok::<(), NpioError>(())
+    }.await;
+
+    if let Err(e) = result {
+        // Files whose rollback failed stay where that left them; earlier steps cannot be undone
+        let mut stuck: Vec<usize> = Vec::new();
+        let mut left_behind = Vec::new();
+        for step in completed.iter().rev() {
+            if stuck.contains(&step.index) {
+                continue;
+            }
+            let undo = RenameStep { index: step.index, from: step.to.clone(), to: step.from.clone() };
+            // Rolling back must not be stopped by the cancellation that may have caused it
+            if let Err(undo_error) = rename_step(&parents, &undo, None).await {
+                stuck.push(step.index);
+                left_behind.push(format!(
+                    "{} (was {}): {}",
+                    parents[step.index].child(&undo.from).uri(),
+                    previews[step.index].old_name,
+                    undo_error,
+                ));
+            }
+        }
+        if !left_behind.is_empty() {
+            return Err(NpioError::new(
+                IOErrorEnum::PartialInput,
+                format!("{}; could not rename back: {}", e, left_behind.join(", ")),
+            ));
+        }
+        return Err(e);
+    }
+
+    Ok(parents
+        .iter()
+        .zip(&previews)
+        .map(|(parent, preview)| parent.child(&preview.new_name))
+        .collect())
+}
+
+/// A temporary name for the file at `index` that is not taken in `parent`, so a name left
+/// over from an earlier run is never replaced.
+async fn unused_temporary_name(
+    parent: &dyn File,
+    index: usize,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<String> {
+    let base = format!("{}-{}-{}", TEMPORARY_NAME_PREFIX, std::process::id(), index);
+    let mut name = base.clone();
+    let mut attempt = 0u32;
+    while parent.child(&name).exists(cancellable).await? {
+        attempt += 1;
+        name = format!("{}.{}", base, attempt);
+    }
+    Ok(name)
+}
+
+/// One rename of a file of the batch inside its directory.
+struct RenameStep {
+    index: usize,
+    from: String,
+    to: String,
+}
+
+async fn rename_step(
+    parents: &[Box<dyn File>],
+    step: &RenameStep,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<()> {
+    if let Some(c) = cancellable {
+        c.check()?;
+    }
+    parents[step.index]
+        .child(&step.from)
+        .set_attribute(
+            "standard::display-name",
+            &FileAttributeType::String(step.to.clone()),
+            FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+            cancellable,
+        )
+        .await
+}
+
+/// Splits a name into stem and extension; a leading dot does not start an extension.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(position) if position > 0 => name.split_at(position),
+        _ => (name, ""),
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') && !name.contains('\0')
+}
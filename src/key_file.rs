@@ -0,0 +1,232 @@
+//! The `[Group]` / `Key=Value` format of desktop entries, `mimeapps.list` and `index.theme`
+//!
+//! Comments, blank lines and the order of groups and keys survive a round
+//! trip, so a rewritten `mimeapps.list` only differs where it was changed.
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+enum Line {
+    Entry { key: String, value: String },
+    /// Comments and blank lines, kept as they were.
+    Other(String),
+}
+
+#[derive(Debug, Clone)]
+struct Group {
+    name: String,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyFile {
+    /// Lines before the first group header.
+    header: Vec<String>,
+    groups: Vec<Group>,
+}
+
+impl KeyFile {
+    pub(crate) fn parse(contents: &str) -> Self {
+        let mut key_file = Self::default();
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                key_file.groups.push(Group { name: name.to_string(), lines: Vec::new() });
+                continue;
+            }
+            let parsed = match trimmed.split_once('=') {
+                Some((key, value)) if !trimmed.starts_with('#') => {
+                    Line::Entry { key: key.trim().to_string(), value: value.trim_start().to_string() }
+                }
+                _ => Line::Other(line.to_string()),
+            };
+            match (key_file.groups.last_mut(), parsed) {
+                (Some(group), parsed) => group.lines.push(parsed),
+                // Keys outside a group are invalid and dropped
+                (None, Line::Other(line)) => key_file.header.push(line),
+                (None, Line::Entry { .. }) => {}
+            }
+        }
+        key_file
+    }
+
+    fn group(&self, group: &str) -> Option<&Group> {
+        self.groups.iter().find(|candidate| candidate.name == group)
+    }
+
+    pub(crate) fn has_group(&self, group: &str) -> bool {
+        self.group(group).is_some()
+    }
+
+    /// The raw value of `key`; the last one wins if it is repeated.
+    pub(crate) fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.group(group)?.lines.iter().rev().find_map(|line| match line {
+            Line::Entry { key: candidate, value } if candidate == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// A string value with its escape sequences replaced.
+    pub(crate) fn get_string(&self, group: &str, key: &str) -> Option<String> {
+        self.get(group, key).map(unescape)
+    }
+
+    /// The value of `key[locale]` for the most specific variant of `locale` there is, else of `key`.
+    pub(crate) fn get_locale_string(&self, group: &str, key: &str, locale: Option<&str>) -> Option<String> {
+        locale
+            .map(crate::locale::locale_variants)
+            .unwrap_or_default()
+            .iter()
+            .find_map(|variant| self.get_string(group, &format!("{}[{}]", key, variant)))
+            .or_else(|| self.get_string(group, key))
+    }
+
+    pub(crate) fn get_bool(&self, group: &str, key: &str) -> bool {
+        self.get(group, key) == Some("true")
+    }
+
+    /// A `;`-separated list; `\;` stands for a semicolon within an item.
+    pub(crate) fn get_string_list(&self, group: &str, key: &str) -> Vec<String> {
+        self.get(group, key).map(split_list).unwrap_or_default()
+    }
+
+    /// A `,`-separated list, as used by icon themes.
+    pub(crate) fn get_comma_list(&self, group: &str, key: &str) -> Vec<String> {
+        self.get(group, key)
+            .map(|value| {
+                value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn get_u32(&self, group: &str, key: &str) -> Option<u32> {
+        self.get(group, key)?.trim().parse().ok()
+    }
+
+    /// The keys of `group` in file order, without repetitions.
+    pub(crate) fn keys(&self, group: &str) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::new();
+        for line in self.group(group).map(|group| group.lines.as_slice()).unwrap_or_default() {
+            if let Line::Entry { key, .. } = line {
+                if !keys.contains(&key.as_str()) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// Sets a list value, replacing every line of `key`; an empty list removes the key.
+    pub(crate) fn set_string_list(&mut self, group: &str, key: &str, values: &[String]) {
+        if values.is_empty() {
+            self.remove(group, key);
+            return;
+        }
+        let value = values.iter().map(|value| format!("{};", escape_list_item(value))).collect::<String>();
+        let index = match self.groups.iter().position(|candidate| candidate.name == group) {
+            Some(index) => index,
+            None => {
+                self.groups.push(Group { name: group.to_string(), lines: Vec::new() });
+                self.groups.len() - 1
+            }
+        };
+        let lines = &mut self.groups[index].lines;
+        match lines.iter().position(|line| matches!(line, Line::Entry { key: candidate, .. } if candidate == key)) {
+            Some(position) => {
+                lines[position] = Line::Entry { key: key.to_string(), value };
+                // Repetitions of the key would otherwise win
+                let mut seen = false;
+                lines.retain(|line| match line {
+                    Line::Entry { key: candidate, .. } if candidate == key => !std::mem::replace(&mut seen, true),
+                    _ => true,
+                });
+            }
+            None => {
+                // After the last entry, so trailing blank lines stay between groups
+                let position = lines
+                    .iter()
+                    .rposition(|line| matches!(line, Line::Entry { .. }))
+                    .map_or(0, |position| position + 1);
+                lines.insert(position, Line::Entry { key: key.to_string(), value });
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, group: &str, key: &str) {
+        if let Some(group) = self.groups.iter_mut().find(|candidate| candidate.name == group) {
+            group.lines.retain(|line| !matches!(line, Line::Entry { key: candidate, .. } if candidate == key));
+        }
+    }
+}
+
+impl fmt::Display for KeyFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.header {
+            writeln!(f, "{}", line)?;
+        }
+        for group in &self.groups {
+            writeln!(f, "[{}]", group.name)?;
+            for line in &group.lines {
+                match line {
+                    Line::Entry { key, value } => writeln!(f, "{}={}", key, value)?,
+                    Line::Other(line) => writeln!(f, "{}", line)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces `\s`, `\n`, `\t`, `\r` and `\\`.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => item.push(';'),
+                Some(other) => {
+                    item.push('\\');
+                    item.push(other);
+                }
+                None => item.push('\\'),
+            },
+            ';' => items.push(unescape(&std::mem::take(&mut item))),
+            c => item.push(c),
+        }
+    }
+    if !item.is_empty() {
+        items.push(unescape(&item));
+    }
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+fn escape_list_item(item: &str) -> String {
+    item.replace('\\', "\\\\").replace(';', "\\;")
+}
@@ -45,39 +45,62 @@
 //! # }
 //! ```
 
+pub mod acl;
+pub mod app_info;
 pub mod backend;
 pub mod cancellable;
+pub mod checksum;
 pub mod drive;
 pub mod error;
 pub mod file;
 pub mod file_enumerator;
 pub mod file_info;
+pub mod icon;
 pub mod iostream;
 pub mod job;
+mod key_file;
+mod locale;
 pub mod metadata;
 pub mod model;
 pub mod monitor;
 pub mod mount;
+pub mod places;
 pub mod service;
+pub mod tags;
 pub mod volume;
+mod xdg;
 
+pub use acl::{Acl, AclEntry, AclKind, AclPermissions, AclTag};
+pub use app_info::{AppInfo, AppLaunchContext};
 pub use backend::{Backend, BackendRegistry, get_file_for_uri, register_backend};
 pub use backend::mount::MountBackend;
 pub use backend::udisks2::UDisks2Backend;
 pub use cancellable::Cancellable;
+pub use checksum::{Checksum, ChecksumType};
 pub use drive::Drive;
 pub use error::{NpioError, NpioResult, IOErrorEnum};
 pub use file::{File, FileQueryInfoFlags};
 pub use file_enumerator::FileEnumerator;
-pub use file_info::{FileInfo, FileAttributeType, FileType};
+pub use file_info::{
+    FileInfo, FileAttributeInfo, FileAttributeInfoFlags, FileAttributeKind, FileAttributeMatcher,
+    FileAttributeStatus, FileAttributeType, FilesystemPreviewType, FileType, FILE_INFO_FORMAT_VERSION,
+};
+pub use file_info::registry::FileAttributeDescription;
+pub use icon::Icon;
+pub use icon::theme::IconTheme;
 pub use iostream::{InputStream, OutputStream};
 pub use metadata::MimeResolver;
+pub use metadata::database::MimeDatabase;
 pub use model::directory::{DirectoryModel, DirectoryUpdate};
 pub use model::devices::DevicesModel;
 pub use monitor::{FileMonitor, FileMonitorEvent};
 pub use mount::Mount;
+pub use places::{PlacesEvent, PlacesMonitor};
+pub use places::bookmarks::{Bookmark, Bookmarks};
+pub use places::user_dirs::{UserDirectory, UserDirs};
 pub use job::{CopyFlags, ProgressCallback, trash};
 pub use service::thumbnail::{ThumbnailService, ThumbnailEvent, ThumbnailImage, ThumbnailImageCache};
 pub use service::volumemonitor::{VolumeMonitor, VolumeMonitorEvent};
+pub use tags::{find_tagged_files, TagColor, Tags};
 pub use backend::thumbnail::{ThumbnailBackend, ThumbnailSize};
 pub use volume::Volume;
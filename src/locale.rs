@@ -0,0 +1,39 @@
+//! Locale names, as the desktop entry and shared-mime-info specifications match them
+
+/// The locale of messages from the environment, as gettext looks it up.
+pub(crate) fn environment_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The names to try for `locale` (`lang_COUNTRY.ENCODING@MODIFIER`), most specific first.
+pub(crate) fn locale_variants(locale: &str) -> Vec<String> {
+    if locale == "C" || locale == "POSIX" {
+        return Vec::new();
+    }
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    // The encoding plays no part in the lookup
+    let locale = locale.split('.').next().unwrap_or(locale);
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+
+    let mut variants = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_string());
+    variants
+}
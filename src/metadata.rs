@@ -1,22 +1,90 @@
+mod comment;
+pub mod database;
+mod glob;
+mod magic;
+mod tree_magic;
+
 use std::path::Path;
 use mime_guess::MimeGuess;
 
+use crate::cancellable::Cancellable;
+use crate::error::NpioResult;
+use crate::file::File;
+use crate::icon::Icon;
+use database::{MimeDatabase, OCTET_STREAM_TYPE};
+
+const DIRECTORY_TYPE: &str = "inode/directory";
+const FOLDER_ICON: &str = "folder";
+
 pub struct MimeResolver;
 
 impl MimeResolver {
-    /// Guesses the MIME type for a file path.
-    /// Currently only uses extension-based guessing for performance and async-friendliness.
-    /// Content sniffing (using `infer`) can be added later if needed, but requires reading file content.
+    /// Guesses the MIME type for a file path from its name alone, using the shared-mime-info
+    /// file name patterns. Without a shared-mime-info database the extension is looked up
+    /// in `mime_guess`'s table.
     pub fn guess_mime_type(path: &Path) -> String {
-        let guess = MimeGuess::from_path(path);
-        guess.first_or_octet_stream().to_string()
+        let database = MimeDatabase::global();
+        if !database.has_globs() {
+            let guess = MimeGuess::from_path(path);
+            return guess.first_or_octet_stream().to_string();
+        }
+        path.file_name()
+            .and_then(|name| database.types_for_file_name(&name.to_string_lossy()).into_iter().next())
+            .unwrap_or_else(|| OCTET_STREAM_TYPE.to_string())
+    }
+
+    /// Guesses the MIME type from a file name and the leading bytes of the file.
+    /// See `MimeDatabase::guess`.
+    pub fn guess_content_type(file_name: Option<&str>, data: &[u8]) -> String {
+        MimeDatabase::global().guess(file_name, Some(data))
+    }
+
+    /// Guesses the MIME type of a file from its name and the bytes `File::read` returns first.
+    pub async fn sniff_content_type<F: File + ?Sized>(file: &F, cancellable: Option<&Cancellable>) -> NpioResult<String> {
+        MimeDatabase::global().sniff(file, cancellable).await
+    }
+
+    /// Guesses the `x-content/*` types of the directory tree at `root`, the root of a mount
+    /// for instance. See `MimeDatabase::guess_for_tree`.
+    pub async fn guess_content_type_for_tree(root: &dyn File, cancellable: Option<&Cancellable>) -> NpioResult<Vec<String>> {
+        MimeDatabase::global().guess_for_tree(root, cancellable).await
     }
 
-    /// Gets the icon name for a given MIME type.
-    /// Follows the freedesktop.org Icon Naming Specification.
+    /// Gets the icon name for a given MIME type: the best of `MimeDatabase::icon_names`,
+    /// following the freedesktop.org Icon Naming Specification. Directories get `folder`.
     pub fn get_icon_name(mime_type: &str) -> String {
-        // Replace '/' with '-' to get the generic icon name
-        // e.g. "text/plain" -> "text-plain"
-        mime_type.replace('/', "-")
+        if mime_type == DIRECTORY_TYPE {
+            return FOLDER_ICON.to_string();
+        }
+        MimeDatabase::global().icon_names(mime_type).swap_remove(0)
+    }
+
+    /// Gets the symbolic icon name for a given MIME type.
+    pub fn get_symbolic_icon_name(mime_type: &str) -> String {
+        if mime_type == DIRECTORY_TYPE {
+            return format!("{}-symbolic", FOLDER_ICON);
+        }
+        MimeDatabase::global().symbolic_icon_names(mime_type).swap_remove(0)
+    }
+
+    /// Gets the icon for a given MIME type, with its fallbacks, for `IconTheme::lookup_icon`.
+    pub fn get_icon(mime_type: &str) -> Icon {
+        if mime_type == DIRECTORY_TYPE {
+            return Icon::themed(FOLDER_ICON);
+        }
+        Icon::for_content_type(mime_type)
+    }
+
+    /// Gets the symbolic icon for a given MIME type, with its fallbacks.
+    pub fn get_symbolic_icon(mime_type: &str) -> Icon {
+        if mime_type == DIRECTORY_TYPE {
+            return Icon::themed(&format!("{}-symbolic", FOLDER_ICON));
+        }
+        Icon::symbolic_for_content_type(mime_type)
+    }
+
+    /// Gets the human readable description of a MIME type in the current locale.
+    pub fn get_description(mime_type: &str) -> Option<String> {
+        MimeDatabase::global().description(mime_type, None)
     }
 }
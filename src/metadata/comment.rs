@@ -0,0 +1,100 @@
+//! Descriptions from the per-type XML files
+//!
+//! `update-mime-database` writes `<media>/<subtype>.xml` for every type, holding
+//! `<comment>` elements in each language, the untranslated one without
+//! `xml:lang`. Only those elements are read, so no XML parser is needed.
+
+const COMMENT_OPEN: &str = "<comment";
+const COMMENT_CLOSE: &str = "</comment>";
+const LANG_ATTRIBUTE: &str = "xml:lang=";
+
+/// Comments of a type: the language (`None` for the untranslated one) and the text.
+pub(super) type Comments = Vec<(Option<String>, String)>;
+
+/// Replaces the predefined entities and character references.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match replacement {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The value of `xml:lang` in the attributes of a start tag.
+fn lang_attribute(attributes: &str) -> Option<String> {
+    let value = &attributes[attributes.find(LANG_ATTRIBUTE)? + LANG_ATTRIBUTE.len()..];
+    let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+/// The `<comment>` elements of a type's XML file.
+pub(super) fn parse_comments(xml: &str) -> Comments {
+    let mut comments = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(COMMENT_OPEN) {
+        rest = &rest[start + COMMENT_OPEN.len()..];
+        // `<comment>` or `<comment xml:lang="..">`, not some other element sharing the prefix
+        if !rest.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let attributes = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        if attributes.ends_with('/') {
+            continue;
+        }
+        let Some(text_end) = rest.find(COMMENT_CLOSE) else {
+            break;
+        };
+        comments.push((lang_attribute(attributes), unescape(rest[..text_end].trim())));
+        rest = &rest[text_end + COMMENT_CLOSE.len()..];
+    }
+    comments
+}
+
+/// The comment for the first matching locale variant, else the untranslated one.
+pub(super) fn select(comments: &Comments, locale: Option<&str>) -> Option<String> {
+    let variants = locale.map(crate::locale::locale_variants).unwrap_or_default();
+    variants
+        .iter()
+        .find_map(|variant| {
+            comments
+                .iter()
+                .find(|(lang, _)| lang.as_deref() == Some(variant.as_str()))
+        })
+        .or_else(|| comments.iter().find(|(lang, _)| lang.is_none()))
+        .map(|(_, text)| text.clone())
+}
@@ -0,0 +1,424 @@
+//! The shared-mime-info database
+//!
+//! Loads the `globs2`, `magic`, `treemagic`, `aliases`, `subclasses`, `icons`
+//! and `generic-icons` files that `update-mime-database` writes to the `mime`
+//! directory of every XDG data directory. Less important directories are read
+//! first, so the user's own definitions in `$XDG_DATA_HOME/mime` override the
+//! system ones. Descriptions come from the per-type XML files, read on demand.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use once_cell::sync::Lazy;
+use tokio::io::AsyncReadExt;
+
+use super::comment::{self, Comments};
+use super::glob::{self, Glob};
+use super::magic::{self, MagicRule};
+use super::tree_magic::{self, TreeMagicRule};
+use crate::cancellable::Cancellable;
+use crate::error::NpioResult;
+use crate::file::File;
+
+/// Subdirectory of each XDG data directory holding the database.
+const MIME_DIR: &str = "mime";
+const GLOBS2_FILE: &str = "globs2";
+const MAGIC_FILE: &str = "magic";
+const TREE_MAGIC_FILE: &str = "treemagic";
+const ALIASES_FILE: &str = "aliases";
+const SUBCLASSES_FILE: &str = "subclasses";
+const ICONS_FILE: &str = "icons";
+const GENERIC_ICONS_FILE: &str = "generic-icons";
+/// Suffix of the per-type files holding descriptions.
+const TYPE_XML_SUFFIX: &str = ".xml";
+/// Suffix of the icon names symbolic icon themes provide.
+const SYMBOLIC_SUFFIX: &str = "-symbolic";
+
+/// How often the global database checks whether its files changed.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bytes read to sniff a file at least, for `infer` and the text check.
+const MIN_SNIFF_LENGTH: usize = 8 * 1024;
+/// Upper bound for the bytes read to sniff a file, whatever the magic rules ask for.
+const MAX_SNIFF_LENGTH: usize = 64 * 1024;
+
+pub(crate) const OCTET_STREAM_TYPE: &str = "application/octet-stream";
+pub(crate) const TEXT_PLAIN_TYPE: &str = "text/plain";
+/// The type of empty files that no file name pattern matches.
+pub(crate) const ZERO_SIZE_TYPE: &str = "application/x-zerosize";
+/// URI scheme of audio CDs, which have no files to look at.
+const CDDA_SCHEME: &str = "cdda://";
+const AUDIO_CDDA_TYPE: &str = "x-content/audio-cdda";
+/// File marking a portable music player's storage, from HAL's days.
+const AUDIO_PLAYER_MARKER: &str = ".is_audio_player";
+const AUDIO_PLAYER_TYPE: &str = "x-content/audio-player";
+
+/// Media type whose members all derive from `text/plain`.
+const TEXT_MEDIA_TYPE: &str = "text";
+/// Media type of things without a byte stream, which do not derive from `application/octet-stream`.
+const INODE_MEDIA_TYPE: &str = "inode";
+
+struct GlobalDatabase {
+    database: Arc<MimeDatabase>,
+    checked: Instant,
+}
+
+static GLOBAL_DATABASE: Lazy<Mutex<Option<GlobalDatabase>>> = Lazy::new(|| Mutex::new(None));
+
+/// Types by file name patterns and by contents.
+#[derive(Debug, Default)]
+pub struct MimeDatabase {
+    /// The `mime` directories, most important first.
+    dirs: Vec<PathBuf>,
+    /// Modification times of the files read, to notice updates.
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+    globs: Vec<Glob>,
+    /// Highest priority first.
+    magic: Vec<MagicRule>,
+    sniff_length: usize,
+    /// Highest priority first.
+    tree_magic: Vec<TreeMagicRule>,
+    /// Alias to canonical type.
+    aliases: HashMap<String, String>,
+    /// Canonical type to the types it derives from.
+    parents: HashMap<String, Vec<String>>,
+    icons: HashMap<String, String>,
+    generic_icons: HashMap<String, String>,
+    /// Comments of the types looked up so far, `None` for types without an XML file.
+    comments: Mutex<HashMap<String, Option<Comments>>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The lines of `aliases` and `subclasses` (`type other`) or the icon files (`type:icon`).
+fn parse_pairs(contents: &str, separator: char) -> impl Iterator<Item = (String, String)> + '_ {
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(move |line| line.split_once(separator))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+}
+
+/// Whether `mime_type` looks like `media/subtype`, so it can name a file below a `mime` directory.
+fn is_valid_type(mime_type: &str) -> bool {
+    mime_type.split_once('/').is_some_and(|(media, subtype)| {
+        [media, subtype].iter().all(|part| {
+            !part.is_empty() && !part.starts_with('.') && !part.contains(['/', '\\', '\0'])
+        })
+    })
+}
+
+/// The media type of `mime_type`, `text` for `text/html`.
+fn media_type(mime_type: &str) -> &str {
+    mime_type.split('/').next().unwrap_or(mime_type)
+}
+
+impl MimeDatabase {
+    /// Loads the database from `mime_dirs`, most important first. Missing files are skipped.
+    pub fn load(mime_dirs: &[PathBuf]) -> Self {
+        let mut database = Self { dirs: mime_dirs.to_vec(), ..Self::default() };
+        for dir in mime_dirs.iter().rev() {
+            let globs_path = dir.join(GLOBS2_FILE);
+            if let Ok(contents) = std::fs::read_to_string(&globs_path) {
+                glob::parse_globs2(&contents, &mut database.globs);
+            }
+            database.stamps.push((globs_path.clone(), modified(&globs_path)));
+
+            let magic_path = dir.join(MAGIC_FILE);
+            if let Ok(contents) = std::fs::read(&magic_path) {
+                let rules = magic::parse_magic(&contents);
+                // A directory that describes a type replaces the rules others had for it
+                database.magic.retain(|rule| !rules.iter().any(|new| new.mime_type == rule.mime_type));
+                database.magic.extend(rules);
+            }
+            database.stamps.push((magic_path.clone(), modified(&magic_path)));
+
+            let tree_magic_path = dir.join(TREE_MAGIC_FILE);
+            if let Ok(contents) = std::fs::read(&tree_magic_path) {
+                let rules = tree_magic::parse_tree_magic(&contents);
+                database.tree_magic.retain(|rule| !rules.iter().any(|new| new.mime_type == rule.mime_type));
+                database.tree_magic.extend(rules);
+            }
+            database.stamps.push((tree_magic_path.clone(), modified(&tree_magic_path)));
+
+            let aliases_path = dir.join(ALIASES_FILE);
+            if let Ok(contents) = std::fs::read_to_string(&aliases_path) {
+                database.aliases.extend(parse_pairs(&contents, ' '));
+            }
+            database.stamps.push((aliases_path.clone(), modified(&aliases_path)));
+
+            let subclasses_path = dir.join(SUBCLASSES_FILE);
+            if let Ok(contents) = std::fs::read_to_string(&subclasses_path) {
+                // As with magic, a directory's parents of a type replace those of others
+                let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+                for (mime_type, parent) in parse_pairs(&contents, ' ') {
+                    parents.entry(mime_type).or_default().push(parent);
+                }
+                database.parents.extend(parents);
+            }
+            database.stamps.push((subclasses_path.clone(), modified(&subclasses_path)));
+
+            for (file, icons) in [(ICONS_FILE, &mut database.icons), (GENERIC_ICONS_FILE, &mut database.generic_icons)] {
+                let path = dir.join(file);
+                if let Ok(contents) = std::fs::read_to_string(&path) {
+                    icons.extend(parse_pairs(&contents, ':'));
+                }
+                database.stamps.push((path.clone(), modified(&path)));
+            }
+        }
+        // Stable, so rules of equal priority keep the order of the files
+        database.magic.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        database.tree_magic.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        database.sniff_length = database
+            .magic
+            .iter()
+            .map(MagicRule::extent)
+            .fold(MIN_SNIFF_LENGTH, usize::max)
+            .min(MAX_SNIFF_LENGTH);
+        database
+    }
+
+    /// The `mime` directories of the XDG data directories, most important first.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        crate::xdg::data_search_dirs().into_iter().map(|dir| dir.join(MIME_DIR)).collect()
+    }
+
+    /// The database of the XDG data directories, reloaded when its files change.
+    pub fn global() -> Arc<MimeDatabase> {
+        let mut global = match GLOBAL_DATABASE.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on MIME database: {}", e);
+                e.into_inner()
+            }
+        };
+        let needs_load = match global.as_mut() {
+            None => true,
+            Some(global) if global.checked.elapsed() >= RELOAD_CHECK_INTERVAL => {
+                global.checked = Instant::now();
+                global.database.is_outdated()
+            }
+            Some(_) => false,
+        };
+        if needs_load {
+            *global = Some(GlobalDatabase {
+                database: Arc::new(MimeDatabase::load(&MimeDatabase::default_dirs())),
+                checked: Instant::now(),
+            });
+        }
+        global.as_ref().map(|global| global.database.clone()).unwrap_or_default()
+    }
+
+    fn is_outdated(&self) -> bool {
+        self.dirs != MimeDatabase::default_dirs()
+            || self.stamps.iter().any(|(path, stamp)| modified(path) != *stamp)
+    }
+
+    /// Whether any file name patterns were found.
+    pub fn has_globs(&self) -> bool {
+        !self.globs.is_empty()
+    }
+
+    /// How many leading bytes of a file to pass to `guess`.
+    pub fn sniff_length(&self) -> usize {
+        self.sniff_length
+    }
+
+    /// The types whose file name patterns fit `file_name` best; several if they are equally good.
+    pub fn types_for_file_name(&self, file_name: &str) -> Vec<String> {
+        glob::match_name(&self.globs, file_name)
+    }
+
+    /// The type the magic rules find in `data`, the leading bytes of a file.
+    pub fn type_for_data(&self, data: &[u8]) -> Option<&str> {
+        self.magic
+            .iter()
+            .find(|rule| rule.matches(data))
+            .map(|rule| rule.mime_type.as_str())
+    }
+
+    /// Guesses a type from a file name and the leading bytes of the file, like GIO's
+    /// `g_content_type_guess`. The file name decides unless its patterns are ambiguous or
+    /// missing; then the contents do, through the magic rules, the `infer` crate and
+    /// finally a check for text.
+    pub fn guess(&self, file_name: Option<&str>, data: Option<&[u8]>) -> String {
+        let globbed = file_name.map(|name| self.types_for_file_name(name)).unwrap_or_default();
+        let sniffed = data.and_then(|data| self.type_for_data(data));
+
+        if let Some(sniffed) = sniffed {
+            // Contents that agree with one of the candidates settle an ambiguous name
+            if globbed.iter().any(|candidate| candidate == sniffed) {
+                return sniffed.to_string();
+            }
+            // A zip file named `.odt` is an OpenDocument text
+            if let Some(candidate) = globbed.iter().find(|candidate| self.is_a(candidate, sniffed)) {
+                return candidate.clone();
+            }
+        }
+        if let Some(first) = globbed.first() {
+            return first.clone();
+        }
+        if let Some(sniffed) = sniffed {
+            return sniffed.to_string();
+        }
+
+        match data {
+            Some([]) => ZERO_SIZE_TYPE.to_string(),
+            Some(data) => infer::get(data)
+                .map(|kind| kind.mime_type().to_string())
+                .unwrap_or_else(|| if looks_like_text(data) { TEXT_PLAIN_TYPE } else { OCTET_STREAM_TYPE }.to_string()),
+            None => OCTET_STREAM_TYPE.to_string(),
+        }
+    }
+
+    /// Guesses the type of `file` from its name and the bytes `File::read` returns first.
+    pub async fn sniff<F: File + ?Sized>(&self, file: &F, cancellable: Option<&Cancellable>) -> NpioResult<String> {
+        let mut input = file.read(cancellable).await?;
+        let mut data = vec![0u8; self.sniff_length];
+        let mut filled = 0;
+        while filled < data.len() {
+            if let Some(c) = cancellable {
+                c.check()?;
+            }
+            let n = input.read(&mut data[filled..]).await?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        input.close(cancellable)?;
+        data.truncate(filled);
+        Ok(self.guess(Some(&file.basename()), Some(&data)))
+    }
+
+    /// The `x-content/*` types of the directory tree at `root`, such as the root of a mounted
+    /// camera or DVD, like GIO's `g_content_type_guess_for_tree`; the most likely first.
+    pub async fn guess_for_tree(&self, root: &dyn File, cancellable: Option<&Cancellable>) -> NpioResult<Vec<String>> {
+        if root.uri().starts_with(CDDA_SCHEME) {
+            return Ok(vec![AUDIO_CDDA_TYPE.to_string()]);
+        }
+        let mut types = Vec::new();
+        for rule in &self.tree_magic {
+            if let Some(c) = cancellable {
+                c.check()?;
+            }
+            if !types.contains(&rule.mime_type) && rule.matches(root, self, cancellable).await? {
+                types.push(rule.mime_type.clone());
+            }
+        }
+        if root.child(AUDIO_PLAYER_MARKER).query_info("standard::type", cancellable).await.is_ok() {
+            types.push(AUDIO_PLAYER_TYPE.to_string());
+        }
+        Ok(types)
+    }
+
+    /// The canonical name of `mime_type`, which may be an alias such as `application/x-pdf`.
+    pub fn canonicalize(&self, mime_type: &str) -> String {
+        self.aliases.get(mime_type).cloned().unwrap_or_else(|| mime_type.to_string())
+    }
+
+    /// The types `mime_type` directly derives from, as listed in `subclasses`.
+    pub fn parents(&self, mime_type: &str) -> Vec<String> {
+        self.parents.get(&self.canonicalize(mime_type)).cloned().unwrap_or_default()
+    }
+
+    /// Whether `mime_type` is `supertype` or derives from it, like GIO's `g_content_type_is_a`.
+    /// Besides `subclasses`, every `text/*` type is a `text/plain` and everything but `inode/*`
+    /// an `application/octet-stream`. A `supertype` of `media/*` matches the whole media type.
+    pub fn is_a(&self, mime_type: &str, supertype: &str) -> bool {
+        let supertype = self.canonicalize(supertype);
+        let mut visited = HashSet::new();
+        let mut pending = vec![self.canonicalize(mime_type)];
+        while let Some(current) = pending.pop() {
+            if current == supertype {
+                return true;
+            }
+            let media = media_type(&current);
+            let implicit = match supertype.as_str() {
+                TEXT_PLAIN_TYPE => media == TEXT_MEDIA_TYPE,
+                OCTET_STREAM_TYPE => media != INODE_MEDIA_TYPE,
+                _ => supertype.strip_suffix("/*") == Some(media),
+            };
+            if implicit {
+                return true;
+            }
+            if visited.insert(current.clone()) {
+                pending.extend(self.parents.get(&current).into_iter().flatten().map(|parent| self.canonicalize(parent)));
+            }
+        }
+        false
+    }
+
+    /// The description of `mime_type` in `locale` (such as `de_DE.UTF-8`), falling back to the
+    /// untranslated one. Without a locale the messages locale of the environment is used.
+    pub fn description(&self, mime_type: &str, locale: Option<&str>) -> Option<String> {
+        let mime_type = self.canonicalize(mime_type);
+        let mut comments = match self.comments.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("Failed to acquire lock on MIME descriptions: {}", e);
+                e.into_inner()
+            }
+        };
+        let comments = comments.entry(mime_type.clone()).or_insert_with(|| self.load_comments(&mime_type));
+        let locale = locale.map(str::to_string).or_else(crate::locale::environment_locale);
+        comment::select(comments.as_ref()?, locale.as_deref())
+    }
+
+    /// The comments in the most important directory with an XML file for `mime_type`.
+    fn load_comments(&self, mime_type: &str) -> Option<Comments> {
+        if !is_valid_type(mime_type) {
+            return None;
+        }
+        let file_name = format!("{}{}", mime_type, TYPE_XML_SUFFIX);
+        self.dirs
+            .iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join(&file_name)).ok())
+            .map(|xml| comment::parse_comments(&xml))
+    }
+
+    /// The icon of the generic kind of `mime_type`, such as `text-x-generic` or `folder`.
+    pub fn generic_icon_name(&self, mime_type: &str) -> String {
+        let mime_type = self.canonicalize(mime_type);
+        self.generic_icons
+            .get(&mime_type)
+            .cloned()
+            .unwrap_or_else(|| format!("{}-x-generic", media_type(&mime_type)))
+    }
+
+    /// Icon names for `mime_type`, best first: the one from `icons`, the type with `/` turned
+    /// into `-` and the generic icon, as the Icon Naming Specification suggests.
+    pub fn icon_names(&self, mime_type: &str) -> Vec<String> {
+        let mime_type = self.canonicalize(mime_type);
+        let mut names: Vec<String> = self.icons.get(&mime_type).cloned().into_iter().collect();
+        for name in [mime_type.replace('/', "-"), self.generic_icon_name(&mime_type)] {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// The symbolic variants of `icon_names`.
+    pub fn symbolic_icon_names(&self, mime_type: &str) -> Vec<String> {
+        self.icon_names(mime_type)
+            .into_iter()
+            .map(|name| format!("{}{}", name, SYMBOLIC_SUFFIX))
+            .collect()
+    }
+}
+
+/// Whether `data` reads as UTF-8 text; a character cut off at the end is fine.
+fn looks_like_text(data: &[u8]) -> bool {
+    if data.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(data) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
@@ -0,0 +1,143 @@
+//! shared-mime-info `globs2` files
+//!
+//! Each line is `weight:type:pattern[:flags]`. Patterns match file names
+//! case-insensitively unless the `cs` flag is given. A `__NOGLOBS__` pattern
+//! drops the patterns less important directories gave for that type.
+
+/// Pattern that discards earlier patterns of a type.
+const NO_GLOBS: &str = "__NOGLOBS__";
+
+/// Flag of case-sensitive patterns.
+const CASE_SENSITIVE_FLAG: &str = "cs";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Glob {
+    pub(super) weight: u32,
+    pub(super) mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+impl Glob {
+    fn matches(&self, name: &str, lowercase_name: &str) -> bool {
+        let name = if self.case_sensitive { name } else { lowercase_name };
+        if is_literal(&self.pattern) {
+            return self.pattern == name;
+        }
+        // Most patterns are a plain suffix such as `*.txt`
+        if let Some(suffix) = self.pattern.strip_prefix('*').filter(|suffix| is_literal(suffix)) {
+            return name.ends_with(suffix);
+        }
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        fnmatch(&pattern, &name)
+    }
+}
+
+fn is_literal(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '['])
+}
+
+/// Adds the patterns of a `globs2` file to `globs`, which holds those of less important directories.
+pub(super) fn parse_globs2(contents: &str, globs: &mut Vec<Glob>) {
+    for line in contents.lines().filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut fields = line.splitn(4, ':');
+        let (Some(weight), Some(mime_type), Some(pattern)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let Ok(weight) = weight.parse() else {
+            continue;
+        };
+        if pattern == NO_GLOBS {
+            globs.retain(|glob| glob.mime_type != mime_type);
+            continue;
+        }
+        let case_sensitive = fields.next().is_some_and(|flags| flags.split(',').any(|flag| flag == CASE_SENSITIVE_FLAG));
+        globs.push(Glob {
+            weight,
+            mime_type: mime_type.to_string(),
+            pattern: if case_sensitive { pattern.to_string() } else { pattern.to_lowercase() },
+            case_sensitive,
+        });
+    }
+}
+
+/// The types whose patterns match `name` best: the highest weight wins, then the longest pattern.
+pub(super) fn match_name(globs: &[Glob], name: &str) -> Vec<String> {
+    let lowercase_name = name.to_lowercase();
+    let mut best: Option<(u32, usize)> = None;
+    let mut types: Vec<String> = Vec::new();
+    for glob in globs.iter().filter(|glob| glob.matches(name, &lowercase_name)) {
+        let rank = (glob.weight, glob.pattern.len());
+        if best.is_some_and(|best| rank < best) {
+            continue;
+        }
+        if best != Some(rank) {
+            best = Some(rank);
+            types.clear();
+        }
+        if !types.contains(&glob.mime_type) {
+            types.push(glob.mime_type.clone());
+        }
+    }
+    types
+}
+
+/// Shell-style matching of `*`, `?` and `[...]` classes, as `fnmatch(3)` without flags.
+fn fnmatch(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*`: pattern position and name position
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        let matched = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(pattern, p, name[n]),
+            Some(&c) => (c == name[n]).then_some(p + 1),
+            None => None,
+        };
+        match (matched, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                n += 1;
+            }
+            (None, Some((star, start))) => {
+                p = star;
+                n = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the class starting at `pattern[start]`, returning the position after it.
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<usize> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let &class_char = pattern.get(i)?;
+        if class_char == ']' && !first {
+            break;
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&end| end != ']') {
+            found |= (class_char..=pattern[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class_char == c;
+            i += 1;
+        }
+    }
+    (found != negated).then_some(i + 1)
+}
@@ -0,0 +1,178 @@
+//! shared-mime-info `magic` files
+//!
+//! After the `MIME-Magic\0\n` header come sections headed `[priority:type]`,
+//! each a tree of matches, one per line:
+//! `[indent]>offset=<u16 length><value>[&<mask>][~word-size][+range]`.
+//! A match applies if its value is found at any offset in `offset..offset+range`
+//! and, when it has nested matches, one of those applies as well.
+
+const MAGIC_HEADER: &[u8] = b"MIME-Magic\0\n";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MagicMatch {
+    offset: usize,
+    range: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    children: Vec<MagicMatch>,
+}
+
+impl MagicMatch {
+    fn matches(&self, data: &[u8]) -> bool {
+        let found = (self.offset..self.offset + self.range).any(|start| {
+            let Some(window) = data.get(start..start + self.value.len()) else {
+                return false;
+            };
+            match &self.mask {
+                Some(mask) => window
+                    .iter()
+                    .zip(&self.value)
+                    .zip(mask)
+                    .all(|((byte, value), mask)| byte & mask == value & mask),
+                None => window == self.value.as_slice(),
+            }
+        });
+        found && (self.children.is_empty() || self.children.iter().any(|child| child.matches(data)))
+    }
+
+    /// How many leading bytes of a file this match can look at.
+    fn extent(&self) -> usize {
+        let own = self.offset + self.range - 1 + self.value.len();
+        self.children.iter().map(MagicMatch::extent).fold(own, usize::max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MagicRule {
+    pub(super) priority: u32,
+    pub(super) mime_type: String,
+    matches: Vec<MagicMatch>,
+}
+
+impl MagicRule {
+    pub(super) fn matches(&self, data: &[u8]) -> bool {
+        self.matches.iter().any(|magic_match| magic_match.matches(data))
+    }
+
+    pub(super) fn extent(&self) -> usize {
+        self.matches.iter().map(MagicMatch::extent).max().unwrap_or(0)
+    }
+}
+
+/// Reads a decimal number at `data[*position..]`, if there is one.
+fn parse_number(data: &[u8], position: &mut usize) -> Option<usize> {
+    let digits = data[*position..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let number = std::str::from_utf8(&data[*position..*position + digits]).ok()?.parse().ok()?;
+    *position += digits;
+    Some(number)
+}
+
+/// Parses one match line starting at `data[*position]`, which is left after the line.
+/// Returns the indent and the match; `None` for lines that cannot be understood.
+fn parse_match(data: &[u8], position: &mut usize) -> Option<(usize, MagicMatch)> {
+    let indent = parse_number(data, position).unwrap_or(0);
+    if data.get(*position) != Some(&b'>') {
+        return None;
+    }
+    *position += 1;
+    let offset = parse_number(data, position)?;
+    if data.get(*position) != Some(&b'=') {
+        return None;
+    }
+    *position += 1;
+    let length_bytes = data.get(*position..*position + 2)?;
+    let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+    *position += 2;
+    let mut value = data.get(*position..*position + length)?.to_vec();
+    *position += length;
+
+    let mut mask = None;
+    let mut word_size = 1;
+    let mut range = 1;
+    loop {
+        match data.get(*position) {
+            Some(b'&') => {
+                mask = Some(data.get(*position + 1..*position + 1 + length)?.to_vec());
+                *position += 1 + length;
+            }
+            Some(b'~') => {
+                *position += 1;
+                word_size = parse_number(data, position)?;
+            }
+            Some(b'+') => {
+                *position += 1;
+                range = parse_number(data, position)?.max(1);
+            }
+            Some(b'\n') => {
+                *position += 1;
+                break;
+            }
+            // Later versions of the format may add fields; the line is skipped
+            _ => return None,
+        }
+    }
+
+    // Values of multi-byte words are stored big-endian
+    if word_size > 1 && cfg!(target_endian = "little") {
+        for bytes in [Some(&mut value), mask.as_mut()].into_iter().flatten() {
+            for word in bytes.chunks_exact_mut(word_size) {
+                word.reverse();
+            }
+        }
+    }
+
+    Some((indent, MagicMatch { offset, range, value, mask, children: Vec::new() }))
+}
+
+/// Adds `magic_match` below the last match at `indent - 1`.
+fn insert(matches: &mut Vec<MagicMatch>, indent: usize, magic_match: MagicMatch) {
+    if indent == 0 {
+        matches.push(magic_match);
+    } else if let Some(parent) = matches.last_mut() {
+        insert(&mut parent.children, indent - 1, magic_match);
+    }
+}
+
+/// Parses a `magic` file; an unknown header gives no rules.
+pub(super) fn parse_magic(data: &[u8]) -> Vec<MagicRule> {
+    let Some(mut rest) = data.strip_prefix(MAGIC_HEADER) else {
+        return Vec::new();
+    };
+    let mut rules = Vec::new();
+    while let Some(header_end) = rest.iter().position(|&byte| byte == b'\n') {
+        let header = &rest[..header_end];
+        let section = header
+            .strip_prefix(b"[")
+            .and_then(|header| header.strip_suffix(b"]"))
+            .and_then(|header| std::str::from_utf8(header).ok())
+            .and_then(|header| header.split_once(':'));
+
+        // The section runs until the next line starting with `[`
+        let mut position = header_end + 1;
+        let mut matches = Vec::new();
+        while position < rest.len() && rest[position] != b'[' {
+            let start = position;
+            match parse_match(rest, &mut position) {
+                Some((indent, magic_match)) => insert(&mut matches, indent, magic_match),
+                None => {
+                    // Skip to the next line
+                    position = rest[start..]
+                        .iter()
+                        .position(|&byte| byte == b'\n')
+                        .map_or(rest.len(), |end| start + end + 1);
+                }
+            }
+        }
+
+        if let Some((priority, mime_type)) = section {
+            if let Ok(priority) = priority.parse() {
+                rules.push(MagicRule { priority, mime_type: mime_type.to_string(), matches });
+            }
+        }
+        rest = &rest[position..];
+    }
+    rules
+}
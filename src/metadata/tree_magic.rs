@@ -0,0 +1,227 @@
+//! shared-mime-info `treemagic` files
+//!
+//! After the `MIME-TreeMagic\0\n` header come sections headed
+//! `[priority:x-content/type]`, each a tree of matches, one per line:
+//! `[indent]>"path"=type[,flag...][,mime/type]`. The path is relative to the
+//! root of the tree and compared case-insensitively unless `match-case` is
+//! given; the type is `file`, `directory`, `link` or `any`. As with `magic`, a
+//! match with nested matches only applies if one of those applies as well.
+
+use futures::future::BoxFuture;
+
+use super::database::MimeDatabase;
+use crate::cancellable::Cancellable;
+use crate::error::{IOErrorEnum, NpioResult};
+use crate::file::File;
+use crate::file_info::{FileAttributeType, FileInfo, FileType};
+
+const TREE_MAGIC_HEADER: &[u8] = b"MIME-TreeMagic\0\n";
+
+/// What is looked up while walking a path.
+const ENTRY_ATTRIBUTES: &str = "standard::name,standard::type,standard::is-symlink,standard::size,access::can-execute";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Directory,
+    Link,
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeMatch {
+    path: String,
+    kind: EntryKind,
+    match_case: bool,
+    executable: bool,
+    non_empty: bool,
+    /// The entry's content type must be, or derive from, this one.
+    mime_type: Option<String>,
+    children: Vec<TreeMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TreeMagicRule {
+    pub(super) priority: u32,
+    pub(super) mime_type: String,
+    matches: Vec<TreeMatch>,
+}
+
+/// Parses one match line, without its line break; `None` for lines that cannot be understood.
+fn parse_match(line: &str) -> Option<(usize, TreeMatch)> {
+    let (indent, rest) = line.split_once('>')?;
+    let indent = if indent.is_empty() { 0 } else { indent.parse().ok()? };
+    let rest = rest.strip_prefix('"')?;
+    let (path, rest) = rest.split_once('"')?;
+    let mut fields = rest.strip_prefix('=')?.split(',');
+    let kind = match fields.next()? {
+        "file" => EntryKind::File,
+        "directory" => EntryKind::Directory,
+        "link" => EntryKind::Link,
+        "any" => EntryKind::Any,
+        _ => return None,
+    };
+    let mut tree_match = TreeMatch {
+        path: path.to_string(),
+        kind,
+        match_case: false,
+        executable: false,
+        non_empty: false,
+        mime_type: None,
+        children: Vec::new(),
+    };
+    for field in fields {
+        match field {
+            "match-case" => tree_match.match_case = true,
+            "executable" => tree_match.executable = true,
+            "non-empty" => tree_match.non_empty = true,
+            // Whether the tree is on an optical disc is not known here; such rules still apply
+            "on-disc" => {}
+            mime_type if mime_type.contains('/') => tree_match.mime_type = Some(mime_type.to_string()),
+            _ => return None,
+        }
+    }
+    Some((indent, tree_match))
+}
+
+/// Adds `tree_match` below the last match at `indent - 1`.
+fn insert(matches: &mut Vec<TreeMatch>, indent: usize, tree_match: TreeMatch) {
+    if indent == 0 {
+        matches.push(tree_match);
+    } else if let Some(parent) = matches.last_mut() {
+        insert(&mut parent.children, indent - 1, tree_match);
+    }
+}
+
+/// Parses a `treemagic` file; an unknown header gives no rules.
+pub(super) fn parse_tree_magic(data: &[u8]) -> Vec<TreeMagicRule> {
+    let Some(rest) = data.strip_prefix(TREE_MAGIC_HEADER) else {
+        return Vec::new();
+    };
+    let mut rules: Vec<TreeMagicRule> = Vec::new();
+    for line in String::from_utf8_lossy(rest).lines() {
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            if let Some((priority, mime_type)) = section.split_once(':') {
+                if let Ok(priority) = priority.parse() {
+                    rules.push(TreeMagicRule { priority, mime_type: mime_type.to_string(), matches: Vec::new() });
+                }
+            }
+            continue;
+        }
+        if let (Some(rule), Some((indent, tree_match))) = (rules.last_mut(), parse_match(line)) {
+            insert(&mut rule.matches, indent, tree_match);
+        }
+    }
+    rules
+}
+
+/// The entry at `path` below `root` with its info, if there is one.
+async fn find_entry(
+    root: &dyn File,
+    path: &str,
+    match_case: bool,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<Option<(Box<dyn File>, FileInfo)>> {
+    let mut current: Option<(Box<dyn File>, FileInfo)> = None;
+    for component in path.split('/').filter(|component| !component.is_empty()) {
+        let directory = current.as_ref().map_or(root, |(file, _)| &**file);
+        let mut enumerator = match directory.enumerate_children(ENTRY_ATTRIBUTES, cancellable).await {
+            Ok(enumerator) => enumerator,
+            Err(e) if matches!(e.kind(), IOErrorEnum::Cancelled) => return Err(e),
+            // Missing, not a directory or unreadable: nothing matches below it
+            Err(_) => return Ok(None),
+        };
+        let lowercase_component = component.to_lowercase();
+        let mut found = None;
+        while let Some((info, _)) = enumerator.next_file(cancellable).await? {
+            let Some(name) = info.get_name() else {
+                continue;
+            };
+            let equal = if match_case { name == component } else { name.to_lowercase() == lowercase_component };
+            if equal {
+                found = Some((directory.child(name), info));
+                break;
+            }
+        }
+        enumerator.close(cancellable).await?;
+        match found {
+            Some(entry) => current = Some(entry),
+            None => return Ok(None),
+        }
+    }
+    Ok(current)
+}
+
+async fn has_children(directory: &dyn File, cancellable: Option<&Cancellable>) -> NpioResult<bool> {
+    let mut enumerator = directory.enumerate_children("standard::name", cancellable).await?;
+    let has_children = enumerator.next_file(cancellable).await?.is_some();
+    enumerator.close(cancellable).await?;
+    Ok(has_children)
+}
+
+impl TreeMatch {
+    fn matches<'a>(
+        &'a self,
+        root: &'a dyn File,
+        database: &'a MimeDatabase,
+        cancellable: Option<&'a Cancellable>,
+    ) -> BoxFuture<'a, NpioResult<bool>> {
+        Box::pin(async move {
+            let Some((file, info)) = find_entry(root, &self.path, self.match_case, cancellable).await? else {
+                return Ok(false);
+            };
+            let file_type = info.get_file_type();
+            let kind_matches = match self.kind {
+                EntryKind::File => file_type == FileType::Regular,
+                EntryKind::Directory => file_type == FileType::Directory,
+                EntryKind::Link => info.get_is_symlink(),
+                EntryKind::Any => true,
+            };
+            if !kind_matches {
+                return Ok(false);
+            }
+            if self.executable
+                && info.get_attribute("access::can-execute") != Some(&FileAttributeType::Boolean(true))
+            {
+                return Ok(false);
+            }
+            if self.non_empty {
+                let non_empty = match file_type {
+                    FileType::Directory => has_children(&*file, cancellable).await.unwrap_or(false),
+                    _ => info.get_size() > 0,
+                };
+                if !non_empty {
+                    return Ok(false);
+                }
+            }
+            if let Some(mime_type) = &self.mime_type {
+                let content_type = database.sniff(&*file, cancellable).await?;
+                if !database.is_a(&content_type, mime_type) {
+                    return Ok(false);
+                }
+            }
+            for child in &self.children {
+                if child.matches(root, database, cancellable).await? {
+                    return Ok(true);
+                }
+            }
+            Ok(self.children.is_empty())
+        })
+    }
+}
+
+impl TreeMagicRule {
+    pub(super) async fn matches(
+        &self,
+        root: &dyn File,
+        database: &MimeDatabase,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<bool> {
+        for tree_match in &self.matches {
+            if tree_match.matches(root, database, cancellable).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use crate::cancellable::Cancellable;
 use crate::error::NpioResult;
+use crate::icon::Icon;
 use crate::drive::Drive;
 use crate::volume::Volume;
 use crate::file::File;
@@ -26,6 +27,18 @@ pub trait Mount: Send + Sync + std::fmt::Debug {
         None
     }
 
+    /// Gets the icon for the mount, falling back to shorter names such as `drive-harddisk`
+    /// for `drive-harddisk-usb`.
+    fn get_themed_icon(&self) -> Icon {
+        Icon::themed_with_default_fallbacks(&self.get_icon())
+    }
+
+    /// Gets the symbolic icon for the mount, made from the regular icon name if there is none.
+    fn get_themed_symbolic_icon(&self) -> Icon {
+        let name = self.get_symbolic_icon().unwrap_or_else(|| format!("{}-symbolic", self.get_icon()));
+        Icon::themed_with_default_fallbacks(&name)
+    }
+
     /// Gets the UUID of the mount.
     fn get_uuid(&self) -> Option<String>;
 
@@ -64,6 +77,16 @@ pub trait Mount: Send + Sync + std::fmt::Debug {
         ))
     }
 
+    /// Guesses the `x-content/*` types of the mount's contents, such as
+    /// `x-content/image-dcf` for a camera, to offer a fitting application.
+    async fn guess_content_type(
+        &self,
+        cancellable: Option<&Cancellable>,
+    ) -> NpioResult<Vec<String>> {
+        let root = self.get_root();
+        crate::metadata::MimeResolver::guess_content_type_for_tree(&*root, cancellable).await
+    }
+
     /// Gets a sort key for ordering mounts.
     fn get_sort_key(&self) -> Option<String> {
         None
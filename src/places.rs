@@ -0,0 +1,166 @@
+//! Places of file manager sidebars
+//!
+//! The user's well-known directories (`UserDirs`) and GTK bookmarks
+//! (`Bookmarks`). `PlacesMonitor` watches the directories holding their files,
+//! as both are replaced by renames when saved, and reports the new contents
+//! whenever they change.
+
+pub mod bookmarks;
+pub mod user_dirs;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, RwLock};
+use tokio::task;
+
+use crate::backend::get_file_for_uri;
+use crate::cancellable::Cancellable;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file::File;
+use crate::monitor::{FileMonitor, FileMonitorEvent};
+use bookmarks::{Bookmarks, BOOKMARKS_DIR, BOOKMARKS_FILE};
+use user_dirs::{UserDirs, USER_DIRS_FILE};
+
+/// Events emitted by PlacesMonitor, with what the changed file now says
+#[derive(Debug, Clone)]
+pub enum PlacesEvent {
+    UserDirsChanged(UserDirs),
+    BookmarksChanged(Bookmarks),
+}
+
+/// Reports changes to `user-dirs.dirs` and the bookmarks file
+pub struct PlacesMonitor {
+    event_sender: broadcast::Sender<PlacesEvent>,
+    monitor_handles: RwLock<Vec<task::JoinHandle<()>>>,
+}
+
+impl Default for PlacesMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The file an event is about; for moves, where it went.
+fn event_file(event: &FileMonitorEvent) -> &dyn File {
+    match event {
+        FileMonitorEvent::Changed(file, _)
+        | FileMonitorEvent::ChangesDoneHint(file)
+        | FileMonitorEvent::Deleted(file)
+        | FileMonitorEvent::Created(file)
+        | FileMonitorEvent::AttributeChanged(file)
+        | FileMonitorEvent::PreUnmount(file)
+        | FileMonitorEvent::Unmounted(file)
+        | FileMonitorEvent::Moved(_, file) => &**file,
+    }
+}
+
+/// Runs `load` on the blocking thread pool; `None` if it panicked.
+async fn load_blocking<T, L>(load: &Arc<L>) -> Option<T>
+where
+    T: Send + 'static,
+    L: Fn() -> T + Send + Sync + 'static,
+{
+    let load = load.clone();
+    task::spawn_blocking(move || load()).await.ok()
+}
+
+/// Sends `to_event(load())`, if there is an event, whenever the file `name` in `monitor`'s
+/// directory changes what `load` returns, until the task is aborted.
+async fn spawn_watch<T, L, E>(
+    mut monitor: Box<FileMonitor>,
+    name: &'static str,
+    load: L,
+    to_event: E,
+    sender: broadcast::Sender<PlacesEvent>,
+) -> NpioResult<task::JoinHandle<()>>
+where
+    T: PartialEq + Send + 'static,
+    L: Fn() -> T + Send + Sync + 'static,
+    E: Fn(&T) -> Option<PlacesEvent> + Send + 'static,
+{
+    let load = Arc::new(load);
+    let mut current = load_blocking(&load)
+        .await
+        .ok_or_else(|| NpioError::new(IOErrorEnum::Failed, format!("Failed to load {}", name)))?;
+    Ok(task::spawn(async move {
+        while let Some(event) = monitor.next_event().await {
+            if event_file(&event).basename() != name {
+                continue;
+            }
+            // Saving takes several events; only those that change the contents are reported
+            let Some(loaded) = load_blocking(&load).await else {
+                continue;
+            };
+            if loaded == current {
+                continue;
+            }
+            current = loaded;
+            if let Some(event) = to_event(&current) {
+                // Nobody may be subscribed right now, but later subscribers still want events
+                let _ = sender.send(event);
+            }
+        }
+    }))
+}
+
+impl PlacesMonitor {
+    /// Creates a new PlacesMonitor
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(100);
+        Self { event_sender: sender, monitor_handles: RwLock::new(Vec::new()) }
+    }
+
+    /// Monitors the directory `dir`, creating it if needed.
+    async fn monitor_dir(dir: PathBuf, cancellable: Option<&Cancellable>) -> NpioResult<Box<FileMonitor>> {
+        tokio::fs::create_dir_all(&dir).await?;
+        let file = get_file_for_uri(&format!("file://{}", dir.to_string_lossy()))?;
+        file.monitor(cancellable).await
+    }
+
+    /// Starts monitoring `$XDG_CONFIG_HOME/user-dirs.dirs` and the bookmarks file
+    pub async fn start(&self, cancellable: Option<&Cancellable>) -> NpioResult<()> {
+        if let Some(c) = cancellable {
+            c.check()?;
+        }
+
+        let mut handles = self.monitor_handles.write().await;
+        if !handles.is_empty() {
+            return Ok(()); // Already started
+        }
+
+        let config_home = crate::xdg::config_home()
+            .ok_or_else(|| NpioError::new(IOErrorEnum::NotFound, "Could not determine the config directory"))?;
+        let user_dirs_monitor = Self::monitor_dir(config_home.clone(), cancellable).await?;
+        let bookmarks_monitor = Self::monitor_dir(config_home.join(BOOKMARKS_DIR), cancellable).await?;
+
+        handles.push(spawn_watch(
+            user_dirs_monitor,
+            USER_DIRS_FILE,
+            UserDirs::load,
+            |user_dirs| Some(PlacesEvent::UserDirsChanged(user_dirs.clone())),
+            self.event_sender.clone(),
+        ).await?);
+        // An unreadable file is reported once it can be read again
+        handles.push(spawn_watch(
+            bookmarks_monitor,
+            BOOKMARKS_FILE,
+            || Bookmarks::load().ok(),
+            |bookmarks| bookmarks.clone().map(PlacesEvent::BookmarksChanged),
+            self.event_sender.clone(),
+        ).await?);
+        Ok(())
+    }
+
+    /// Stops monitoring
+    pub async fn stop(&self) {
+        for handle in self.monitor_handles.write().await.drain(..) {
+            handle.abort();
+        }
+    }
+
+    /// Subscribes to place events
+    pub fn subscribe(&self) -> broadcast::Receiver<PlacesEvent> {
+        self.event_sender.subscribe()
+    }
+}
@@ -0,0 +1,234 @@
+//! GTK bookmarks
+//!
+//! The bookmarks of file chooser and file manager sidebars live in
+//! `$XDG_CONFIG_HOME/gtk-3.0/bookmarks`, one per line: a URI, then optionally a
+//! space and a label. GTK 2 kept them in `~/.gtk-bookmarks`, which is still
+//! read while the new file does not exist.
+
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+use crate::backend::get_file_for_uri;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file::File;
+
+pub(super) const BOOKMARKS_DIR: &str = "gtk-3.0";
+pub(super) const BOOKMARKS_FILE: &str = "bookmarks";
+const LEGACY_BOOKMARKS_FILE: &str = ".gtk-bookmarks";
+const FILE_URI_PREFIX: &str = "file://";
+
+/// Characters escaped in the paths of `file://` bookmarks, as GLib escapes them.
+const URI_PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// A bookmarked location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    uri: String,
+    label: Option<String>,
+}
+
+impl Bookmark {
+    /// A bookmark of `uri`, which must be escaped as in the bookmarks file and so cannot
+    /// contain whitespace. Line breaks in `label` become spaces, as each bookmark is one line.
+    pub fn new(uri: &str, label: Option<&str>) -> NpioResult<Self> {
+        if uri.is_empty() || uri.contains(char::is_whitespace) {
+            return Err(NpioError::new(
+                IOErrorEnum::InvalidArg,
+                format!("Bookmark URI must be non-empty and escaped: {:?}", uri),
+            ));
+        }
+        // Trailing whitespace would not survive reading the file back
+        let label = label
+            .map(|label| label.replace(['\r', '\n'], " ").trim_end().to_string())
+            .filter(|label| !label.is_empty());
+        Ok(Self { uri: uri.to_string(), label })
+    }
+
+    /// A bookmark of `file`, escaping the path of local files.
+    pub fn for_file(file: &dyn File, label: Option<&str>) -> NpioResult<Self> {
+        let uri = match file.path() {
+            Some(path) => {
+                format!("{}{}", FILE_URI_PREFIX, utf8_percent_encode(&path.to_string_lossy(), URI_PATH_ENCODE_SET))
+            }
+            None => file.uri(),
+        };
+        Self::new(&uri, label)
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The path of a `file://` bookmark.
+    pub fn path(&self) -> Option<PathBuf> {
+        let path = self.uri.strip_prefix(FILE_URI_PREFIX)?;
+        Some(PathBuf::from(percent_decode_str(path).decode_utf8_lossy().into_owned()))
+    }
+
+    /// The label, or else the last part of the location.
+    pub fn display_name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        let location = self.uri.split_once("://").map_or(self.uri.as_str(), |(_, location)| location);
+        let name = match location.trim_end_matches('/').rsplit('/').next() {
+            Some(name) if !name.is_empty() => name,
+            _ => &self.uri,
+        };
+        percent_decode_str(name).decode_utf8_lossy().into_owned()
+    }
+
+    /// The bookmarked file, from the backend registered for the URI's scheme.
+    pub fn get_file(&self) -> NpioResult<Box<dyn File>> {
+        // Local files are addressed by their unescaped path
+        match self.path() {
+            Some(path) => get_file_for_uri(&format!("{}{}", FILE_URI_PREFIX, path.to_string_lossy())),
+            None => get_file_for_uri(&self.uri),
+        }
+    }
+}
+
+/// The list of bookmarks, in the order the sidebar shows them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmarks {
+    path: PathBuf,
+    bookmarks: Vec<Bookmark>,
+}
+
+/// Lines whose URI is not escaped, which GTK would not have written, are left out.
+fn parse_bookmarks(contents: &str) -> Vec<Bookmark> {
+    contents
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.split_once(' ') {
+            Some((uri, label)) => Bookmark::new(uri, Some(label)).ok(),
+            None => Bookmark::new(line, None).ok(),
+        })
+        .collect()
+}
+
+impl Bookmarks {
+    /// `$XDG_CONFIG_HOME/gtk-3.0/bookmarks`.
+    pub fn file_path() -> Option<PathBuf> {
+        crate::xdg::config_home().map(|dir| dir.join(BOOKMARKS_DIR).join(BOOKMARKS_FILE))
+    }
+
+    /// Reads the user's bookmarks, from `~/.gtk-bookmarks` if GTK 3 has not saved any yet.
+    pub fn load() -> NpioResult<Self> {
+        let path = Self::file_path()
+            .ok_or_else(|| NpioError::new(IOErrorEnum::NotFound, "Could not determine the config directory"))?;
+        if !path.exists() {
+            let legacy = directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(LEGACY_BOOKMARKS_FILE));
+            if let Some(contents) = legacy.and_then(|legacy| std::fs::read_to_string(legacy).ok()) {
+                return Ok(Self { path, bookmarks: parse_bookmarks(&contents) });
+            }
+        }
+        Self::load_from(&path)
+    }
+
+    /// Reads the bookmarks file at `path`; a missing file has no bookmarks.
+    pub fn load_from(path: &Path) -> NpioResult<Self> {
+        let bookmarks = match std::fs::read_to_string(path) {
+            Ok(contents) => parse_bookmarks(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: path.to_path_buf(), bookmarks })
+    }
+
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// The bookmarked files, leaving out those of schemes no backend handles.
+    pub fn files(&self) -> Vec<Box<dyn File>> {
+        self.bookmarks.iter().filter_map(|bookmark| bookmark.get_file().ok()).collect()
+    }
+
+    fn position(&self, uri: &str) -> NpioResult<usize> {
+        self.bookmarks
+            .iter()
+            .position(|bookmark| bookmark.uri == uri)
+            .ok_or_else(|| NpioError::new(IOErrorEnum::NotFound, format!("No bookmark for {}", uri)))
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.bookmarks.iter().any(|bookmark| bookmark.uri == uri)
+    }
+
+    /// Inserts `bookmark` at `position`, or at the end.
+    pub fn insert(&mut self, bookmark: Bookmark, position: Option<usize>) -> NpioResult<()> {
+        if self.contains(&bookmark.uri) {
+            return Err(NpioError::new(IOErrorEnum::Exists, format!("{} is already bookmarked", bookmark.uri)));
+        }
+        let position = position.unwrap_or(self.bookmarks.len()).min(self.bookmarks.len());
+        self.bookmarks.insert(position, bookmark);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, uri: &str) -> NpioResult<Bookmark> {
+        let position = self.position(uri)?;
+        Ok(self.bookmarks.remove(position))
+    }
+
+    /// Sets the label of the bookmark of `uri`; `None` shows its name instead.
+    pub fn rename(&mut self, uri: &str, label: Option<&str>) -> NpioResult<()> {
+        let position = self.position(uri)?;
+        self.bookmarks[position] = Bookmark::new(uri, label)?;
+        Ok(())
+    }
+
+    /// Moves the bookmark of `uri` to `position`.
+    pub fn move_to(&mut self, uri: &str, position: usize) -> NpioResult<()> {
+        let bookmark = self.remove(uri)?;
+        let position = position.min(self.bookmarks.len());
+        self.bookmarks.insert(position, bookmark);
+        Ok(())
+    }
+
+    /// Writes the bookmarks back to the file they were read from.
+    pub fn save(&self) -> NpioResult<()> {
+        let dir = self
+            .path
+            .parent()
+            .ok_or_else(|| NpioError::new(IOErrorEnum::InvalidFilename, "Bookmarks file has no directory"))?;
+        std::fs::create_dir_all(dir)?;
+        let contents: String = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| match &bookmark.label {
+                Some(label) => format!("{} {}\n", bookmark.uri, label),
+                None => format!("{}\n", bookmark.uri),
+            })
+            .collect();
+        // Written next to the file and renamed over it, so readers never see half of it
+        let file_name = self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let temp_path = dir.join(format!(".{}.{}", file_name, std::process::id()));
+        std::fs::write(&temp_path, contents)?;
+        std::fs::rename(&temp_path, &self.path).inspect_err(|_| {
+            std::fs::remove_file(&temp_path).ok();
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! XDG user directories
+//!
+//! `xdg-user-dirs-update` records the Desktop, Documents, Downloads and other
+//! well-known directories in `$XDG_CONFIG_HOME/user-dirs.dirs`, as shell
+//! assignments like `XDG_DOCUMENTS_DIR="$HOME/Dokumente"`. The directories are
+//! named in the user's language, so they can only be found through this file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::get_file_for_uri;
+use crate::error::NpioResult;
+use crate::file::File;
+
+pub(super) const USER_DIRS_FILE: &str = "user-dirs.dirs";
+const HOME_VARIABLE: &str = "$HOME";
+/// Where the desktop is when `user-dirs.dirs` does not say.
+const DEFAULT_DESKTOP_DIR: &str = "Desktop";
+
+/// A well-known directory of the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserDirectory {
+    Desktop,
+    Documents,
+    Download,
+    Music,
+    Pictures,
+    PublicShare,
+    Templates,
+    Videos,
+}
+
+impl UserDirectory {
+    pub const ALL: &'static [UserDirectory] = &[
+        UserDirectory::Desktop,
+        UserDirectory::Documents,
+        UserDirectory::Download,
+        UserDirectory::Music,
+        UserDirectory::Pictures,
+        UserDirectory::PublicShare,
+        UserDirectory::Templates,
+        UserDirectory::Videos,
+    ];
+
+    /// The variable setting the directory in `user-dirs.dirs`, such as `XDG_DOCUMENTS_DIR`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            UserDirectory::Desktop => "XDG_DESKTOP_DIR",
+            UserDirectory::Documents => "XDG_DOCUMENTS_DIR",
+            UserDirectory::Download => "XDG_DOWNLOAD_DIR",
+            UserDirectory::Music => "XDG_MUSIC_DIR",
+            UserDirectory::Pictures => "XDG_PICTURES_DIR",
+            UserDirectory::PublicShare => "XDG_PUBLICSHARE_DIR",
+            UserDirectory::Templates => "XDG_TEMPLATES_DIR",
+            UserDirectory::Videos => "XDG_VIDEOS_DIR",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        UserDirectory::ALL.iter().copied().find(|directory| directory.key() == key)
+    }
+}
+
+/// The user's well-known directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserDirs {
+    home: PathBuf,
+    dirs: HashMap<UserDirectory, PathBuf>,
+}
+
+/// The path of a quoted `user-dirs.dirs` value: `"$HOME/..."` or `"/..."`, with `\` escapes.
+fn parse_value(value: &str, home: &Path) -> Option<PathBuf> {
+    let quoted = value.strip_prefix('"')?;
+    let mut unescaped = String::new();
+    let mut chars = quoted.chars();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => unescaped.push(chars.next()?),
+            c => unescaped.push(c),
+        }
+    }
+    let path = match unescaped.strip_prefix(HOME_VARIABLE) {
+        Some("") => home.to_path_buf(),
+        Some(relative) => home.join(relative.strip_prefix('/')?),
+        None if unescaped.starts_with('/') => PathBuf::from(&unescaped),
+        // The specification allows no other form
+        None => return None,
+    };
+    Some(path.components().collect())
+}
+
+impl UserDirs {
+    /// `$XDG_CONFIG_HOME/user-dirs.dirs`.
+    pub fn file_path() -> Option<PathBuf> {
+        crate::xdg::config_home().map(|dir| dir.join(USER_DIRS_FILE))
+    }
+
+    /// Reads `user-dirs.dirs`; without it, only the desktop is known.
+    pub fn load() -> Self {
+        let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf()).unwrap_or_default();
+        let contents = Self::file_path().and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+        Self::parse(&contents, &home)
+    }
+
+    /// Parses the contents of a `user-dirs.dirs`, with `$HOME` standing for `home`.
+    pub fn parse(contents: &str, home: &Path) -> Self {
+        let mut dirs = HashMap::new();
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let Some(directory) = UserDirectory::from_key(key.trim()) else {
+                continue;
+            };
+            if let Some(path) = parse_value(value.trim(), home) {
+                dirs.insert(directory, path);
+            }
+        }
+        Self { home: home.to_path_buf(), dirs }
+    }
+
+    /// The path of `directory`. A directory set to the home directory is disabled, as the
+    /// specification says, and has none; the desktop defaults to `~/Desktop`.
+    pub fn get_path(&self, directory: UserDirectory) -> Option<PathBuf> {
+        match self.dirs.get(&directory) {
+            Some(path) if *path == self.home => None,
+            Some(path) => Some(path.clone()),
+            None if directory == UserDirectory::Desktop => Some(self.home.join(DEFAULT_DESKTOP_DIR)),
+            None => None,
+        }
+    }
+
+    /// `directory` as a file from the backend registry.
+    pub fn get_file(&self, directory: UserDirectory) -> NpioResult<Option<Box<dyn File>>> {
+        self.get_path(directory)
+            .map(|path| get_file_for_uri(&format!("file://{}", path.to_string_lossy())))
+            .transpose()
+    }
+
+    /// The name of `directory` in the user's language, which is the name `xdg-user-dirs-update`
+    /// gave it.
+    pub fn display_name(&self, directory: UserDirectory) -> Option<String> {
+        self.get_path(directory)?.file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+}
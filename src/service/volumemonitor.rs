@@ -73,12 +73,13 @@ impl VolumeMonitor {
         // Full udev integration would require more complex thread handling
         let sender = self.event_sender.clone();
         let udisks2 = self.udisks2_backend.clone();
+        let mount_backend = self.mount_backend.clone();
         let volumes = self.volumes.clone();
         let mounts = self.mounts.clone();
         let drives = self.drives.clone();
 
         let handle = task::spawn(async move {
-            monitor_udev_events(sender, udisks2, volumes, mounts, drives).await;
+            monitor_udev_events(sender, udisks2, mount_backend, volumes, mounts, drives).await;
         });
 
         {
@@ -237,8 +238,9 @@ impl Default for VolumeMonitor {
 async fn monitor_udev_events(
     sender: broadcast::Sender<VolumeMonitorEvent>,
     udisks2: Arc<UDisks2Backend>,
+    mount_backend: Arc<MountBackend>,
     volumes: Arc<RwLock<HashMap<String, Box<dyn Volume>>>>,
-    _mounts: Arc<RwLock<HashMap<String, Box<dyn Mount>>>>,
+    mounts: Arc<RwLock<HashMap<String, Box<dyn Mount>>>>,
     _drives: Arc<RwLock<HashMap<String, Box<dyn Drive>>>>,
 ) {
     // Poll UDisks2 periodically for changes instead of using udev directly
@@ -277,6 +279,35 @@ async fn monitor_udev_events(
                 }
             }
         }
+
+        // Mounts are keyed by mount point, /proc/self/mountinfo lists them regardless of UDisks2
+        if let Ok(mounts_list) = mount_backend.get_mounts().await {
+            let mut mounts_guard = mounts.write().await;
+
+            let mut current_mounts: HashMap<String, Box<dyn Mount>> = HashMap::new();
+            for mount in mounts_list {
+                let uri = mount.get_root().uri();
+                let key = uri.strip_prefix("file://").unwrap_or(&uri).to_string();
+                current_mounts.insert(key, mount);
+            }
+
+            let removed_keys: Vec<String> = mounts_guard
+                .keys()
+                .filter(|key| !current_mounts.contains_key(*key))
+                .cloned()
+                .collect();
+            for key in removed_keys {
+                mounts_guard.remove(&key);
+                let _ = sender.send(VolumeMonitorEvent::MountRemoved { mount: key });
+            }
+
+            for (key, mount) in current_mounts {
+                if let std::collections::hash_map::Entry::Vacant(entry) = mounts_guard.entry(key) {
+                    let _ = sender.send(VolumeMonitorEvent::MountAdded { mount: entry.key().clone() });
+                    entry.insert(mount);
+                }
+            }
+        }
     }
 }
 
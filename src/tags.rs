@@ -0,0 +1,229 @@
+//! File tags and color labels
+//!
+//! Follows the freedesktop convention used by Dolphin and Baloo: tags are kept
+//! comma-separated in the `user.xdg.tags` extended attribute and a free-form
+//! comment in `user.xdg.comment`. Color labels are tags named after a color,
+//! as on macOS, so other applications see them as ordinary tags. `query_info`
+//! exposes them as `tags::list`, `tags::comment` and `tags::color-label`.
+
+use std::fmt;
+
+use crate::cancellable::Cancellable;
+use crate::error::{IOErrorEnum, NpioError, NpioResult};
+use crate::file::File;
+use crate::file_info::{FileAttributeType, FileInfo, FileType};
+
+/// Separator of tags in `user.xdg.tags`.
+const TAG_SEPARATOR: char = ',';
+
+/// Attributes requested for each child when searching for tagged files.
+const TAG_SEARCH_ATTRIBUTES: &str = "standard::type,tags::list";
+
+/// A color label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl TagColor {
+    pub const ALL: &'static [TagColor] = &[
+        TagColor::Red,
+        TagColor::Orange,
+        TagColor::Yellow,
+        TagColor::Green,
+        TagColor::Blue,
+        TagColor::Purple,
+        TagColor::Gray,
+    ];
+
+    /// The tag that stands for this color.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TagColor::Red => "Red",
+            TagColor::Orange => "Orange",
+            TagColor::Yellow => "Yellow",
+            TagColor::Green => "Green",
+            TagColor::Blue => "Blue",
+            TagColor::Purple => "Purple",
+            TagColor::Gray => "Gray",
+        }
+    }
+
+    /// The color a tag stands for, ignoring case.
+    pub fn from_name(name: &str) -> Option<TagColor> {
+        TagColor::ALL.iter().copied().find(|color| color.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// The tags of a file, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    tags: Vec<String>,
+}
+
+impl Tags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a tag set from separate tags, dropping duplicates.
+    pub fn from_list<I, S>(tags: I) -> NpioResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut result = Self::new();
+        for tag in tags {
+            result.add(tag.as_ref())?;
+        }
+        Ok(result)
+    }
+
+    /// Parses the comma-separated `user.xdg.tags` form. Blank entries are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut tags = Self::new();
+        for tag in text.split(TAG_SEPARATOR).map(str::trim).filter(|tag| !tag.is_empty()) {
+            if !tags.contains(tag) {
+                tags.tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag)
+    }
+
+    /// Adds a tag; returns false if the file already had it. Tags cannot be
+    /// blank or contain the separator.
+    pub fn add(&mut self, tag: &str) -> NpioResult<bool> {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.contains(TAG_SEPARATOR) {
+            return Err(NpioError::new(IOErrorEnum::InvalidArg, format!("Invalid tag: {:?}", tag)));
+        }
+        if self.contains(tag) {
+            return Ok(false);
+        }
+        self.tags.push(tag.to_string());
+        Ok(true)
+    }
+
+    /// Removes a tag; returns false if the file did not have it.
+    pub fn remove(&mut self, tag: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != len
+    }
+
+    /// The first tag that is a color label.
+    pub fn color_label(&self) -> Option<TagColor> {
+        self.iter().find_map(TagColor::from_name)
+    }
+
+    /// Replaces the color label, or removes it for `None`.
+    pub fn set_color_label(&mut self, color: Option<TagColor>) {
+        self.tags.retain(|tag| TagColor::from_name(tag).is_none());
+        if let Some(color) = color {
+            self.tags.push(color.name().to_string());
+        }
+    }
+
+    /// The value of `tags::list`.
+    pub fn to_attribute(&self) -> FileAttributeType {
+        FileAttributeType::Stringv(self.tags.clone())
+    }
+
+    /// Reads `tags::list` from `info`; a missing attribute means no tags.
+    pub fn from_info(info: &FileInfo) -> Self {
+        match info.get_attribute("tags::list") {
+            Some(FileAttributeType::Stringv(tags)) => Self::parse(&tags.join(",")),
+            _ => Self::new(),
+        }
+    }
+}
+
+impl fmt::Display for Tags {
+    /// The comma-separated `user.xdg.tags` form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, tag) in self.tags.iter().enumerate() {
+            if index > 0 {
+                write!(f, "{}", TAG_SEPARATOR)?;
+            }
+            write!(f, "{}", tag)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the files in `directory` tagged with `tag`, descending into
+/// subdirectories if `recursive` is set. Symlinks to directories are not followed.
+pub async fn find_tagged_files<F: File + ?Sized>(
+    directory: &F,
+    tag: &str,
+    recursive: bool,
+    cancellable: Option<&Cancellable>,
+) -> NpioResult<Vec<(FileInfo, Box<dyn File>)>> {
+    let mut found = Vec::new();
+    let mut pending: Vec<Box<dyn File>> = Vec::new();
+    // `None` while enumerating `directory` itself
+    let mut current: Option<Box<dyn File>> = None;
+
+    loop {
+        let enumerated = match &current {
+            Some(current) => current.enumerate_children(TAG_SEARCH_ATTRIBUTES, cancellable).await,
+            None => directory.enumerate_children(TAG_SEARCH_ATTRIBUTES, cancellable).await,
+        };
+        let mut enumerator = match enumerated {
+            Ok(enumerator) => enumerator,
+            // Subdirectories may be unreadable or vanish during the walk; only `directory` must be listable
+            Err(e) if current.is_some()
+                && matches!(e.kind(), IOErrorEnum::PermissionDenied | IOErrorEnum::NotFound) =>
+            {
+                match pending.pop() {
+                    Some(next) => current = Some(next),
+                    None => break,
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        while let Some((info, file)) = enumerator.next_file(cancellable).await? {
+            if recursive && info.get_file_type() == FileType::Directory {
+                // The enumerated handle may be returned, so the directory is visited through its own
+                let name = file.basename();
+                pending.push(match &current {
+                    Some(current) => current.child(&name),
+                    None => directory.child(&name),
+                });
+            }
+            if Tags::from_info(&info).contains(tag) {
+                found.push((info, file));
+            }
+        }
+        enumerator.close(cancellable).await?;
+
+        match pending.pop() {
+            Some(next) => current = Some(next),
+            None => break,
+        }
+    }
+    Ok(found)
+}
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use crate::cancellable::Cancellable;
 use crate::error::NpioResult;
+use crate::icon::Icon;
 use crate::drive::Drive;
 use crate::mount::Mount;
 use crate::file::File;
@@ -18,6 +19,18 @@ pub trait Volume: Send + Sync + std::fmt::Debug {
         None
     }
 
+    /// Gets the icon for the volume, falling back to shorter names such as `drive-harddisk`
+    /// for `drive-harddisk-usb`.
+    fn get_themed_icon(&self) -> Icon {
+        Icon::themed_with_default_fallbacks(&self.get_icon())
+    }
+
+    /// Gets the symbolic icon for the volume, made from the regular icon name if there is none.
+    fn get_themed_symbolic_icon(&self) -> Icon {
+        let name = self.get_symbolic_icon().unwrap_or_else(|| format!("{}-symbolic", self.get_icon()));
+        Icon::themed_with_default_fallbacks(&name)
+    }
+
     /// Gets the UUID of the volume.
     fn get_uuid(&self) -> Option<String>;
 
@@ -0,0 +1,60 @@
+//! XDG base directories
+//!
+//! The environment variables of the Base Directory Specification take
+//! precedence over its defaults; relative paths in them are ignored, as the
+//! specification asks.
+
+use std::path::PathBuf;
+
+/// Data directories searched when `XDG_DATA_DIRS` is unset.
+const DEFAULT_DATA_DIRS: [&str; 2] = ["/usr/local/share", "/usr/share"];
+/// Configuration directories searched when `XDG_CONFIG_DIRS` is unset.
+const DEFAULT_CONFIG_DIRS: [&str; 1] = ["/etc/xdg"];
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).map(PathBuf::from).filter(|path| path.is_absolute())
+}
+
+fn env_dirs(name: &str) -> Option<Vec<PathBuf>> {
+    let dirs: Vec<PathBuf> = std::env::split_paths(&std::env::var_os(name)?)
+        .filter(|path| path.is_absolute())
+        .collect();
+    (!dirs.is_empty()).then_some(dirs)
+}
+
+/// `$XDG_DATA_HOME`, `~/.local/share` by default.
+pub(crate) fn data_home() -> Option<PathBuf> {
+    env_dir("XDG_DATA_HOME").or_else(|| directories::BaseDirs::new().map(|dirs| dirs.data_dir().to_path_buf()))
+}
+
+/// `$XDG_DATA_DIRS`, most important first.
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    env_dirs("XDG_DATA_DIRS").unwrap_or_else(|| DEFAULT_DATA_DIRS.iter().map(PathBuf::from).collect())
+}
+
+/// The data home followed by the data directories, most important first.
+pub(crate) fn data_search_dirs() -> Vec<PathBuf> {
+    data_home().into_iter().chain(data_dirs()).collect()
+}
+
+/// `$XDG_CONFIG_HOME`, `~/.config` by default.
+pub(crate) fn config_home() -> Option<PathBuf> {
+    env_dir("XDG_CONFIG_HOME").or_else(|| directories::BaseDirs::new().map(|dirs| dirs.config_dir().to_path_buf()))
+}
+
+/// `$XDG_CONFIG_DIRS`, most important first.
+pub(crate) fn config_dirs() -> Vec<PathBuf> {
+    env_dirs("XDG_CONFIG_DIRS").unwrap_or_else(|| DEFAULT_CONFIG_DIRS.iter().map(PathBuf::from).collect())
+}
+
+/// The config home followed by the config directories, most important first.
+pub(crate) fn config_search_dirs() -> Vec<PathBuf> {
+    config_home().into_iter().chain(config_dirs()).collect()
+}
+
+/// The desktop environments named in `$XDG_CURRENT_DESKTOP`, most specific first.
+pub(crate) fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|desktops| desktops.split(':').filter(|desktop| !desktop.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,49 @@
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, FileAttributeType};
+
+#[tokio::test]
+async fn test_query_access_attributes() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_access_attributes_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"access").await.unwrap();
+    std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    // The only test in this binary, as it points the home trash somewhere else
+    std::env::set_var("XDG_DATA_HOME", test_dir.join("data"));
+
+    let info = file.query_info("access::*", None).await.unwrap();
+    let get = |key: &str| match info.get_attribute(key) {
+        Some(FileAttributeType::Boolean(value)) => *value,
+        other => panic!("Unexpected {}: {:?}", key, other),
+    };
+    assert!(get("access::can-read"));
+    assert!(get("access::can-write"));
+    assert!(!get("access::can-execute"));
+    assert!(get("access::can-delete"));
+    assert!(get("access::can-rename"));
+    assert!(get("access::can-trash"));
+
+    let info = file.query_info("access::can-read", None).await.unwrap();
+    assert!(!info.has_attribute("access::can-write"));
+
+    // A trash on another filesystem can't take the file
+    let shm_dir = std::path::Path::new("/dev/shm");
+    if shm_dir.is_dir() {
+        std::env::set_var("XDG_DATA_HOME", shm_dir.join("npio_access_attributes_test"));
+        let info = file.query_info("access::can-trash,access::can-delete", None).await.unwrap();
+        assert_eq!(info.get_attribute("access::can-trash"), Some(&FileAttributeType::Boolean(false)));
+        assert_eq!(info.get_attribute("access::can-delete"), Some(&FileAttributeType::Boolean(true)));
+    }
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::file::local::copy::copy_local_tree;
+use npio::job;
+use npio::{
+    get_file_for_uri, register_backend, Acl, AclKind, AclPermissions, AclTag, CopyFlags, FileAttributeType,
+    FileQueryInfoFlags,
+};
+
+#[test]
+fn test_acl_text_and_xattr() {
+    let acl = Acl::parse("user::rw-,group::r--,other::---,user:1000:rwx,mask::r-x").unwrap();
+    // Entries are kept in the order the kernel expects
+    assert_eq!(acl.to_string(), "user::rw-,user:1000:rwx,group::r--,mask::r-x,other::---");
+    assert_eq!(acl.get(AclTag::User(1000)), Some(AclPermissions::READ | AclPermissions::WRITE | AclPermissions::EXECUTE));
+    assert_eq!(acl.to_mode(), 0o650);
+    assert!(!acl.is_minimal());
+    assert!(acl.validate().is_ok());
+    assert_eq!(Acl::from_xattr(&acl.to_xattr()).unwrap(), acl);
+
+    let short: Acl = "u::rwx\ng::r-x\no::r-x".parse().unwrap();
+    assert_eq!(short, Acl::from_mode(0o755));
+    assert!(short.is_minimal());
+
+    let mut acl = Acl::from_mode(0o640);
+    acl.set(AclTag::Group(100), AclPermissions::WRITE);
+    assert!(acl.validate().is_err());
+    acl.calculate_mask();
+    assert_eq!(acl.get(AclTag::Mask), Some(AclPermissions::READ | AclPermissions::WRITE));
+    assert!(acl.remove(AclTag::Group(100)));
+    assert!(!acl.remove(AclTag::Group(100)));
+
+    assert!(Acl::parse("user::rw-,user::r--").is_err());
+    assert!(Acl::parse("user:alice:rw-").is_err());
+    assert!(Acl::parse("mask:5:rw-").is_err());
+    assert!(Acl::parse("other::rwz").is_err());
+    assert!(Acl::parse("user::rw-,group::r--").unwrap().validate().is_err());
+    assert!(Acl::from_xattr(&[2, 0, 0, 0, 1]).is_err());
+}
+
+#[tokio::test]
+async fn test_local_acl() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_acl_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let source_dir = test_dir.join("source");
+    tokio::fs::create_dir(&source_dir).await.unwrap();
+    let file_path = source_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"acl").await.unwrap();
+    let dir = get_file_for_uri(&format!("file://{}", source_dir.to_string_lossy())).unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    // Without a stored ACL the mode is reported
+    std::fs::set_permissions(&file_path, std::os::unix::fs::PermissionsExt::from_mode(0o640)).unwrap();
+    assert_eq!(file.query_acl(AclKind::Access, None).await.unwrap(), Some(Acl::from_mode(0o640)));
+    assert_eq!(dir.query_acl(AclKind::Default, None).await.unwrap(), None);
+    assert!(!file.query_info("acl::*", None).await.unwrap().has_attribute("acl::access"));
+
+    let mut acl = Acl::from_mode(0o640);
+    acl.set(AclTag::User(12345), AclPermissions::READ | AclPermissions::WRITE);
+    acl.calculate_mask();
+    match file.set_acl(AclKind::Access, Some(&acl), None).await {
+        Err(e) if matches!(e.kind(), npio::IOErrorEnum::NotSupported) => {
+            // The temporary directory is on a filesystem without ACLs
+            tokio::fs::remove_dir_all(&test_dir).await.ok();
+            return;
+        }
+        result => result.unwrap(),
+    }
+    assert_eq!(file.query_acl(AclKind::Access, None).await.unwrap(), Some(acl.clone()));
+    let info = file.query_info("acl::*,unix::mode", None).await.unwrap();
+    assert_eq!(info.get_attribute("acl::access"), Some(&FileAttributeType::String(acl.to_string())));
+    // The mask shows up as the group bits
+    assert_eq!(info.get_attribute("unix::mode").and_then(|mode| match mode {
+        FileAttributeType::Uint32(mode) => Some(mode & 0o777),
+        _ => None,
+    }), Some(0o660));
+
+    // Default ACLs through the attribute, and only on directories
+    let default_acl = "user::rwx,user:12345:r-x,group::r-x,mask::r-x,other::---";
+    dir.set_attribute("acl::default", &FileAttributeType::String(default_acl.to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let info = dir.query_info("acl::default", None).await.unwrap();
+    assert_eq!(info.get_attribute("acl::default"), Some(&FileAttributeType::String(default_acl.to_string())));
+    let err = file.set_acl(AclKind::Default, Some(&Acl::from_mode(0o644)), None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::NotDirectory));
+    let err = file
+        .set_attribute("acl::access", &FileAttributeType::String("user::rw-".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+
+    // Copies keep ACLs with ALL_METADATA only
+    let copy_path = test_dir.join("copy.txt");
+    let copy = get_file_for_uri(&format!("file://{}", copy_path.to_string_lossy())).unwrap();
+    job::copy(&*file, &*copy, CopyFlags::ALL_METADATA, None, None).await.expect("Copy failed");
+    assert_eq!(copy.query_acl(AclKind::Access, None).await.unwrap(), Some(acl.clone()));
+    let plain_path = test_dir.join("plain.txt");
+    let plain = get_file_for_uri(&format!("file://{}", plain_path.to_string_lossy())).unwrap();
+    job::copy(&*file, &*plain, CopyFlags::NONE, None, None).await.expect("Copy failed");
+    assert!(plain.query_acl(AclKind::Access, None).await.unwrap().unwrap().is_minimal());
+
+    let tree_path = test_dir.join("tree");
+    copy_local_tree(&source_dir, &tree_path, CopyFlags::NONE, None, None).expect("Tree copy failed");
+    let tree = get_file_for_uri(&format!("file://{}", tree_path.to_string_lossy())).unwrap();
+    assert_eq!(
+        tree.query_acl(AclKind::Default, None).await.unwrap().map(|acl| acl.to_string()),
+        Some(default_acl.to_string())
+    );
+
+    // Removing the ACLs
+    dir.set_acl(AclKind::Default, None, None).await.unwrap();
+    assert_eq!(dir.query_acl(AclKind::Default, None).await.unwrap(), None);
+    file.set_attribute("acl::access", &FileAttributeType::Invalid, FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    assert!(file.query_acl(AclKind::Access, None).await.unwrap().unwrap().is_minimal());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+use npio::AppInfo;
+
+fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+fn ids(apps: Vec<AppInfo>) -> Vec<String> {
+    apps.iter().map(|app| app.id().to_string()).collect()
+}
+
+// The XDG variables are process-wide, so everything runs in one test
+#[test]
+fn test_app_info_lookup() {
+    let test_dir = std::env::temp_dir().join("npio_app_info_test");
+    if test_dir.exists() {
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+    let data_home = test_dir.join("data_home");
+    let data_dir = test_dir.join("data_dir");
+    let config_home = test_dir.join("config_home");
+    let config_dir = test_dir.join("config_dir");
+    std::env::set_var("XDG_DATA_HOME", &data_home);
+    std::env::set_var("XDG_DATA_DIRS", &data_dir);
+    std::env::set_var("XDG_CONFIG_HOME", &config_home);
+    std::env::set_var("XDG_CONFIG_DIRS", &config_dir);
+    std::env::set_var("XDG_CURRENT_DESKTOP", "Test:Other");
+    std::env::set_var("LC_ALL", "de_DE.UTF-8");
+
+    write(&data_dir.join("mime/aliases"), "text/x-c text/x-csrc\n");
+    write(&data_dir.join("mime/subclasses"), "application/x-shellscript text/plain\n");
+
+    write(
+        &data_dir.join("applications/editor.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Editor\nName[de]=Texteditor\nGenericName=Text Editor\n\
+         Exec=editor %U\nIcon=accessories-text-editor\nMimeType=text/plain;text/x-csrc;\n",
+    );
+    write(
+        &data_dir.join("applications/kde/viewer.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Viewer\nExec=viewer %f\nMimeType=text/plain;image/png;\nOnlyShowIn=KDE;\n",
+    );
+    write(
+        &data_dir.join("applications/ide.desktop"),
+        "[Desktop Entry]\nType=Application\nName=IDE\nExec=ide %F\nMimeType=text/x-c;\nNoDisplay=true\n",
+    );
+    write(
+        &data_dir.join("applications/hidden.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Hidden\nExec=hidden\nMimeType=text/plain;\n",
+    );
+    write(
+        &data_dir.join("applications/missing.desktop"),
+        "[Desktop Entry]\nType=Application\nName=Missing\nTryExec=npio-no-such-program\nExec=missing\n",
+    );
+    write(&data_dir.join("applications/link.desktop"), "[Desktop Entry]\nType=Link\nName=Link\nURL=https://example.com\n");
+    // The user's entry hides the system one
+    write(&data_home.join("applications/hidden.desktop"), "[Desktop Entry]\nType=Application\nName=Hidden\nExec=hidden\nHidden=true\n");
+
+    // System-wide defaults, overridden for this desktop and by the user
+    write(
+        &config_dir.join("mimeapps.list"),
+        "[Default Applications]\ntext/plain=kde-viewer.desktop\nimage/png=kde-viewer.desktop\n",
+    );
+    write(
+        &config_dir.join("test-mimeapps.list"),
+        "[Default Applications]\ntext/plain=uninstalled.desktop;editor.desktop;\n",
+    );
+    write(
+        &config_home.join("mimeapps.list"),
+        "# My associations\n[Added Associations]\nimage/png=editor.desktop;\n\n[Removed Associations]\ntext/x-csrc=editor.desktop;\n",
+    );
+
+    let all = ids(AppInfo::get_all());
+    assert_eq!(all, vec!["editor.desktop", "ide.desktop", "kde-viewer.desktop"]);
+
+    let editor = AppInfo::new("editor.desktop").unwrap();
+    assert_eq!(editor.name(), "Texteditor");
+    assert_eq!(editor.generic_name(), Some("Text Editor"));
+    assert_eq!(editor.icon(), Some("accessories-text-editor"));
+    assert_eq!(editor.executable(), "editor");
+    assert!(editor.supports_uris());
+    assert!(editor.should_show());
+    let viewer = AppInfo::new("kde-viewer.desktop").unwrap();
+    assert!(!viewer.should_show());
+    assert!(!viewer.supports_uris());
+    assert!(!AppInfo::new("ide.desktop").unwrap().should_show());
+    assert!(AppInfo::new("hidden.desktop").is_none());
+
+    // The desktop-specific list wins over the general one of the same directory
+    assert_eq!(AppInfo::get_default_for_type("text/plain", false).unwrap().id(), "editor.desktop");
+    assert_eq!(AppInfo::get_default_for_type("image/png", false).unwrap().id(), "kde-viewer.desktop");
+    assert_eq!(AppInfo::get_default_for_type("image/png", true).unwrap().id(), "editor.desktop");
+    // No default of its own, so the parent type's default applies
+    assert_eq!(AppInfo::get_default_for_type("application/x-shellscript", false).unwrap().id(), "editor.desktop");
+    assert!(AppInfo::get_default_for_type("video/x-unknown", false).is_none());
+
+    // The user's association comes before the system-wide default
+    assert_eq!(ids(AppInfo::get_recommended_for_type("image/png")), vec!["editor.desktop", "kde-viewer.desktop"]);
+    // Aliases are resolved and the user's removal applies to the entry's own claim
+    assert_eq!(ids(AppInfo::get_recommended_for_type("text/x-c")), vec!["ide.desktop"]);
+    assert_eq!(ids(AppInfo::get_fallback_for_type("text/x-csrc")), vec!["editor.desktop", "kde-viewer.desktop"]);
+    assert_eq!(
+        ids(AppInfo::get_all_for_type("text/x-csrc")),
+        vec!["ide.desktop", "editor.desktop", "kde-viewer.desktop"]
+    );
+
+    // Setting a default edits the user's list and keeps what was there
+    AppInfo::new("ide.desktop").unwrap().set_as_default_for_type("image/png").unwrap();
+    editor.set_as_default_for_type("text/x-csrc").unwrap();
+    assert_eq!(AppInfo::get_default_for_type("image/png", false).unwrap().id(), "ide.desktop");
+    assert_eq!(ids(AppInfo::get_recommended_for_type("text/x-csrc")), vec!["editor.desktop", "ide.desktop"]);
+    let list = std::fs::read_to_string(config_home.join("mimeapps.list")).unwrap();
+    assert!(list.starts_with("# My associations\n"));
+    assert!(list.contains("image/png=ide.desktop;editor.desktop;\n"));
+    assert!(!list.contains("[Removed Associations]\ntext/x-csrc"));
+    assert!(editor.set_as_default_for_type("nonsense").is_err());
+
+    let from_file = AppInfo::from_filename(&data_dir.join("applications/kde/viewer.desktop")).unwrap();
+    assert_eq!(from_file.id(), "viewer.desktop");
+    assert!(AppInfo::from_filename(&data_dir.join("applications/link.desktop")).is_err());
+
+    std::fs::remove_dir_all(&test_dir).ok();
+}
@@ -0,0 +1,166 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, AppInfo, AppLaunchContext, File};
+
+// Each run of the script records its environment and arguments in its own file
+const RECORD_SCRIPT: &str = r#"#!/bin/sh
+{
+    echo "pwd=$PWD"
+    echo "startup=$DESKTOP_STARTUP_ID"
+    echo "extra=$NPIO_EXTRA"
+    for arg in "$@"; do echo "arg=$arg"; done
+} > "$NPIO_OUT/run.$$"
+"#;
+
+fn runs(out_dir: &Path) -> Vec<Vec<String>> {
+    let mut runs: Vec<Vec<String>> = std::fs::read_dir(out_dir)
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap().lines().map(str::to_string).collect())
+        .collect();
+    runs.sort();
+    runs
+}
+
+#[tokio::test]
+async fn test_launch() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_app_launch_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    let out_dir = test_dir.join("out");
+    let work_dir = test_dir.join("work");
+    tokio::fs::create_dir_all(&out_dir).await.unwrap();
+    tokio::fs::create_dir_all(&work_dir).await.unwrap();
+    let script = test_dir.join("record.sh");
+    std::fs::write(&script, RECORD_SCRIPT).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let first_path = test_dir.join("first file.txt");
+    let second_path = test_dir.join("second.txt");
+    std::fs::write(&first_path, b"1").unwrap();
+    std::fs::write(&second_path, b"2").unwrap();
+    let first = get_file_for_uri(&format!("file://{}", first_path.to_string_lossy())).unwrap();
+    let second = get_file_for_uri(&format!("file://{}", second_path.to_string_lossy())).unwrap();
+    let files: Vec<&dyn File> = vec![&*first, &*second];
+
+    // Our own startup ID is not passed on to applications without startup notification
+    std::env::set_var("DESKTOP_STARTUP_ID", "inherited");
+    let mut context = AppLaunchContext::new();
+    context.setenv("NPIO_OUT", &out_dir.to_string_lossy());
+    context.setenv("NPIO_EXTRA", "yes");
+    context.set_timestamp(42);
+
+    // All files to one process, with quoting and the other field codes
+    let desktop_path = test_dir.join("recorder.desktop");
+    std::fs::write(
+        &desktop_path,
+        format!(
+            "[Desktop Entry]\nType=Application\nName=Recorder\nIcon=recorder-icon\nPath={}\nStartupNotify=true\n\
+             Exec={} --name=%c %i \"quoted \\\\\"x\\\\\" %f\" \"\" %F %k 100%%\n",
+            work_dir.display(),
+            script.display()
+        ),
+    )
+    .unwrap();
+    let recorder = AppInfo::from_filename(&desktop_path).unwrap();
+    assert!(!recorder.supports_uris());
+    let children = recorder.launch(&files, Some(&context)).unwrap();
+    assert_eq!(children.len(), 1);
+    for mut child in children {
+        assert!(child.wait().await.unwrap().success());
+    }
+    let recorded = runs(&out_dir);
+    assert_eq!(recorded.len(), 1);
+    let run = &recorded[0];
+    assert_eq!(run[0], format!("pwd={}", work_dir.display()));
+    assert!(run[1].starts_with("startup=npio-") && run[1].ends_with("_TIME42"), "{}", run[1]);
+    assert_eq!(run[2], "extra=yes");
+    assert_eq!(
+        run[3..].to_vec(),
+        vec![
+            "arg=--name=Recorder".to_string(),
+            "arg=--icon".to_string(),
+            "arg=recorder-icon".to_string(),
+            "arg=quoted \"x\" %f".to_string(),
+            "arg=".to_string(),
+            format!("arg={}", first_path.display()),
+            format!("arg={}", second_path.display()),
+            format!("arg={}", desktop_path.display()),
+            "arg=100%".to_string(),
+        ]
+    );
+
+    // A single URI per process
+    std::fs::remove_dir_all(&out_dir).unwrap();
+    std::fs::create_dir(&out_dir).unwrap();
+    let opener_path = test_dir.join("opener.desktop");
+    std::fs::write(
+        &opener_path,
+        format!("[Desktop Entry]\nType=Application\nName=Opener\nExec={} %u\n", script.display()),
+    )
+    .unwrap();
+    let opener = AppInfo::from_filename(&opener_path).unwrap();
+    assert!(opener.supports_uris());
+    let children = opener.launch(&files, Some(&context)).unwrap();
+    assert_eq!(children.len(), 2);
+    for mut child in children {
+        assert!(child.wait().await.unwrap().success());
+    }
+    let recorded = runs(&out_dir);
+    let mut uris: Vec<String> = recorded.iter().map(|run| run[3].clone()).collect();
+    uris.sort();
+    assert_eq!(uris, vec![format!("arg={}", first.uri()), format!("arg={}", second.uri())]);
+    assert!(recorded.iter().all(|run| run[1] == "startup="));
+
+    // Without files, `%u` leaves no argument behind
+    std::fs::remove_dir_all(&out_dir).unwrap();
+    std::fs::create_dir(&out_dir).unwrap();
+    for mut child in opener.launch(&[], Some(&context)).unwrap() {
+        assert!(child.wait().await.unwrap().success());
+    }
+    assert_eq!(runs(&out_dir)[0].len(), 3);
+
+    let broken_path = test_dir.join("broken.desktop");
+    std::fs::write(&broken_path, "[Desktop Entry]\nType=Application\nName=Broken\nExec=\"unterminated %f\n").unwrap();
+    assert!(AppInfo::from_filename(&broken_path).unwrap().launch(&files, None).is_err());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_launch_non_utf8_path() {
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let test_dir = std::env::temp_dir().join("npio_app_launch_non_utf8_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir_all(&test_dir).await.unwrap();
+    let out_path = test_dir.join("arg");
+    let script = test_dir.join("record.sh");
+    std::fs::write(&script, format!("#!/bin/sh\nprintf '%s' \"$1\" > '{}'\n", out_path.display())).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Latin-1 "café", which is not valid UTF-8
+    let file_path = test_dir.join(std::ffi::OsString::from_vec(b"caf\xe9.txt".to_vec()));
+    std::fs::write(&file_path, b"1").unwrap();
+    let file = npio::file::local::LocalFile::new(file_path.clone());
+
+    let desktop_path = test_dir.join("viewer.desktop");
+    std::fs::write(
+        &desktop_path,
+        format!("[Desktop Entry]\nType=Application\nName=Viewer\nExec={} %f\n", script.display()),
+    )
+    .unwrap();
+    let viewer = AppInfo::from_filename(&desktop_path).unwrap();
+    for mut child in viewer.launch(&[&file], None).unwrap() {
+        assert!(child.wait().await.unwrap().success());
+    }
+    assert_eq!(std::fs::read(&out_path).unwrap(), file_path.as_os_str().as_bytes());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, FileAttributeMatcher};
+
+#[test]
+fn test_matcher_parse_and_match() {
+    let matcher = FileAttributeMatcher::new("standard::content-type-foo,time::*");
+    assert!(!matcher.matches("standard::content-type"));
+    assert!(matcher.matches("standard::content-type-foo"));
+    assert!(matcher.matches("time::modified"));
+    assert!(!matcher.matches("timex::modified"));
+
+    let matcher = FileAttributeMatcher::new("*");
+    assert!(matcher.matches("standard::name"));
+    assert!(matcher.matches("xattr::user.foo"));
+
+    let matcher = FileAttributeMatcher::new("");
+    assert!(!matcher.matches("standard::name"));
+    assert!(!matcher.enumerate_namespace("standard"));
+
+    // Exclusions win over wildcards
+    let matcher = FileAttributeMatcher::new("*, -standard::icon, -time::*");
+    assert!(matcher.matches("standard::name"));
+    assert!(!matcher.matches("standard::icon"));
+    assert!(!matcher.matches("time::modified"));
+    assert!(matcher.enumerate_namespace("standard"));
+    assert!(!matcher.enumerate_namespace("time"));
+
+    let matcher = FileAttributeMatcher::new("standard::name,unix::mode");
+    assert!(matcher.enumerate_namespace("unix"));
+    assert!(!matcher.enumerate_namespace("time"));
+}
+
+#[test]
+fn test_matcher_union_subtract() {
+    let a = FileAttributeMatcher::new("*,-standard::icon");
+    let b = FileAttributeMatcher::new("standard::icon");
+    let union = a.union(&b);
+    assert!(union.matches("standard::icon"));
+    assert!(union.matches("time::modified"));
+
+    let union = a.union(&FileAttributeMatcher::new("time::*"));
+    assert!(!union.matches("standard::icon"));
+
+    // Removing single keys from a namespace or from everything
+    let subtracted = FileAttributeMatcher::new("standard::*,time::modified")
+        .subtract(&FileAttributeMatcher::new("standard::size,time::*"));
+    assert!(subtracted.matches("standard::name"));
+    assert!(!subtracted.matches("standard::size"));
+    assert!(!subtracted.matches("time::modified"));
+
+    // What the subtracted matcher excludes stays
+    let subtracted = FileAttributeMatcher::new("*")
+        .subtract(&FileAttributeMatcher::new("standard::*,-standard::name"));
+    assert!(subtracted.matches("standard::name"));
+    assert!(!subtracted.matches("standard::size"));
+    assert!(subtracted.matches("unix::mode"));
+}
+
+#[tokio::test]
+async fn test_query_info_only_requested() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_attribute_matcher_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"content").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    let info = file.query_info("standard::name", None).await.unwrap();
+    assert_eq!(info.get_name(), Some("file.txt"));
+    assert!(!info.has_attribute("standard::size"));
+    assert!(!info.has_attribute("time::modified"));
+
+    // Expensive attributes are not part of "*"
+    let info = file.query_info("*,-standard::icon", None).await.unwrap();
+    assert_eq!(info.get_size(), 7);
+    assert!(info.has_attribute("standard::content-type"));
+    assert!(!info.has_attribute("standard::icon"));
+    assert!(!info.has_attribute("checksum::md5"));
+
+    // Enumerated children carry the requested attributes
+    let dir = get_file_for_uri(&format!("file://{}", test_dir.to_string_lossy())).unwrap();
+    let mut enumerator = dir.enumerate_children("standard::size,standard::name", None).await.unwrap();
+    let (info, _child) = enumerator.next_file(None).await.unwrap().expect("Missing child");
+    assert_eq!(info.get_name(), Some("file.txt"));
+    assert_eq!(info.get_size(), 7);
+    assert!(!info.has_attribute("standard::content-type"));
+    assert!(enumerator.next_file(None).await.unwrap().is_none());
+
+    let info = dir.query_filesystem_info("filesystem::size", None).await.unwrap();
+    assert!(info.has_attribute("filesystem::size"));
+    assert!(!info.has_attribute("filesystem::free"));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
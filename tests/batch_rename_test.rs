@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::job::batch_rename::{self, BatchRename, CaseChange, RenameConflict, RenameReplacement, RenameTemplate};
+use npio::{get_file_for_uri, register_backend, File, FileAttributeType, FileQueryInfoFlags};
+
+async fn setup(name: &str, files: &[&str]) -> (std::path::PathBuf, Vec<Box<dyn File>>) {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join(name);
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let mut handles = Vec::new();
+    for file in files {
+        let path = test_dir.join(file);
+        tokio::fs::write(&path, file.as_bytes()).await.unwrap();
+        handles.push(get_file_for_uri(&format!("file://{}", path.to_string_lossy())).unwrap());
+    }
+    (test_dir, handles)
+}
+
+#[test]
+fn test_render_template() {
+    let mut rename = BatchRename::new(RenameTemplate::parse("Holiday {n:3} - {name}{ext} {{x}}").unwrap());
+    rename.counter_start = 9;
+    rename.counter_step = 2;
+    assert_eq!(rename.render("beach.tar.gz", 0, None), "Holiday 009 - beach.tar.gz {x}");
+    assert_eq!(rename.render(".hidden", 1, None), "Holiday 011 - .hidden {x}");
+
+    let mut rename = BatchRename::new(RenameTemplate::parse("{name}{ext}").unwrap());
+    rename.replacements.push(RenameReplacement::new(r"IMG_(\d+)", "photo-$1").unwrap());
+    rename.case_change = CaseChange::Title;
+    assert_eq!(rename.render("IMG_0042.JPG", 0, None), "Photo-0042.Jpg");
+    rename.case_change = CaseChange::Lower;
+    assert_eq!(rename.render("IMG_0042.JPG", 0, None), "photo-0042.jpg");
+
+    assert!(RenameTemplate::parse("{name").is_err());
+    assert!(RenameTemplate::parse("{unknown}").is_err());
+    assert!(RenameTemplate::parse("{n:wide}").is_err());
+    assert!(RenameTemplate::parse("{n:100000}").is_err());
+    assert!(RenameTemplate::parse("{mtime:%Q}").is_err());
+    assert!(RenameReplacement::new("(", "").is_err());
+}
+
+#[tokio::test]
+async fn test_batch_rename_preview_conflicts() {
+    let (test_dir, files) = setup("npio_batch_rename_preview_test", &["a.txt", "b.txt", "other.txt"]).await;
+    tokio::fs::write(test_dir.join("taken.txt"), b"taken").await.unwrap();
+
+    // Both files end up with the same name
+    let rename = BatchRename::new(RenameTemplate::parse("same{ext}").unwrap());
+    let previews = batch_rename::preview(&files[..2], &rename, None).await.unwrap();
+    assert!(previews.iter().all(|p| p.conflict == Some(RenameConflict::Duplicate)));
+
+    // A file outside the batch already has the name
+    let rename = BatchRename::new(RenameTemplate::parse("taken{ext}").unwrap());
+    let previews = batch_rename::preview(&files[..1], &rename, None).await.unwrap();
+    assert_eq!(previews[0].new_name, "taken.txt");
+    assert_eq!(previews[0].conflict, Some(RenameConflict::Exists));
+    let err = batch_rename::apply(&files[..1], &rename, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Exists));
+
+    let rename = BatchRename::new(RenameTemplate::parse("a/b").unwrap());
+    let previews = batch_rename::preview(&files[..1], &rename, None).await.unwrap();
+    assert_eq!(previews[0].conflict, Some(RenameConflict::InvalidName));
+
+    // The name of another file in the batch is fine, that file moves away
+    let mut rename = BatchRename::new(RenameTemplate::parse("{name}{ext}").unwrap());
+    rename.replacements.push(RenameReplacement::new("^other", "a").unwrap());
+    rename.replacements.push(RenameReplacement::new("^a", "renamed").unwrap());
+    let previews = batch_rename::preview(&files, &rename, None).await.unwrap();
+    assert_eq!(previews[0].new_name, "renamed.txt");
+    assert_eq!(previews[2].new_name, "renamed.txt");
+    assert_eq!(previews[0].conflict, Some(RenameConflict::Duplicate));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_batch_rename_apply() {
+    let (test_dir, files) = setup("npio_batch_rename_apply_test", &["a.txt", "b.txt", "c.jpg"]).await;
+
+    // Swapping names needs temporary names
+    let mut rename = BatchRename::new(RenameTemplate::parse("{name}{ext}").unwrap());
+    rename.replacements.push(RenameReplacement::new(r"^a\.", "x.").unwrap());
+    rename.replacements.push(RenameReplacement::new(r"^b\.", "a.").unwrap());
+    rename.replacements.push(RenameReplacement::new(r"^x\.", "b.").unwrap());
+    let renamed = batch_rename::apply(&files, &rename, None, None).await.expect("Rename failed");
+    assert_eq!(tokio::fs::read(test_dir.join("b.txt")).await.unwrap(), b"a.txt");
+    assert_eq!(tokio::fs::read(test_dir.join("a.txt")).await.unwrap(), b"b.txt");
+    assert_eq!(renamed[0].basename(), "b.txt");
+    assert_eq!(renamed[2].basename(), "c.jpg");
+
+    // Modification time and counter
+    let modified = 1_592_222_400u64; // 2020-06-15
+    files[2].set_attribute("time::modified", &FileAttributeType::Uint64(modified), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let rename = BatchRename::new(RenameTemplate::parse("{mtime:%Y}-{n:2}{ext}").unwrap());
+    let renamed = batch_rename::apply(&files[2..], &rename, None, None).await.expect("Rename failed");
+    assert_eq!(renamed[0].basename(), "2020-01.jpg");
+    assert!(test_dir.join("2020-01.jpg").exists());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_batch_rename_rollback() {
+    let (test_dir, files) = setup("npio_batch_rename_rollback_test", &["a.txt", "b.txt", "c.txt"]).await;
+
+    let cancellable = npio::Cancellable::new();
+    let cancellable_clone = cancellable.clone();
+    let rename = BatchRename::new(RenameTemplate::parse("file-{n}{ext}").unwrap());
+    let result = batch_rename::apply(
+        &files,
+        &rename,
+        Some(Box::new(move |_current, _total| cancellable_clone.cancel())),
+        Some(&cancellable),
+    ).await;
+    assert!(matches!(result.unwrap_err().kind(), npio::IOErrorEnum::Cancelled));
+
+    // The first file was renamed before the cancellation and got its name back
+    let mut names: Vec<String> = std::fs::read_dir(&test_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["a.txt", "b.txt", "c.txt"]);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_batch_rename_failed_rollback() {
+    let (test_dir, files) = setup("npio_batch_rename_failed_rollback_test", &["a.txt", "b.txt"]).await;
+
+    // Something takes the old name of the first file before it can get it back
+    let cancellable = npio::Cancellable::new();
+    let cancellable_clone = cancellable.clone();
+    let blocker = test_dir.join("a.txt");
+    let rename = BatchRename::new(RenameTemplate::parse("file-{n}{ext}").unwrap());
+    let err = batch_rename::apply(
+        &files,
+        &rename,
+        Some(Box::new(move |_current, _total| {
+            std::fs::write(&blocker, b"blocker").unwrap();
+            cancellable_clone.cancel();
+        })),
+        Some(&cancellable),
+    ).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::PartialInput), "{}", err);
+    assert!(err.to_string().contains("file-1.txt"), "{}", err);
+
+    let mut names: Vec<String> = std::fs::read_dir(&test_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["a.txt", "b.txt", "file-1.txt"]);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_batch_rename_keeps_leftover_temporary_names() {
+    let (test_dir, files) = setup("npio_batch_rename_leftover_test", &["a.txt", "b.txt"]).await;
+
+    // Left behind by an earlier run of a process with the same ID
+    let leftover = test_dir.join(format!(".npio-rename-{}-0", std::process::id()));
+    std::fs::write(&leftover, b"leftover").unwrap();
+
+    // Swapping names goes through temporary names
+    let mut rename = BatchRename::new(RenameTemplate::parse("{name}{ext}").unwrap());
+    rename.replacements.push(RenameReplacement::new("^a", "c").unwrap());
+    rename.replacements.push(RenameReplacement::new("^b", "a").unwrap());
+    rename.replacements.push(RenameReplacement::new("^c", "b").unwrap());
+    batch_rename::apply(&files, &rename, None, None).await.unwrap();
+
+    assert_eq!(std::fs::read(test_dir.join("a.txt")).unwrap(), b"b.txt");
+    assert_eq!(std::fs::read(test_dir.join("b.txt")).unwrap(), b"a.txt");
+    assert_eq!(std::fs::read(&leftover).unwrap(), b"leftover");
+    assert_eq!(std::fs::read_dir(&test_dir).unwrap().count(), 3);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, ChecksumType, CopyFlags, FileAttributeType, IOErrorEnum};
+use npio::job;
+
+const ABC_DIGESTS: &[(ChecksumType, &str)] = &[
+    (ChecksumType::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+    (ChecksumType::Sha1, "a9993e364706816aba3e25717850c26c9cd0d89d"),
+    (ChecksumType::Sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+    (ChecksumType::Blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+];
+
+#[tokio::test]
+async fn test_compute_checksum() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_checksum_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let path = test_dir.join("abc.txt");
+    tokio::fs::write(&path, b"abc").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", path.to_string_lossy())).unwrap();
+
+    for (checksum_type, expected) in ABC_DIGESTS {
+        let progress = Arc::new(AtomicU64::new(0));
+        let progress_clone = progress.clone();
+        let digest = file.compute_checksum(
+            *checksum_type,
+            None,
+            Some(Box::new(move |current, _total| {
+                progress_clone.store(current, Ordering::SeqCst);
+            })),
+        ).await.expect("Checksum failed");
+        assert_eq!(digest, *expected);
+        assert_eq!(progress.load(Ordering::SeqCst), 3);
+    }
+
+    // Checksums are only reported when requested
+    let info = file.query_info("standard::*", None).await.unwrap();
+    assert!(!info.has_attribute("checksum::sha256"));
+
+    let info = file.query_info("checksum::*", None).await.unwrap();
+    for (checksum_type, expected) in ABC_DIGESTS {
+        assert_eq!(
+            info.get_attribute(checksum_type.attribute_key()),
+            Some(&FileAttributeType::String(expected.to_string()))
+        );
+    }
+
+    // Cancellation
+    let cancellable = npio::Cancellable::new();
+    cancellable.cancel();
+    let err = file.compute_checksum(ChecksumType::Sha256, Some(&cancellable), None).await.unwrap_err();
+    assert!(matches!(err.kind(), IOErrorEnum::Cancelled));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_verify() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_verify_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let src_path = test_dir.join("source.bin");
+    let content: Vec<u8> = (0..300_000).map(|i| (i % 253) as u8).collect();
+    tokio::fs::write(&src_path, &content).await.unwrap();
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+
+    let dest_path = test_dir.join("dest.bin");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+    job::copy(&*src_file, &*dest_file, CopyFlags::VERIFY, None, None).await.expect("Verified copy failed");
+    assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), content);
+
+    // Verified by the backend itself, not only through job::copy
+    let direct_path = test_dir.join("direct.bin");
+    let direct_file = get_file_for_uri(&format!("file://{}", direct_path.to_string_lossy())).unwrap();
+    src_file.copy(&*direct_file, CopyFlags::VERIFY, None, None).await.expect("Verified File::copy failed");
+    assert_eq!(tokio::fs::read(&direct_path).await.unwrap(), content);
+    let resumed_path = test_dir.join("resumed.bin");
+    let resumed_file = get_file_for_uri(&format!("file://{}", resumed_path.to_string_lossy())).unwrap();
+    src_file.copy(&*resumed_file, CopyFlags::VERIFY | CopyFlags::RESUMABLE, None, None).await
+        .expect("Verified resumable copy failed");
+    assert_eq!(tokio::fs::read(&resumed_path).await.unwrap(), content);
+
+    // A copied symlink has no contents of its own to compare
+    let link_path = test_dir.join("link");
+    std::os::unix::fs::symlink(&src_path, &link_path).unwrap();
+    let link_file = get_file_for_uri(&format!("file://{}", link_path.to_string_lossy())).unwrap();
+    let link_copy_path = test_dir.join("link-copy");
+    let link_copy_file = get_file_for_uri(&format!("file://{}", link_copy_path.to_string_lossy())).unwrap();
+    link_file.copy(&*link_copy_file, CopyFlags::VERIFY | CopyFlags::NOFOLLOW_SYMLINKS, None, None).await
+        .expect("Verified symlink copy failed");
+    assert!(link_copy_path.symlink_metadata().unwrap().file_type().is_symlink());
+
+    // A destination that differs from the source is reported as a mismatch
+    let other_path = test_dir.join("other.bin");
+    let mut corrupted = content.clone();
+    corrupted[1234] ^= 0xff;
+    tokio::fs::write(&other_path, &corrupted).await.unwrap();
+    let other_file = get_file_for_uri(&format!("file://{}", other_path.to_string_lossy())).unwrap();
+    let err = job::verify_copy(&*src_file, &*other_file, None).await.unwrap_err();
+    assert!(matches!(err.kind(), IOErrorEnum::ChecksumMismatch));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
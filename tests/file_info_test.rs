@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::file_info::registry;
+use npio::{
+    get_file_for_uri, register_backend, FileAttributeKind, FileAttributeType, FileInfo, FileQueryInfoFlags,
+};
+
+#[test]
+fn test_registry() {
+    let size = registry::lookup("standard::size").expect("Missing standard::size");
+    assert_eq!(size.kind, FileAttributeKind::Uint64);
+    assert_eq!(size.namespace(), "standard");
+    assert!(!size.settable);
+
+    let mode = registry::lookup("unix::mode").expect("Missing unix::mode");
+    assert!(mode.settable);
+    assert!(mode.flags.contains(npio::FileAttributeInfoFlags::COPY_WITH_FILE));
+
+    // Sized thumbnail attributes share the description of the plain one
+    assert_eq!(registry::lookup("thumbnail::path-large").map(|d| d.name), Some("thumbnail::path"));
+    assert!(registry::lookup("thumbnail::pathological").is_none());
+    assert!(registry::lookup("xattr::user.comment").is_none());
+    assert!(registry::all().iter().all(|d| d.name.contains("::")));
+}
+
+#[test]
+fn test_set_attribute_validation() {
+    let mut info = FileInfo::new();
+    let err = info.try_set_attribute("standard::size", FileAttributeType::Int64(42)).unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+    assert!(!info.has_attribute("standard::size"));
+
+    info.try_set_attribute("standard::size", FileAttributeType::Uint64(42)).unwrap();
+    assert_eq!(info.get_size(), 42);
+    info.try_set_attribute("standard::size", FileAttributeType::Invalid).unwrap();
+    info.try_set_attribute("xattr::anything", FileAttributeType::Int32(-1)).unwrap();
+}
+
+#[test]
+fn test_file_info_serde() {
+    let mut info = FileInfo::new();
+    info.set_name("report.pdf");
+    info.set_size(1024);
+    info.set_symlink_target(&[0xff, b'a']);
+    info.set_attribute("xattr::tag", FileAttributeType::Stringv(vec!["a".to_string(), "b".to_string()]));
+
+    let json = serde_json::to_string(&info).unwrap();
+    assert!(json.starts_with(&format!("{{\"version\":{},", npio::FILE_INFO_FORMAT_VERSION)));
+    assert!(json.contains("\"standard::size\":{\"uint64\":1024}"));
+    // Attributes are sorted, so the output is stable
+    assert_eq!(serde_json::to_string(&info.clone()).unwrap(), json);
+
+    let decoded: FileInfo = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.get_name(), Some("report.pdf"));
+    assert_eq!(decoded.get_size(), 1024);
+    assert_eq!(decoded.get_symlink_target(), Some(&[0xff, b'a'][..]));
+    assert_eq!(decoded.get_attribute("xattr::tag"), info.get_attribute("xattr::tag"));
+
+    // Newer formats and wrongly typed values are rejected
+    let newer = json.replacen(
+        &format!("\"version\":{}", npio::FILE_INFO_FORMAT_VERSION),
+        &format!("\"version\":{}", npio::FILE_INFO_FORMAT_VERSION + 1),
+        1,
+    );
+    assert!(serde_json::from_str::<FileInfo>(&newer).is_err());
+    let mistyped = r#"{"version":1,"attributes":{"standard::size":{"int64":5}}}"#;
+    assert!(serde_json::from_str::<FileInfo>(mistyped).is_err());
+}
+
+#[tokio::test]
+async fn test_set_attribute_wrong_type() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_file_info_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"typed").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    let err = file
+        .set_attribute("unix::mode", &FileAttributeType::Uint64(0o600), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+
+    let settable = file.query_settable_attributes(None).await.unwrap();
+    assert!(settable.iter().any(|a| a.name == "unix::mode" && a.kind == FileAttributeKind::Uint32));
+    assert!(!settable.iter().any(|a| a.name == "standard::size"));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[test]
+fn test_file_info_set_attribute_wrong_type_keeps_value() {
+    let mut info = FileInfo::new();
+    info.set_attribute("standard::size", FileAttributeType::String("42".to_string()));
+    assert!(!info.has_attribute("standard::size"));
+
+    info.set_size(7);
+    info.set_attribute("standard::size", FileAttributeType::String("42".to_string()));
+    assert_eq!(info.get_size(), 7);
+}
@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+use npio::{FileInfo, Icon, IconTheme};
+
+const CHILD_INDEX: &str = "[Icon Theme]\n\
+Name=Child\n\
+Inherits=Parent\n\
+Directories=16x16/apps,48x48/apps,scalable/apps\n\
+ScaledDirectories=16x16@2/apps\n\
+\n\
+[16x16/apps]\nSize=16\nType=Fixed\n\
+\n\
+[16x16@2/apps]\nSize=16\nScale=2\nType=Fixed\n\
+\n\
+[48x48/apps]\nSize=48\nType=Threshold\n\
+\n\
+[scalable/apps]\nSize=64\nMinSize=8\nMaxSize=512\nType=Scalable\n";
+
+const PARENT_INDEX: &str = "[Icon Theme]\nName=Parent\nDirectories=24x24/devices\n\n[24x24/devices]\nSize=24\nType=Fixed\n";
+
+const HICOLOR_INDEX: &str = "[Icon Theme]\nName=Hicolor\nDirectories=32x32/apps\n\n[32x32/apps]\nSize=32\n";
+
+fn touch(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, b"").unwrap();
+}
+
+#[test]
+fn test_icon_theme_lookup() {
+    let test_dir = std::env::temp_dir().join("npio_icon_test");
+    if test_dir.exists() {
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+    let icons = test_dir.join("icons");
+    let pixmaps = test_dir.join("pixmaps");
+    let child = icons.join("Child");
+    std::fs::create_dir_all(&child).unwrap();
+    std::fs::create_dir_all(icons.join("Parent")).unwrap();
+    std::fs::create_dir_all(icons.join("hicolor")).unwrap();
+    std::fs::write(child.join("index.theme"), CHILD_INDEX).unwrap();
+    std::fs::write(icons.join("Parent/index.theme"), PARENT_INDEX).unwrap();
+    std::fs::write(icons.join("hicolor/index.theme"), HICOLOR_INDEX).unwrap();
+    touch(&child.join("16x16/apps/editor.png"));
+    touch(&child.join("16x16@2/apps/editor.png"));
+    touch(&child.join("48x48/apps/editor.png"));
+    touch(&child.join("scalable/apps/editor.svg"));
+    touch(&child.join("scalable/apps/viewer.svg"));
+    touch(&icons.join("Parent/24x24/devices/drive-harddisk.png"));
+    touch(&icons.join("hicolor/32x32/apps/fallback.png"));
+    touch(&pixmaps.join("legacy.xpm"));
+
+    let theme = IconTheme::with_search_path("Child", vec![icons.clone(), pixmaps.clone()]);
+    assert_eq!(theme.name(), "Child");
+    assert_eq!(theme.themes(), vec!["Child", "Parent", "hicolor"]);
+
+    // Fixed sizes match exactly, at the right scale
+    assert_eq!(theme.lookup("editor", 16, 1), Some(child.join("16x16/apps/editor.png")));
+    assert_eq!(theme.lookup("editor", 16, 2), Some(child.join("16x16@2/apps/editor.png")));
+    // Within the threshold of 48
+    assert_eq!(theme.lookup("editor", 50, 1), Some(child.join("48x48/apps/editor.png")));
+    // Only the scalable directory covers 128
+    assert_eq!(theme.lookup("editor", 128, 1), Some(child.join("scalable/apps/editor.svg")));
+    // Nothing matches 1024: the closest directory wins
+    assert_eq!(theme.lookup("viewer", 1024, 1), Some(child.join("scalable/apps/viewer.svg")));
+    assert_eq!(theme.lookup("editor", 1024, 1), Some(child.join("scalable/apps/editor.svg")));
+
+    // Inherited themes, hicolor and unthemed icons
+    assert_eq!(theme.lookup("drive-harddisk", 16, 1), Some(icons.join("Parent/24x24/devices/drive-harddisk.png")));
+    assert_eq!(theme.lookup("fallback", 16, 1), Some(icons.join("hicolor/32x32/apps/fallback.png")));
+    assert_eq!(theme.lookup("legacy", 16, 1), Some(pixmaps.join("legacy.xpm")));
+    assert_eq!(theme.lookup("missing", 16, 1), None);
+    assert!(theme.has_icon("drive-harddisk"));
+    assert!(!theme.has_icon("legacy"));
+
+    // Icons try their names in order
+    let usb = Icon::themed_with_default_fallbacks("drive-harddisk-usb");
+    assert_eq!(theme.lookup_icon(&usb, 24, 1), Some(icons.join("Parent/24x24/devices/drive-harddisk.png")));
+    let file = Icon::File(pixmaps.join("legacy.xpm"));
+    assert_eq!(theme.lookup_icon(&file, 24, 1), Some(pixmaps.join("legacy.xpm")));
+    let emblemed = Icon::emblemed(Icon::themed("editor"), vec![Icon::themed("emblem-readonly")]);
+    assert_eq!(theme.lookup_icon(&emblemed, 16, 1), Some(child.join("16x16/apps/editor.png")));
+    assert_eq!(theme.lookup_icon(&Icon::File(PathBuf::from("/nonexistent/icon.png")), 16, 1), None);
+
+    // Lookups are cached, so files added later are only seen by a new theme
+    touch(&child.join("16x16/apps/missing.png"));
+    assert_eq!(theme.lookup("missing", 16, 1), None);
+    let reloaded = IconTheme::with_search_path("Child", vec![icons.clone(), pixmaps.clone()]);
+    assert_eq!(reloaded.lookup("missing", 16, 1), Some(child.join("16x16/apps/missing.png")));
+
+    // An unknown theme still falls back to hicolor
+    let unknown = IconTheme::with_search_path("Unknown", vec![icons.clone()]);
+    assert_eq!(unknown.themes(), vec!["hicolor"]);
+    assert_eq!(unknown.lookup("fallback", 32, 1), Some(icons.join("hicolor/32x32/apps/fallback.png")));
+
+    std::fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_icon_strings() {
+    assert_eq!(
+        Icon::themed_with_default_fallbacks("drive-harddisk-usb").names(),
+        &["drive-harddisk-usb", "drive-harddisk", "drive"]
+    );
+    assert_eq!(
+        Icon::themed_with_default_fallbacks("drive-removable-media-symbolic").names(),
+        &["drive-removable-media-symbolic", "drive-removable-symbolic", "drive-symbolic"]
+    );
+
+    let icons = vec![
+        Icon::themed("folder"),
+        Icon::Themed(vec!["text-x-rust".to_string(), "text-x-generic".to_string()]),
+        Icon::themed("name with space"),
+        Icon::File(PathBuf::from("/usr/share/pixmaps/app.png")),
+        Icon::File(PathBuf::from("relative.png")),
+        Icon::emblemed(Icon::themed("folder"), vec![Icon::themed("emblem-readonly"), Icon::File(PathBuf::from("/e.png"))]),
+    ];
+    for icon in icons {
+        assert_eq!(Icon::from_string(&icon.to_string()).unwrap(), icon, "{}", icon);
+    }
+    assert_eq!(Icon::themed("folder").to_string(), "folder");
+    assert_eq!(Icon::File(PathBuf::from("/a/b.png")).to_string(), "/a/b.png");
+    assert_eq!(Icon::Themed(vec!["a".to_string(), "b c".to_string()]).to_string(), ". ThemedIcon a b%20c");
+    assert_eq!(Icon::emblemed(Icon::themed("folder"), Vec::new()), Icon::themed("folder"));
+    assert!(Icon::from_string("").is_err());
+    assert!(Icon::from_string(". UnknownIcon x").is_err());
+
+    // FileInfo icons carry the content type's fallbacks
+    let mut info = FileInfo::new();
+    info.set_content_type("text/x-rust");
+    info.set_attribute("standard::icon", npio::FileAttributeType::String("text-x-rust".to_string()));
+    let icon = info.get_icon().unwrap();
+    assert_eq!(icon.names().first().map(String::as_str), Some("text-x-rust"));
+    assert!(icon.names().len() > 1);
+    info.set_attribute("standard::symbolic-icon", npio::FileAttributeType::String("/custom.svg".to_string()));
+    assert_eq!(info.get_symbolic_icon(), Some(Icon::File(PathBuf::from("/custom.svg"))));
+}
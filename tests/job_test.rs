@@ -77,3 +77,630 @@ async fn test_job_lifecycle() {
     // Cleanup
     tokio::fs::remove_dir_all(&test_dir).await.ok();
 }
+
+#[tokio::test]
+async fn test_copy_methods_produce_identical_content() {
+    use npio::file::local::copy::{copy_file_contents, CopyMethod};
+
+    let test_dir = std::env::temp_dir().join("npio_copy_methods_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    // Larger than one read/write buffer, with non-repeating content
+    let content: Vec<u8> = (0..1024 * 1024 + 123).map(|i| (i % 251) as u8).collect();
+    let src_path = test_dir.join("source.bin");
+    tokio::fs::write(&src_path, &content).await.unwrap();
+
+    for method in [CopyMethod::CopyFileRange, CopyMethod::Sendfile, CopyMethod::ReadWrite] {
+        let dest_path = test_dir.join(format!("dest_{:?}.bin", method));
+        let mut source = std::fs::File::open(&src_path).unwrap();
+        let mut destination = std::fs::File::create(&dest_path).unwrap();
+
+        let last_progress = Arc::new(AtomicU64::new(0));
+        let progress_clone = last_progress.clone();
+        let expected_total = content.len() as u64;
+        let progress: npio::ProgressCallback = Box::new(move |current, total| {
+            assert_eq!(total, expected_total);
+            progress_clone.store(current, Ordering::SeqCst);
+        });
+
+        // Fall back to read/write if the kernel method is unavailable on this filesystem
+        let used = copy_file_contents(
+            &mut source,
+            &mut destination,
+            &[method, CopyMethod::ReadWrite],
+            None,
+            Some(&progress),
+        ).expect("Copy failed");
+        assert!(used == method || used == CopyMethod::ReadWrite);
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), content);
+        assert_eq!(last_progress.load(Ordering::SeqCst), content.len() as u64);
+    }
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_cancelled() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_cancel_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let src_path = test_dir.join("source.txt");
+    tokio::fs::write(&src_path, vec![b'a'; 1024]).await.unwrap();
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+    let dest_path = test_dir.join("dest.txt");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+
+    let cancellable = npio::Cancellable::new();
+    cancellable.cancel();
+
+    let result = job::copy(&*src_file, &*dest_file, CopyFlags::NONE, None, Some(&cancellable)).await;
+    assert!(matches!(result.unwrap_err().kind(), npio::IOErrorEnum::Cancelled));
+    assert!(!dest_path.exists());
+
+    // Cancelling partway through removes what was written so far
+    let large_path = test_dir.join("large.bin");
+    tokio::fs::write(&large_path, vec![b'b'; 64 * 1024 * 1024]).await.unwrap();
+    let large_file = get_file_for_uri(&format!("file://{}", large_path.to_string_lossy())).unwrap();
+    let cancellable = npio::Cancellable::new();
+    let cancellable_clone = cancellable.clone();
+    let written = Arc::new(AtomicU64::new(0));
+    let written_clone = written.clone();
+    let result = job::copy(
+        &*large_file,
+        &*dest_file,
+        CopyFlags::NONE,
+        Some(Box::new(move |current, _total| {
+            written_clone.store(current, Ordering::SeqCst);
+            cancellable_clone.cancel();
+        })),
+        Some(&cancellable),
+    ).await;
+    assert!(matches!(result.unwrap_err().kind(), npio::IOErrorEnum::Cancelled));
+    let written = written.load(Ordering::SeqCst);
+    assert!(written > 0 && written < 64 * 1024 * 1024, "{}", written);
+    assert!(!dest_path.exists());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_preserves_metadata() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_metadata_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let src_path = test_dir.join("source.txt");
+    tokio::fs::write(&src_path, b"metadata").await.unwrap();
+    std::fs::set_permissions(&src_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let old_time = std::time::UNIX_EPOCH + std::time::Duration::new(1_000_000_000, 123_456_789);
+    std::fs::File::options().write(true).open(&src_path).unwrap().set_modified(old_time).unwrap();
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+
+    // Permissions are copied by default, timestamps only with ALL_METADATA
+    let default_path = test_dir.join("default.txt");
+    let default_file = get_file_for_uri(&format!("file://{}", default_path.to_string_lossy())).unwrap();
+    job::copy(&*src_file, &*default_file, CopyFlags::NONE, None, None).await.expect("Copy failed");
+    let default_meta = std::fs::metadata(&default_path).unwrap();
+    assert_eq!(default_meta.mode() & 0o7777, 0o640);
+    assert_ne!(default_meta.mtime(), 1_000_000_000);
+
+    let all_path = test_dir.join("all.txt");
+    let all_file = get_file_for_uri(&format!("file://{}", all_path.to_string_lossy())).unwrap();
+    job::copy(&*src_file, &*all_file, CopyFlags::ALL_METADATA, None, None).await.expect("Copy failed");
+    let all_meta = std::fs::metadata(&all_path).unwrap();
+    assert_eq!(all_meta.mode() & 0o7777, 0o640);
+    assert_eq!(all_meta.mtime(), 1_000_000_000);
+    assert_eq!(all_meta.mtime_nsec(), 123_456_789);
+
+    // TARGET_DEFAULT_PERMS keeps the permissions a new file gets anyway
+    let default_perms_path = test_dir.join("default_perms.txt");
+    let default_perms_file = get_file_for_uri(&format!("file://{}", default_perms_path.to_string_lossy())).unwrap();
+    job::copy(&*src_file, &*default_perms_file, CopyFlags::TARGET_DEFAULT_PERMS, None, None).await.expect("Copy failed");
+    let fresh_path = test_dir.join("fresh.txt");
+    std::fs::write(&fresh_path, b"").unwrap();
+    assert_eq!(
+        std::fs::metadata(&default_perms_path).unwrap().mode() & 0o7777,
+        std::fs::metadata(&fresh_path).unwrap().mode() & 0o7777
+    );
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_preserves_large_xattrs() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    // tmpfs takes values up to the kernel's 64 KiB limit, unlike ext4 without ea_inode
+    let test_dir = std::path::PathBuf::from("/dev/shm").join("npio_copy_large_xattr_test");
+    if !std::path::Path::new("/dev/shm").is_dir() {
+        return;
+    }
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let src_path = test_dir.join("source.txt");
+    tokio::fs::write(&src_path, b"xattrs").await.unwrap();
+    let value: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let c_src = std::ffi::CString::new(src_path.to_string_lossy().as_bytes()).unwrap();
+    let set = unsafe {
+        libc::setxattr(c_src.as_ptr(), c"user.large".as_ptr(), value.as_ptr().cast(), value.len(), 0)
+    };
+    if set != 0 {
+        tokio::fs::remove_dir_all(&test_dir).await.ok();
+        return;
+    }
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+
+    let dest_path = test_dir.join("dest.txt");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+    job::copy(&*src_file, &*dest_file, CopyFlags::ALL_METADATA, None, None).await.expect("Copy failed");
+
+    let c_dest = std::ffi::CString::new(dest_path.to_string_lossy().as_bytes()).unwrap();
+    let mut copied = vec![0u8; value.len()];
+    let size = unsafe {
+        libc::getxattr(c_dest.as_ptr(), c"user.large".as_ptr(), copied.as_mut_ptr().cast(), copied.len())
+    };
+    assert_eq!(size, value.len() as isize);
+    assert_eq!(copied, value);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_preserve_sparse() {
+    use std::os::unix::fs::{FileExt, MetadataExt};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_sparse_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    // 16 MiB file with a single data block in the middle
+    let size = 16 * 1024 * 1024u64;
+    let src_path = test_dir.join("sparse.bin");
+    {
+        let file = std::fs::File::create(&src_path).unwrap();
+        file.set_len(size).unwrap();
+        file.write_all_at(b"data in the middle", size / 2).unwrap();
+    }
+    let src_meta = std::fs::metadata(&src_path).unwrap();
+    if src_meta.blocks() * 512 >= size {
+        // The filesystem holding the temp dir does not support holes
+        tokio::fs::remove_dir_all(&test_dir).await.ok();
+        return;
+    }
+
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+    let dest_path = test_dir.join("copy.bin");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+
+    let progress = Arc::new(AtomicU64::new(0));
+    let progress_clone = progress.clone();
+    job::copy(
+        &*src_file,
+        &*dest_file,
+        CopyFlags::PRESERVE_SPARSE,
+        Some(Box::new(move |current, _total| {
+            progress_clone.store(current, Ordering::SeqCst);
+        })),
+        None,
+    ).await.expect("Copy failed");
+
+    let dest_meta = std::fs::metadata(&dest_path).unwrap();
+    assert_eq!(dest_meta.len(), size);
+    assert!(dest_meta.blocks() * 512 < size);
+    assert_eq!(progress.load(Ordering::SeqCst), size);
+    assert_eq!(std::fs::read(&dest_path).unwrap(), std::fs::read(&src_path).unwrap());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_symlink_and_backup() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_symlink_backup_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let target_path = test_dir.join("target.txt");
+    tokio::fs::write(&target_path, b"new").await.unwrap();
+    let link_path = test_dir.join("link");
+    std::os::unix::fs::symlink("target.txt", &link_path).unwrap();
+    let link_file = get_file_for_uri(&format!("file://{}", link_path.to_string_lossy())).unwrap();
+
+    // NOFOLLOW_SYMLINKS copies the link itself
+    let link_copy_path = test_dir.join("link_copy");
+    let link_copy = get_file_for_uri(&format!("file://{}", link_copy_path.to_string_lossy())).unwrap();
+    job::copy(&*link_file, &*link_copy, CopyFlags::NOFOLLOW_SYMLINKS, None, None).await.expect("Copy failed");
+    assert_eq!(std::fs::read_link(&link_copy_path).unwrap(), std::path::PathBuf::from("target.txt"));
+
+    // BACKUP keeps the overwritten destination as "<name>~"
+    let dest_path = test_dir.join("dest.txt");
+    tokio::fs::write(&dest_path, b"old").await.unwrap();
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+    job::copy(&*link_file, &*dest_file, CopyFlags::OVERWRITE | CopyFlags::BACKUP, None, None).await.expect("Copy failed");
+    assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), b"new");
+    assert_eq!(tokio::fs::read(test_dir.join("dest.txt~")).await.unwrap(), b"old");
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_move_across_filesystems() {
+    use std::os::unix::fs::MetadataExt;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    // /dev/shm is usually a tmpfs, separate from the temp dir
+    let shm_dir = std::path::PathBuf::from("/dev/shm").join("npio_move_xdev_test");
+    let test_dir = std::env::temp_dir().join("npio_move_xdev_test");
+    let shm_root = std::path::Path::new("/dev/shm");
+    if !shm_root.is_dir()
+        || std::fs::metadata(shm_root).unwrap().dev() == std::fs::metadata(std::env::temp_dir()).unwrap().dev()
+    {
+        return;
+    }
+    for dir in [&shm_dir, &test_dir] {
+        if dir.exists() {
+            tokio::fs::remove_dir_all(dir).await.unwrap();
+        }
+        tokio::fs::create_dir(dir).await.unwrap();
+    }
+
+    // Source tree: tree/a.txt, tree/sub/b.txt, tree/link -> a.txt
+    let tree_path = shm_dir.join("tree");
+    tokio::fs::create_dir_all(tree_path.join("sub")).await.unwrap();
+    tokio::fs::write(tree_path.join("a.txt"), vec![b'a'; 1000]).await.unwrap();
+    tokio::fs::write(tree_path.join("sub").join("b.txt"), vec![b'b'; 2000]).await.unwrap();
+    std::os::unix::fs::symlink("a.txt", tree_path.join("link")).unwrap();
+    let tree = get_file_for_uri(&format!("file://{}", tree_path.to_string_lossy())).unwrap();
+
+    let moved_path = test_dir.join("tree");
+    let moved = get_file_for_uri(&format!("file://{}", moved_path.to_string_lossy())).unwrap();
+
+    // Without fallback the move is refused and nothing changes
+    let err = job::move_(&*tree, &*moved, CopyFlags::NO_FALLBACK_FOR_MOVE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::WouldRecurse));
+    assert!(tree_path.exists());
+    assert!(!moved_path.exists());
+
+    let single_path = shm_dir.join("single.txt");
+    tokio::fs::write(&single_path, b"single").await.unwrap();
+    let single = get_file_for_uri(&format!("file://{}", single_path.to_string_lossy())).unwrap();
+    let single_dest = get_file_for_uri(&format!("file://{}", test_dir.join("single.txt").to_string_lossy())).unwrap();
+    let err = job::move_(&*single, &*single_dest, CopyFlags::NO_FALLBACK_FOR_MOVE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::NotSupported));
+
+    // With fallback the whole tree is copied, verified, then removed
+    let progress = Arc::new(std::sync::Mutex::new((0u64, 0u64)));
+    let progress_clone = progress.clone();
+    job::move_(
+        &*tree,
+        &*moved,
+        CopyFlags::VERIFY,
+        Some(Box::new(move |current, total| {
+            *progress_clone.lock().unwrap() = (current, total);
+        })),
+        None,
+    ).await.expect("Move failed");
+
+    assert!(!tree_path.exists());
+    assert_eq!(tokio::fs::read(moved_path.join("a.txt")).await.unwrap().len(), 1000);
+    assert_eq!(tokio::fs::read(moved_path.join("sub").join("b.txt")).await.unwrap().len(), 2000);
+    assert_eq!(std::fs::read_link(moved_path.join("link")).unwrap(), std::path::PathBuf::from("a.txt"));
+    assert_eq!(*progress.lock().unwrap(), (3000, 3000));
+
+    tokio::fs::remove_dir_all(&shm_dir).await.ok();
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_failed_move_across_filesystems_leaves_no_partial_copy() {
+    use std::os::unix::fs::MetadataExt;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let shm_dir = std::path::PathBuf::from("/dev/shm").join("npio_move_xdev_fail_test");
+    let test_dir = std::env::temp_dir().join("npio_move_xdev_fail_test");
+    let shm_root = std::path::Path::new("/dev/shm");
+    if !shm_root.is_dir()
+        || std::fs::metadata(shm_root).unwrap().dev() == std::fs::metadata(std::env::temp_dir()).unwrap().dev()
+    {
+        return;
+    }
+    for dir in [&shm_dir, &test_dir] {
+        if dir.exists() {
+            tokio::fs::remove_dir_all(dir).await.unwrap();
+        }
+        tokio::fs::create_dir(dir).await.unwrap();
+    }
+
+    // A FIFO deep in the tree cannot be copied
+    let tree_path = shm_dir.join("tree");
+    tokio::fs::create_dir_all(tree_path.join("sub")).await.unwrap();
+    tokio::fs::write(tree_path.join("a.txt"), b"a").await.unwrap();
+    tokio::fs::write(tree_path.join("sub").join("b.txt"), b"b").await.unwrap();
+    let fifo_path = std::ffi::CString::new(tree_path.join("sub").join("fifo").to_string_lossy().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(fifo_path.as_ptr(), 0o644) }, 0);
+    let tree = get_file_for_uri(&format!("file://{}", tree_path.to_string_lossy())).unwrap();
+
+    let moved_path = test_dir.join("tree");
+    let moved = get_file_for_uri(&format!("file://{}", moved_path.to_string_lossy())).unwrap();
+    let err = job::move_(&*tree, &*moved, CopyFlags::NONE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::NotSupported));
+    assert!(!moved_path.exists());
+    assert!(tree_path.join("sub").join("b.txt").exists());
+
+    // Failing halfway through removes what was copied so far
+    std::fs::remove_file(tree_path.join("sub").join("fifo")).unwrap();
+    let cancellable = npio::Cancellable::new();
+    let cancel_clone = cancellable.clone();
+    let err = job::move_(
+        &*tree,
+        &*moved,
+        CopyFlags::NONE,
+        Some(Box::new(move |_current, _total| cancel_clone.cancel())),
+        Some(&cancellable),
+    ).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Cancelled), "{}", err);
+    assert!(!moved_path.exists());
+    assert!(tree_path.join("a.txt").exists());
+
+    tokio::fs::remove_dir_all(&shm_dir).await.ok();
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_move_across_filesystems_reports_leftover_source() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::os::unix::io::AsRawFd;
+
+    // FS_IMMUTABLE_FL from linux/fs.h
+    const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let shm_dir = std::path::PathBuf::from("/dev/shm").join("npio_move_xdev_leftover_test");
+    let test_dir = std::env::temp_dir().join("npio_move_xdev_leftover_test");
+    let shm_root = std::path::Path::new("/dev/shm");
+    if !shm_root.is_dir()
+        || std::fs::metadata(shm_root).unwrap().dev() == std::fs::metadata(std::env::temp_dir()).unwrap().dev()
+    {
+        return;
+    }
+    for dir in [&shm_dir, &test_dir] {
+        if dir.exists() {
+            tokio::fs::remove_dir_all(dir).await.unwrap();
+        }
+        tokio::fs::create_dir(dir).await.unwrap();
+    }
+
+    let tree_path = shm_dir.join("tree");
+    let locked_path = tree_path.join("locked");
+    tokio::fs::create_dir_all(&locked_path).await.unwrap();
+    tokio::fs::write(tree_path.join("a.txt"), b"a").await.unwrap();
+    tokio::fs::write(locked_path.join("b.txt"), b"b").await.unwrap();
+
+    // A read-only directory keeps its entries, except for root, where only an
+    // immutable entry does
+    std::fs::set_permissions(&locked_path, std::fs::Permissions::from_mode(0o555)).unwrap();
+    let pinned = std::fs::File::open(locked_path.join("b.txt")).unwrap();
+    let immutable = unsafe { libc::geteuid() } == 0
+        && unsafe { libc::ioctl(pinned.as_raw_fd(), libc::FS_IOC_SETFLAGS, &FS_IMMUTABLE_FL) } == 0;
+    if unsafe { libc::geteuid() } == 0 && !immutable {
+        std::fs::set_permissions(&locked_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        tokio::fs::remove_dir_all(&shm_dir).await.ok();
+        tokio::fs::remove_dir_all(&test_dir).await.ok();
+        return;
+    }
+
+    let tree = get_file_for_uri(&format!("file://{}", tree_path.to_string_lossy())).unwrap();
+    let moved_path = test_dir.join("tree");
+    let moved = get_file_for_uri(&format!("file://{}", moved_path.to_string_lossy())).unwrap();
+    let err = job::move_(&*tree, &*moved, CopyFlags::NONE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::PartialInput), "{}", err);
+    assert!(err.to_string().contains(&*tree_path.to_string_lossy()), "{}", err);
+
+    // The copy is complete and what could not be removed is still there
+    assert_eq!(tokio::fs::read(moved_path.join("a.txt")).await.unwrap(), b"a");
+    assert_eq!(tokio::fs::read(moved_path.join("locked").join("b.txt")).await.unwrap(), b"b");
+    assert!(locked_path.join("b.txt").exists());
+
+    if immutable {
+        let flags: libc::c_int = 0;
+        unsafe { libc::ioctl(pinned.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) };
+    }
+    std::fs::set_permissions(&locked_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::set_permissions(moved_path.join("locked"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    tokio::fs::remove_dir_all(&shm_dir).await.ok();
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_resumable() {
+    use npio::file::local::resume::CopyJournal;
+
+    const MIB: usize = 1024 * 1024;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_resumable_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    // A repeating pattern that does not line up with the copy chunks catches offset mistakes
+    let content: Vec<u8> = (0..40 * MIB).map(|i| (i % 251) as u8).collect();
+    let src_path = test_dir.join("source.bin");
+    tokio::fs::write(&src_path, &content).await.unwrap();
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+    let dest_path = test_dir.join("dest.bin");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+    let journal_path = CopyJournal::path_for(&dest_path);
+
+    // Interrupt the copy halfway through
+    let cancellable = npio::Cancellable::new();
+    let cancellable_clone = cancellable.clone();
+    let result = job::copy(
+        &*src_file,
+        &*dest_file,
+        CopyFlags::RESUMABLE,
+        Some(Box::new(move |current, _total| {
+            if current >= 20 * MIB as u64 {
+                cancellable_clone.cancel();
+            }
+        })),
+        Some(&cancellable),
+    ).await;
+    assert!(matches!(result.unwrap_err().kind(), npio::IOErrorEnum::Cancelled));
+
+    let journal = CopyJournal::load(&journal_path).expect("Journal missing after interruption");
+    assert_eq!(journal.source_size, content.len() as u64);
+    assert!(journal.committed > 0 && journal.committed < content.len() as u64);
+    assert!(std::fs::metadata(&dest_path).unwrap().len() >= journal.committed);
+
+    // Continue where the first attempt stopped
+    let first_progress = Arc::new(AtomicU64::new(u64::MAX));
+    let first_progress_clone = first_progress.clone();
+    let monitor = npio::VolumeMonitor::new();
+    job::copy_resumable(
+        &*src_file,
+        &*dest_file,
+        CopyFlags::NONE,
+        Some(Box::new(move |current, _total| {
+            first_progress_clone.fetch_min(current, Ordering::SeqCst);
+        })),
+        None,
+        &monitor,
+    ).await.expect("Resumed copy failed");
+
+    assert!(first_progress.load(Ordering::SeqCst) > journal.committed);
+    assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), content);
+    assert!(!journal_path.exists());
+
+    // Without a journal an existing destination is only replaced with OVERWRITE
+    let err = job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Exists));
+
+    // A journal for a source that has changed since is not trusted
+    CopyJournal {
+        committed: 16 * MIB as u64,
+        ..journal
+    }.store(&journal_path).unwrap();
+    let changed: Vec<u8> = content.iter().map(|b| b.wrapping_add(1)).collect();
+    tokio::fs::write(&src_path, &changed).await.unwrap();
+    // Starting over replaces the destination, which needs OVERWRITE
+    let err = job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Exists));
+    assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), content);
+    job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE | CopyFlags::OVERWRITE, None, None)
+        .await
+        .expect("Restarted copy failed");
+    assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), changed);
+    assert!(!journal_path.exists());
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_copy_resumable_damaged_destination() {
+    use npio::file::local::resume::CopyJournal;
+
+    const MIB: usize = 1024 * 1024;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_copy_resumable_damaged_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+
+    let content: Vec<u8> = (0..40 * MIB).map(|i| (i % 251) as u8).collect();
+    let src_path = test_dir.join("source.bin");
+    tokio::fs::write(&src_path, &content).await.unwrap();
+    let src_file = get_file_for_uri(&format!("file://{}", src_path.to_string_lossy())).unwrap();
+    let dest_path = test_dir.join("dest.bin");
+    let dest_file = get_file_for_uri(&format!("file://{}", dest_path.to_string_lossy())).unwrap();
+    let journal_path = CopyJournal::path_for(&dest_path);
+
+    let interrupt = || async {
+        let cancellable = npio::Cancellable::new();
+        let cancellable_clone = cancellable.clone();
+        let result = job::copy(
+            &*src_file,
+            &*dest_file,
+            CopyFlags::RESUMABLE | CopyFlags::OVERWRITE,
+            Some(Box::new(move |current, _total| {
+                if current >= 20 * MIB as u64 {
+                    cancellable_clone.cancel();
+                }
+            })),
+            Some(&cancellable),
+        ).await;
+        assert!(matches!(result.unwrap_err().kind(), npio::IOErrorEnum::Cancelled));
+        CopyJournal::load(&journal_path).expect("Journal missing after interruption")
+    };
+
+    // A destination truncated after the interruption is not resumed into a file with a hole
+    let journal = interrupt().await;
+    std::fs::OpenOptions::new().write(true).open(&dest_path).unwrap().set_len(journal.committed / 2).unwrap();
+    let err = job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Exists));
+    job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE | CopyFlags::OVERWRITE, None, None)
+        .await
+        .expect("Restarted copy failed");
+    assert!(tokio::fs::read(&dest_path).await.unwrap() == content);
+    assert!(!journal_path.exists());
+
+    // A symlink put in place of the destination is replaced, not written through
+    interrupt().await;
+    let target_path = test_dir.join("target.bin");
+    tokio::fs::write(&target_path, b"target").await.unwrap();
+    std::fs::remove_file(&dest_path).unwrap();
+    std::os::unix::fs::symlink(&target_path, &dest_path).unwrap();
+    let err = job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE, None, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::Exists));
+    job::copy(&*src_file, &*dest_file, CopyFlags::RESUMABLE | CopyFlags::OVERWRITE, None, None)
+        .await
+        .expect("Restarted copy failed");
+    assert!(!std::fs::symlink_metadata(&dest_path).unwrap().file_type().is_symlink());
+    assert!(tokio::fs::read(&dest_path).await.unwrap() == content);
+    assert_eq!(tokio::fs::read(&target_path).await.unwrap(), b"target");
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
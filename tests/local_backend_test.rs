@@ -47,3 +47,368 @@ async fn test_local_backend_lifecycle() {
     // 9. Verify deleted
     assert!(!file.exists(None).await.expect("Failed to check existence"));
 }
+
+#[tokio::test]
+async fn test_query_standard_attributes() {
+    use npio::FileType;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_standard_attributes_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    tokio::fs::write(test_dir.join("notes.txt"), b"notes").await.unwrap();
+    tokio::fs::write(test_dir.join("notes.txt~"), b"old notes").await.unwrap();
+    tokio::fs::write(test_dir.join(".dotfile"), b"").await.unwrap();
+    tokio::fs::write(test_dir.join("listed"), b"").await.unwrap();
+    tokio::fs::write(test_dir.join(".hidden"), b"listed\n").await.unwrap();
+    std::os::unix::fs::symlink("notes.txt", test_dir.join("link")).unwrap();
+    let fifo_path = std::ffi::CString::new(test_dir.join("fifo").to_string_lossy().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(fifo_path.as_ptr(), 0o644) }, 0);
+
+    let get = |name: &str| get_file_for_uri(&format!("file://{}", test_dir.join(name).to_string_lossy())).unwrap();
+
+    let info = get("notes.txt").query_info("standard::*", None).await.unwrap();
+    assert_eq!(info.get_display_name(), Some("notes.txt"));
+    assert_eq!(info.get_edit_name(), Some("notes.txt"));
+    assert!(!info.get_is_hidden());
+    assert!(!info.get_is_backup());
+    assert!(!info.get_is_symlink());
+    assert_eq!(info.get_sort_order(), 0);
+    assert!(info.has_attribute("standard::allocated-size"));
+    assert_eq!(
+        info.get_attribute("standard::fast-content-type"),
+        Some(&npio::FileAttributeType::String("text/plain".to_string()))
+    );
+    assert_eq!(
+        info.get_attribute("standard::symbolic-icon"),
+        Some(&npio::FileAttributeType::String("text-plain-symbolic".to_string()))
+    );
+
+    assert!(get("notes.txt~").query_info("standard::is-backup", None).await.unwrap().get_is_backup());
+    assert!(get(".dotfile").query_info("standard::is-hidden", None).await.unwrap().get_is_hidden());
+    assert!(get("listed").query_info("standard::is-hidden", None).await.unwrap().get_is_hidden());
+
+    let info = get("link").query_info("standard::*", None).await.unwrap();
+    assert_eq!(info.get_file_type(), FileType::SymbolicLink);
+    assert!(info.get_is_symlink());
+    assert_eq!(info.get_symlink_target(), Some(&b"notes.txt"[..]));
+
+    let info = get("fifo").query_info("standard::type,standard::content-type", None).await.unwrap();
+    assert_eq!(info.get_file_type(), FileType::Special);
+    assert_eq!(info.get_content_type(), Some("inode/fifo"));
+
+    // Enumeration reads .hidden once for all children
+    let dir = get("");
+    let mut enumerator = dir.enumerate_children("standard::name,standard::is-hidden", None).await.unwrap();
+    let mut hidden = Vec::new();
+    while let Some((info, _child)) = enumerator.next_file(None).await.unwrap() {
+        if info.get_is_hidden() {
+            hidden.push(info.get_name().unwrap().to_string());
+        }
+    }
+    hidden.sort();
+    assert_eq!(hidden, vec![".dotfile", ".hidden", "listed"]);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_query_unix_and_owner_attributes() {
+    use std::os::unix::fs::MetadataExt;
+    use npio::{FileAttributeType, FileInfo, FileQueryInfoFlags};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_unix_attributes_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"unix").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+    let metadata = std::fs::metadata(&file_path).unwrap();
+
+    let info = file.query_info("unix::*,owner::*", None).await.unwrap();
+    assert_eq!(info.get_attribute("unix::inode"), Some(&FileAttributeType::Uint64(metadata.ino())));
+    assert_eq!(info.get_attribute("unix::mode"), Some(&FileAttributeType::Uint32(metadata.mode())));
+    assert_eq!(info.get_attribute("unix::uid"), Some(&FileAttributeType::Uint32(metadata.uid())));
+    assert_eq!(info.get_attribute("unix::nlink"), Some(&FileAttributeType::Uint32(1)));
+    assert_eq!(info.get_attribute("unix::is-mountpoint"), Some(&FileAttributeType::Boolean(false)));
+    assert!(info.has_attribute("unix::device"));
+    assert!(info.has_attribute("unix::rdev"));
+    assert!(info.has_attribute("unix::block-size"));
+    assert!(info.has_attribute("unix::blocks"));
+    assert!(!info.has_attribute("standard::name"));
+    if metadata.uid() == 0 {
+        assert_eq!(info.get_attribute("owner::user"), Some(&FileAttributeType::String("root".to_string())));
+        assert!(info.has_attribute("owner::user-real"));
+    }
+    if metadata.gid() == 0 {
+        assert_eq!(info.get_attribute("owner::group"), Some(&FileAttributeType::String("root".to_string())));
+    }
+
+    let root = get_file_for_uri("file:///").unwrap();
+    let info = root.query_info("unix::is-mountpoint", None).await.unwrap();
+    assert_eq!(info.get_attribute("unix::is-mountpoint"), Some(&FileAttributeType::Boolean(true)));
+
+    // Attributes that were set show up in the returned info
+    let mut changes = FileInfo::new();
+    changes.set_attribute("unix::mode", FileAttributeType::Uint32(0o600));
+    let info = file.set_attributes_from_info(&changes, FileQueryInfoFlags::NONE, None).await.unwrap();
+    match info.get_attribute("unix::mode") {
+        Some(FileAttributeType::Uint32(mode)) => assert_eq!(mode & 0o777, 0o600),
+        other => panic!("Unexpected unix::mode: {:?}", other),
+    }
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_nanosecond_timestamps() {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+    use npio::{FileAttributeType, FileInfo, FileQueryInfoFlags};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_timestamps_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"time").await.unwrap();
+    let link_path = test_dir.join("link");
+    std::os::unix::fs::symlink("file.txt", &link_path).unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+    let link = get_file_for_uri(&format!("file://{}", link_path.to_string_lossy())).unwrap();
+
+    let modified = UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+    let accessed = UNIX_EPOCH + Duration::new(1_500_000_000, 987_654_321);
+    let mut changes = FileInfo::new();
+    changes.set_modification_system_time(modified);
+    changes.set_access_system_time(accessed);
+    file.set_attributes_from_info(&changes, FileQueryInfoFlags::NONE, None).await.unwrap();
+
+    let info = file.query_info("time::*", None).await.unwrap();
+    assert_eq!(info.get_modification_system_time(), Some(modified));
+    assert_eq!(info.get_access_system_time(), Some(accessed));
+    assert_eq!(info.get_attribute("time::modified-usec"), Some(&FileAttributeType::Uint32(123_456)));
+    assert_eq!(info.get_modification_date_time().unwrap().timestamp_subsec_nanos(), 123_456_789);
+    assert!(info.get_changed_system_time().is_some());
+    if std::fs::metadata(&file_path).unwrap().created().is_ok() {
+        assert!(info.get_creation_system_time().is_some());
+    }
+
+    // Only the sub-second part changes, the seconds stay
+    file.set_attribute("time::modified-nsec", &FileAttributeType::Uint32(5), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let metadata = std::fs::metadata(&file_path).unwrap();
+    assert_eq!((metadata.mtime(), metadata.mtime_nsec()), (1_600_000_000, 5));
+
+    // Without following, the link itself changes and the target keeps its time
+    link.set_attribute("time::modified", &FileAttributeType::Uint64(1_000_000_000), FileQueryInfoFlags::NOFOLLOW_SYMLINKS, None)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::symlink_metadata(&link_path).unwrap().mtime(), 1_000_000_000);
+    assert_eq!(std::fs::metadata(&file_path).unwrap().mtime(), 1_600_000_000);
+
+    // The older time::accessed name still sets the access time
+    file.set_attribute("time::accessed", &FileAttributeType::Uint64(1_400_000_000), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::metadata(&file_path).unwrap().atime(), 1_400_000_000);
+    let settable = file.query_settable_attributes(None).await.unwrap();
+    assert!(settable.iter().any(|attribute| attribute.name == "time::accessed"));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_extended_attributes() {
+    use npio::{FileAttributeType, FileQueryInfoFlags};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_xattr_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"xattr").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    let namespaces = file.query_writable_namespaces(None).await.unwrap();
+    if !namespaces.iter().any(|namespace| namespace.name == "xattr") {
+        // The temporary directory is on a filesystem without user xattrs
+        tokio::fs::remove_dir_all(&test_dir).await.ok();
+        return;
+    }
+    let settable = file.query_settable_attributes(None).await.unwrap();
+    assert!(settable.iter().any(|attribute| attribute.name == "unix::mode"));
+
+    // Escaped strings and raw bytes end up as the same escaped value
+    file.set_attribute("xattr::comment", &FileAttributeType::String("a\\x00b".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    file.set_attribute("xattr::raw", &FileAttributeType::ByteString(vec![0xff, b'\\', b'z']), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+
+    let info = file.query_info("xattr::*", None).await.unwrap();
+    assert_eq!(info.get_attribute("xattr::comment"), Some(&FileAttributeType::String("a\\x00b".to_string())));
+    assert_eq!(info.get_attribute("xattr::raw"), Some(&FileAttributeType::String("\\xff\\x5cz".to_string())));
+    assert!(!info.has_attribute("standard::name"));
+
+    let info = file.query_info("xattr::raw", None).await.unwrap();
+    assert!(!info.has_attribute("xattr::comment"));
+
+    // Invalid removes the attribute
+    file.set_attribute("xattr::comment", &FileAttributeType::Invalid, FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let info = file.query_info("xattr::*", None).await.unwrap();
+    assert!(!info.has_attribute("xattr::comment"));
+    assert!(info.has_attribute("xattr::raw"));
+
+    // xattr::X is always user.X, even if X itself starts with "user.", so names survive
+    // a query and set round trip
+    file.set_attribute("xattr::nested", &FileAttributeType::String("outer".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    file.set_attribute("xattr::user.nested", &FileAttributeType::String("inner".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let mut info = file.query_info("xattr::*", None).await.unwrap();
+    assert_eq!(info.get_attribute("xattr::nested"), Some(&FileAttributeType::String("outer".to_string())));
+    assert_eq!(info.get_attribute("xattr::user.nested"), Some(&FileAttributeType::String("inner".to_string())));
+
+    let copy_path = test_dir.join("copy.txt");
+    tokio::fs::write(&copy_path, b"xattr").await.unwrap();
+    let copy = get_file_for_uri(&format!("file://{}", copy_path.to_string_lossy())).unwrap();
+    copy.set_attributes_from_info(&info, FileQueryInfoFlags::NONE, None).await.unwrap();
+    let copied = copy.query_info("xattr::*", None).await.unwrap();
+    assert_eq!(copied.get_attribute("xattr::nested"), Some(&FileAttributeType::String("outer".to_string())));
+    assert_eq!(copied.get_attribute("xattr::user.nested"), Some(&FileAttributeType::String("inner".to_string())));
+    let value = |name: &str| {
+        let path = std::ffi::CString::new(copy_path.to_string_lossy().as_bytes()).unwrap();
+        let name = std::ffi::CString::new(name).unwrap();
+        let mut buffer = [0u8; 16];
+        let size = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buffer.as_mut_ptr().cast(), buffer.len()) };
+        buffer[..size.max(0) as usize].to_vec()
+    };
+    assert_eq!(value("user.nested"), b"outer");
+    assert_eq!(value("user.user.nested"), b"inner");
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_set_attributes_status() {
+    use std::os::unix::fs::PermissionsExt;
+    use npio::{FileAttributeStatus, FileAttributeType, FileInfo, FileQueryInfoFlags};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_attribute_status_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"status").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    let mut changes = FileInfo::new();
+    changes.set_attribute("unix::mode", FileAttributeType::Uint32(0o640));
+    changes.set_attribute("time::modified", FileAttributeType::Uint64(1_600_000_000));
+    changes.set_size(1);
+    changes.set_content_type("text/plain");
+    assert_eq!(changes.get_attribute_status("unix::mode"), FileAttributeStatus::Unset);
+
+    // Everything settable is applied despite the failures
+    let err = file.set_attributes_from_info_with_status(&mut changes, FileQueryInfoFlags::NONE, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::NotSupported));
+    let message = err.to_string();
+    assert!(message.contains("standard::size"), "{}", message);
+    assert!(message.contains("standard::content-type"), "{}", message);
+    assert!(!message.contains("unix::mode"), "{}", message);
+
+    assert_eq!(changes.get_attribute_status("unix::mode"), FileAttributeStatus::Set);
+    assert_eq!(changes.get_attribute_status("time::modified"), FileAttributeStatus::Set);
+    assert_eq!(changes.get_attribute_status("standard::size"), FileAttributeStatus::ErrorSetting);
+    assert_eq!(changes.get_attribute_status("standard::content-type"), FileAttributeStatus::ErrorSetting);
+    let metadata = std::fs::metadata(&file_path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert_eq!(std::os::unix::fs::MetadataExt::mtime(&metadata), 1_600_000_000);
+
+    assert!(!changes.set_attribute_status("standard::name", FileAttributeStatus::Set));
+    changes.clear_status();
+    assert_eq!(changes.get_attribute_status("unix::mode"), FileAttributeStatus::Unset);
+
+    // Owner and mode cannot be unset; nothing changes and the status says so
+    let mut changes = FileInfo::new();
+    for key in ["unix::mode", "unix::uid", "unix::gid"] {
+        changes.set_attribute(key, FileAttributeType::Invalid);
+    }
+    let err = file.set_attributes_from_info_with_status(&mut changes, FileQueryInfoFlags::NONE, None).await.unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+    for key in ["unix::mode", "unix::uid", "unix::gid"] {
+        assert_eq!(changes.get_attribute_status(key), FileAttributeStatus::ErrorSetting, "{}", key);
+    }
+    assert_eq!(std::fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, 0o640);
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_query_filesystem_info() {
+    use std::os::unix::fs::MetadataExt;
+    use npio::{FileAttributeType, FilesystemPreviewType};
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir();
+    let dir = get_file_for_uri(&format!("file://{}", test_dir.to_string_lossy())).unwrap();
+    let info = dir.query_filesystem_info("filesystem::*", None).await.unwrap();
+
+    let string = |key: &str| match info.get_attribute(key) {
+        Some(FileAttributeType::String(value)) => value.clone(),
+        other => panic!("Unexpected {}: {:?}", key, other),
+    };
+    assert_ne!(string("filesystem::type"), "unknown");
+    assert!(!string("filesystem::source").is_empty());
+    assert_eq!(string("filesystem::id"), std::fs::metadata(&test_dir).unwrap().dev().to_string());
+    assert_eq!(info.get_attribute("filesystem::remote"), Some(&FileAttributeType::Boolean(false)));
+    assert_eq!(
+        info.get_attribute("filesystem::use-preview"),
+        Some(&FileAttributeType::Uint32(FilesystemPreviewType::IfLocal as u32))
+    );
+    let options = string("filesystem::options");
+    let readonly = options.split(',').any(|option| option == "ro");
+    assert_eq!(info.get_attribute("filesystem::readonly"), Some(&FileAttributeType::Boolean(readonly)));
+    assert!(info.has_attribute("filesystem::inodes"));
+    assert!(info.has_attribute("filesystem::inodes-free"));
+
+    // Kernel pseudo filesystems are never previewed
+    let proc_dir = get_file_for_uri("file:///proc").unwrap();
+    let info = proc_dir.query_filesystem_info("filesystem::type,filesystem::use-preview", None).await.unwrap();
+    assert_eq!(info.get_attribute("filesystem::type"), Some(&FileAttributeType::String("proc".to_string())));
+    assert_eq!(
+        info.get_attribute("filesystem::use-preview"),
+        Some(&FileAttributeType::Uint32(FilesystemPreviewType::Never as u32))
+    );
+    assert!(!info.has_attribute("filesystem::size"));
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::job;
+use npio::{get_file_for_uri, register_backend, CopyFlags, FileAttributeType, FileQueryInfoFlags};
+
+#[tokio::test]
+async fn test_metadata_store() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_metadata_store_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let data_home = test_dir.join("data");
+    std::env::set_var("XDG_DATA_HOME", &data_home);
+
+    let file_path = test_dir.join("file.txt");
+    tokio::fs::write(&file_path, b"metadata").await.unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+
+    // Strings and string lists round-trip, other types are refused
+    let emblems = FileAttributeType::Stringv(vec!["emblem-important".to_string(), "emblem-new".to_string()]);
+    file.set_attribute("metadata::custom-icon", &FileAttributeType::String("folder-music".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    file.set_attribute("metadata::emblems", &emblems, FileQueryInfoFlags::NONE, None).await.unwrap();
+    let err = file
+        .set_attribute("metadata::position", &FileAttributeType::Uint32(3), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+    let info = file.query_info("metadata::*", None).await.unwrap();
+    assert_eq!(info.get_attribute("metadata::custom-icon"), Some(&FileAttributeType::String("folder-music".to_string())));
+    assert_eq!(info.get_attribute("metadata::emblems"), Some(&emblems));
+    let info = file.query_info("metadata::emblems", None).await.unwrap();
+    assert!(!info.has_attribute("metadata::custom-icon"));
+    // The file itself is untouched
+    assert_eq!(tokio::fs::read(&file_path).await.unwrap(), b"metadata");
+
+    let missing = get_file_for_uri(&format!("file://{}", test_dir.join("missing").to_string_lossy())).unwrap();
+    let err = missing
+        .set_attribute("metadata::custom-icon", &FileAttributeType::String("x".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::NotFound));
+
+    file.set_attribute("metadata::custom-icon", &FileAttributeType::Invalid, FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let info = file.query_info("metadata::*", None).await.unwrap();
+    assert!(!info.has_attribute("metadata::custom-icon"));
+    assert!(info.has_attribute("metadata::emblems"));
+
+    // Metadata follows moves, renames and whole directories
+    let moved_path = test_dir.join("moved.txt");
+    let moved = get_file_for_uri(&format!("file://{}", moved_path.to_string_lossy())).unwrap();
+    job::move_(&*file, &*moved, CopyFlags::NONE, None, None).await.unwrap();
+    assert_eq!(moved.query_info("metadata::emblems", None).await.unwrap().get_attribute("metadata::emblems"), Some(&emblems));
+    tokio::fs::write(&file_path, b"new").await.unwrap();
+    assert!(!file.query_info("metadata::*", None).await.unwrap().has_attribute("metadata::emblems"));
+
+    moved
+        .set_attribute("standard::display-name", &FileAttributeType::String("renamed.txt".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let renamed_path = test_dir.join("renamed.txt");
+    let renamed = get_file_for_uri(&format!("file://{}", renamed_path.to_string_lossy())).unwrap();
+    assert!(renamed.query_info("metadata::*", None).await.unwrap().has_attribute("metadata::emblems"));
+
+    let dir_path = test_dir.join("dir");
+    tokio::fs::create_dir(&dir_path).await.unwrap();
+    let child_path = dir_path.join("child.txt");
+    tokio::fs::write(&child_path, b"child").await.unwrap();
+    let child = get_file_for_uri(&format!("file://{}", child_path.to_string_lossy())).unwrap();
+    child
+        .set_attribute("metadata::custom-icon", &FileAttributeType::String("child".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let dir = get_file_for_uri(&format!("file://{}", dir_path.to_string_lossy())).unwrap();
+    let moved_dir = get_file_for_uri(&format!("file://{}", test_dir.join("moved-dir").to_string_lossy())).unwrap();
+    job::move_(&*dir, &*moved_dir, CopyFlags::NONE, None, None).await.unwrap();
+    let moved_child = get_file_for_uri(&format!("file://{}", test_dir.join("moved-dir/child.txt").to_string_lossy())).unwrap();
+    assert_eq!(
+        moved_child.query_info("metadata::custom-icon", None).await.unwrap().get_attribute("metadata::custom-icon"),
+        Some(&FileAttributeType::String("child".to_string()))
+    );
+
+    // Deleting forgets it
+    renamed.delete(None).await.unwrap();
+    tokio::fs::write(&renamed_path, b"again").await.unwrap();
+    assert!(!renamed.query_info("metadata::*", None).await.unwrap().has_attribute("metadata::emblems"));
+
+    // Concurrent writers neither lose changes nor stop the journal from being compacted
+    let value = "x".repeat(256);
+    let mut tasks = Vec::new();
+    for task in 0..4 {
+        let uri = format!("file://{}", file_path.to_string_lossy());
+        let value = value.clone();
+        tasks.push(tokio::spawn(async move {
+            let file = get_file_for_uri(&uri).unwrap();
+            for index in 0..100 {
+                file.set_attribute(
+                    &format!("metadata::key-{}-{}", task, index),
+                    &FileAttributeType::String(value.clone()),
+                    FileQueryInfoFlags::NONE,
+                    None,
+                )
+                .await
+                .unwrap();
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let info = file.query_info("metadata::*", None).await.unwrap();
+    assert_eq!(info.get_all_attributes().filter(|(key, _)| key.starts_with("metadata::key-")).count(), 400);
+    let metadata_dir = data_home.join("npio/metadata");
+    let snapshots = std::fs::read_dir(&metadata_dir)
+        .unwrap()
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "db"))
+        .count();
+    assert_eq!(snapshots, 1);
+
+    std::env::remove_var("XDG_DATA_HOME");
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
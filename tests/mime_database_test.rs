@@ -0,0 +1,82 @@
+use npio::MimeDatabase;
+
+#[test]
+fn test_mime_hierarchy_and_descriptions() {
+    let test_dir = std::env::temp_dir().join("npio_mime_database_test");
+    if test_dir.exists() {
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+    let user_dir = test_dir.join("user/mime");
+    let system_dir = test_dir.join("system/mime");
+    std::fs::create_dir_all(user_dir.join("application")).unwrap();
+    std::fs::create_dir_all(system_dir.join("application")).unwrap();
+
+    std::fs::write(system_dir.join("aliases"), "application/x-old-script application/x-script\n").unwrap();
+    std::fs::write(
+        system_dir.join("subclasses"),
+        "application/x-script text/x-source\ntext/x-source text/plain\napplication/x-archive application/zip\n",
+    )
+    .unwrap();
+    // The user's directory redefines the parents of the archive type
+    std::fs::write(user_dir.join("subclasses"), "application/x-archive application/x-container\n").unwrap();
+    std::fs::write(system_dir.join("generic-icons"), "application/x-script:text-x-script\n").unwrap();
+    std::fs::write(user_dir.join("icons"), "application/x-script:my-script\n").unwrap();
+    std::fs::write(
+        system_dir.join("application/x-script.xml"),
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<mime-type xmlns="http://www.freedesktop.org/standards/shared-mime-info" type="application/x-script">
+  <comment>script &amp; macro</comment>
+  <comment xml:lang="de">Skript &#x26; Makro</comment>
+  <comment xml:lang="pt_BR">roteiro</comment>
+  <acronym>SAM</acronym>
+</mime-type>
+"#,
+    )
+    .unwrap();
+
+    let database = MimeDatabase::load(&[user_dir, system_dir]);
+
+    assert_eq!(database.canonicalize("application/x-old-script"), "application/x-script");
+    assert_eq!(database.canonicalize("text/plain"), "text/plain");
+    assert_eq!(database.parents("application/x-old-script"), vec!["text/x-source"]);
+    assert_eq!(database.parents("application/x-archive"), vec!["application/x-container"]);
+
+    assert!(database.is_a("application/x-old-script", "text/plain"));
+    assert!(database.is_a("application/x-script", "text/*"));
+    assert!(database.is_a("application/x-script", "application/octet-stream"));
+    assert!(database.is_a("text/csv", "text/plain"));
+    assert!(!database.is_a("application/x-archive", "application/zip"));
+    assert!(!database.is_a("inode/directory", "application/octet-stream"));
+    assert!(!database.is_a("text/plain", "application/x-script"));
+
+    assert_eq!(database.description("application/x-old-script", Some("C")).as_deref(), Some("script & macro"));
+    assert_eq!(database.description("application/x-script", Some("de_AT.UTF-8")).as_deref(), Some("Skript & Makro"));
+    assert_eq!(database.description("application/x-script", Some("pt_BR")).as_deref(), Some("roteiro"));
+    assert_eq!(database.description("application/x-script", Some("pt_PT")).as_deref(), Some("script & macro"));
+    assert_eq!(database.description("application/x-unknown", None), None);
+    assert_eq!(database.description("../../etc/passwd", None), None);
+
+    assert_eq!(
+        database.icon_names("application/x-old-script"),
+        vec!["my-script", "application-x-script", "text-x-script"]
+    );
+    assert_eq!(database.icon_names("image/x-thing"), vec!["image-x-thing", "image-x-generic"]);
+    assert_eq!(database.symbolic_icon_names("image/x-thing"), vec!["image-x-thing-symbolic", "image-x-generic-symbolic"]);
+
+    std::fs::remove_dir_all(&test_dir).ok();
+}
+
+#[test]
+fn test_system_mime_database() {
+    let database = MimeDatabase::global();
+    if !database.has_globs() {
+        // No shared-mime-info database installed
+        return;
+    }
+    assert!(database.is_a("application/x-shellscript", "text/plain"));
+    assert_eq!(database.canonicalize("application/x-pdf"), "application/pdf");
+    assert_eq!(database.description("text/plain", Some("C")).as_deref(), Some("plain text document"));
+    assert_eq!(database.generic_icon_name("inode/directory"), "folder");
+    // An OpenDocument file is a zip archive, yet its name decides
+    assert_eq!(database.guess(Some("report.odt"), Some(b"PK\x03\x04")), "application/vnd.oasis.opendocument.text");
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, FileAttributeType, MimeDatabase};
+
+// A magic match line: `[indent]>offset=<u16 length><value>[&mask][+range]\n`
+fn magic_match(indent: &str, offset: usize, value: &[u8], mask: Option<&[u8]>, range: Option<usize>) -> Vec<u8> {
+    let mut line = format!("{}>{}=", indent, offset).into_bytes();
+    line.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    line.extend_from_slice(value);
+    if let Some(mask) = mask {
+        line.push(b'&');
+        line.extend_from_slice(mask);
+    }
+    if let Some(range) = range {
+        line.extend_from_slice(format!("+{}", range).as_bytes());
+    }
+    line.push(b'\n');
+    line
+}
+
+#[test]
+fn test_mime_database_fixtures() {
+    let test_dir = std::env::temp_dir().join("npio_mime_sniff_test");
+    if test_dir.exists() {
+        std::fs::remove_dir_all(&test_dir).unwrap();
+    }
+    let user_dir = test_dir.join("user/mime");
+    let system_dir = test_dir.join("system/mime");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::create_dir_all(&system_dir).unwrap();
+
+    std::fs::write(
+        system_dir.join("globs2"),
+        "# weight:type:pattern\n50:text/x-old:*.foo\n50:text/plain:*.txt\n",
+    )
+    .unwrap();
+    std::fs::write(
+        user_dir.join("globs2"),
+        "50:text/x-old:__NOGLOBS__\n\
+         60:text/x-new:*.foo\n\
+         40:text/x-weak:*.foo\n\
+         50:text/x-readme:README:cs\n\
+         50:application/x-ambig-a:*.amb\n\
+         50:application/x-ambig-b:*.amb\n",
+    )
+    .unwrap();
+
+    let mut magic = b"MIME-Magic\0\n".to_vec();
+    magic.extend_from_slice(b"[60:application/x-ambig-b]\n");
+    magic.extend(magic_match("", 0, b"AMBB", None, None));
+    magic.extend_from_slice(b"[50:application/x-nested]\n");
+    magic.extend(magic_match("", 0, b"NS", None, None));
+    magic.extend(magic_match("1", 4, b"X", None, Some(3)));
+    magic.extend_from_slice(b"[40:application/x-masked]\n");
+    magic.extend(magic_match("", 0, &[0xf0, 0x00], Some(&[0xf0, 0xff]), None));
+    std::fs::write(user_dir.join("magic"), magic).unwrap();
+
+    let database = MimeDatabase::load(&[user_dir, system_dir]);
+    assert!(database.has_globs());
+    assert!(database.sniff_length() >= 8 * 1024);
+
+    // Weights, case and patterns dropped by a more important directory
+    assert_eq!(database.types_for_file_name("notes.FOO"), vec!["text/x-new"]);
+    assert_eq!(database.types_for_file_name("notes.txt"), vec!["text/plain"]);
+    assert_eq!(database.types_for_file_name("README"), vec!["text/x-readme"]);
+    assert!(database.types_for_file_name("readme").is_empty());
+    assert_eq!(database.types_for_file_name("x.amb"), vec!["application/x-ambig-a", "application/x-ambig-b"]);
+
+    // Nested matches, ranges and masks
+    assert_eq!(database.type_for_data(b"NS\0\0\0\0X"), Some("application/x-nested"));
+    assert_eq!(database.type_for_data(b"NS\0\0\0\0\0X"), None);
+    assert_eq!(database.type_for_data(&[0xf5, 0x00]), Some("application/x-masked"));
+    assert_eq!(database.type_for_data(&[0xf5, 0x01]), None);
+
+    // Contents settle ambiguous names but do not override clear ones
+    assert_eq!(database.guess(Some("x.amb"), Some(b"AMBB")), "application/x-ambig-b");
+    assert_eq!(database.guess(Some("x.amb"), Some(b"")), "application/x-ambig-a");
+    assert_eq!(database.guess(Some("a.txt"), Some(b"AMBB")), "text/plain");
+    assert_eq!(database.guess(Some("unknown"), Some(b"AMBB")), "application/x-ambig-b");
+    assert_eq!(database.guess(None, Some(b"")), "application/x-zerosize");
+    assert_eq!(database.guess(None, Some("plain text \u{e9}".as_bytes())), "text/plain");
+    assert_eq!(database.guess(None, Some(&[0x00, 0x01, 0x02])), "application/octet-stream");
+
+    std::fs::remove_dir_all(&test_dir).ok();
+}
+
+#[tokio::test]
+async fn test_local_content_sniffing() {
+    if !MimeDatabase::global().has_globs() {
+        // No shared-mime-info database installed
+        return;
+    }
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_mime_sniff_local_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir(&test_dir).await.unwrap();
+    let script_path = test_dir.join("script");
+    let empty_path = test_dir.join("empty");
+    tokio::fs::write(&script_path, b"#!/bin/sh\necho hello\n").await.unwrap();
+    tokio::fs::write(&empty_path, b"").await.unwrap();
+    let script = get_file_for_uri(&format!("file://{}", script_path.to_string_lossy())).unwrap();
+    let empty = get_file_for_uri(&format!("file://{}", empty_path.to_string_lossy())).unwrap();
+
+    // The fast type only looks at the name, the full one at the contents too
+    let info = script.query_info("standard::content-type,standard::fast-content-type", None).await.unwrap();
+    assert_eq!(info.get_content_type(), Some("application/x-shellscript"));
+    assert_eq!(
+        info.get_attribute("standard::fast-content-type"),
+        Some(&FileAttributeType::String("application/octet-stream".to_string()))
+    );
+
+    let info = empty.query_info("standard::content-type", None).await.unwrap();
+    assert_eq!(info.get_content_type(), Some("application/x-zerosize"));
+
+    // Listings with standard::* do not open the file
+    let info = script.query_info("standard::*", None).await.unwrap();
+    assert_eq!(info.get_content_type(), Some("application/octet-stream"));
+    let info = script.query_info("standard::*,standard::content-type", None).await.unwrap();
+    assert_eq!(info.get_content_type(), Some("application/x-shellscript"));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,68 @@
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{get_file_for_uri, register_backend, MimeDatabase, MountBackend};
+
+const TREE_MAGIC: &str = "MIME-TreeMagic\0\n\
+[50:x-content/image-dcf]\n\
+>\"dcim\"=directory,non-empty\n\
+[50:x-content/unix-software]\n\
+>\"autorun\"=file,match-case\n\
+[60:x-content/video-dvd]\n\
+>\"VIDEO_TS/VIDEO_TS.IFO\"=file\n\
+[50:x-content/win32-software]\n\
+>\"autorun.exe\"=file,executable\n\
+[40:x-content/nested]\n\
+>\"music\"=directory\n\
+1>\"music/playlist.m3u\"=file\n\
+[40:x-content/typed]\n\
+>\"info\"=file,text/x-info\n";
+
+#[tokio::test]
+async fn test_guess_for_tree() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_mime_tree_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    let mime_dir = test_dir.join("mime");
+    let tree = test_dir.join("tree");
+    std::fs::create_dir_all(&mime_dir).unwrap();
+    std::fs::create_dir_all(tree.join("DCIM/100CAMERA")).unwrap();
+    std::fs::create_dir_all(tree.join("video_ts")).unwrap();
+    std::fs::create_dir_all(tree.join("music")).unwrap();
+    std::fs::write(mime_dir.join("treemagic"), TREE_MAGIC).unwrap();
+    std::fs::write(mime_dir.join("globs2"), "50:text/x-info:info\n").unwrap();
+    std::fs::write(tree.join("video_ts/video_ts.ifo"), b"DVD").unwrap();
+    // Wrong case for a match-case rule, and not executable
+    std::fs::write(tree.join("Autorun"), b"#!/bin/sh\n").unwrap();
+    std::fs::write(tree.join("autorun.exe"), b"MZ").unwrap();
+    std::fs::write(tree.join("info"), b"plain words").unwrap();
+    let database = MimeDatabase::load(&[mime_dir]);
+    let root = get_file_for_uri(&format!("file://{}", tree.to_string_lossy())).unwrap();
+
+    // Highest priority first; the nested match needs its child
+    assert_eq!(
+        database.guess_for_tree(&*root, None).await.unwrap(),
+        vec!["x-content/video-dvd", "x-content/image-dcf", "x-content/typed"]
+    );
+
+    std::fs::write(tree.join("music/playlist.m3u"), b"").unwrap();
+    std::fs::set_permissions(tree.join("autorun.exe"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(tree.join(".is_audio_player"), b"").unwrap();
+    std::fs::remove_dir(tree.join("DCIM/100CAMERA")).unwrap();
+    assert_eq!(
+        database.guess_for_tree(&*root, None).await.unwrap(),
+        vec!["x-content/video-dvd", "x-content/win32-software", "x-content/nested", "x-content/typed", "x-content/audio-player"]
+    );
+
+    // Through the mount containing a directory
+    let mount = MountBackend::new().get_mount_for_path(&tree).await.unwrap();
+    if let Some(mount) = mount {
+        assert!(mount.guess_content_type(None).await.is_ok());
+    }
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
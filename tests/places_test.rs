@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use npio::backend::local::LocalBackend;
+use npio::{
+    get_file_for_uri, register_backend, Bookmark, Bookmarks, IOErrorEnum, PlacesEvent, PlacesMonitor, UserDirectory,
+    UserDirs,
+};
+
+const USER_DIRS: &str = "# Written by xdg-user-dirs-update\n\
+XDG_DESKTOP_DIR=\"$HOME/Schreibtisch\"\n\
+XDG_DOCUMENTS_DIR=\"$HOME/Dokumente/\"\n\
+XDG_DOWNLOAD_DIR=\"/data/Down \\\"loads\\\"\"\n\
+XDG_MUSIC_DIR=\"$HOME\"\n\
+XDG_PICTURES_DIR=\"relative/Bilder\"\n\
+XDG_VIDEOS_DIR=$HOME/Videos\n";
+
+const BOOKMARKS: &str = "file:///home/user/My%20Projects Projects and more\n\
+file:///tmp\n\
+\n\
+sftp://server/srv/share Server\n";
+
+async fn next_event(receiver: &mut tokio::sync::broadcast::Receiver<PlacesEvent>) -> PlacesEvent {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn test_places() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_places_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    let home = test_dir.join("home");
+    let config = test_dir.join("config");
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::create_dir_all(&config).unwrap();
+    std::env::set_var("HOME", &home);
+    std::env::set_var("XDG_CONFIG_HOME", &config);
+
+    // User directories
+    let user_dirs = UserDirs::parse(USER_DIRS, &home);
+    assert_eq!(user_dirs.get_path(UserDirectory::Desktop), Some(home.join("Schreibtisch")));
+    assert_eq!(user_dirs.get_path(UserDirectory::Documents), Some(home.join("Dokumente")));
+    assert_eq!(user_dirs.get_path(UserDirectory::Download), Some(PathBuf::from("/data/Down \"loads\"")));
+    // Disabled by pointing at the home directory, or not in a form the specification allows
+    assert_eq!(user_dirs.get_path(UserDirectory::Music), None);
+    assert_eq!(user_dirs.get_path(UserDirectory::Pictures), None);
+    assert_eq!(user_dirs.get_path(UserDirectory::Videos), None);
+    assert_eq!(user_dirs.get_path(UserDirectory::Templates), None);
+    assert_eq!(user_dirs.display_name(UserDirectory::Documents).as_deref(), Some("Dokumente"));
+    let documents = user_dirs.get_file(UserDirectory::Documents).unwrap().unwrap();
+    assert_eq!(documents.path(), Some(home.join("Dokumente")));
+    assert!(user_dirs.get_file(UserDirectory::Music).unwrap().is_none());
+    assert_eq!(UserDirectory::from_key("XDG_PUBLICSHARE_DIR"), Some(UserDirectory::PublicShare));
+
+    // Without user-dirs.dirs only the desktop is known
+    let defaults = UserDirs::load();
+    assert_eq!(defaults.get_path(UserDirectory::Desktop), Some(home.join("Desktop")));
+    assert_eq!(defaults.get_path(UserDirectory::Documents), None);
+    std::fs::write(config.join("user-dirs.dirs"), USER_DIRS).unwrap();
+    assert_eq!(UserDirs::load(), user_dirs);
+
+    // GTK 2 bookmarks are read until GTK 3 ones exist
+    std::fs::write(home.join(".gtk-bookmarks"), "file:///legacy\n").unwrap();
+    let legacy = Bookmarks::load().unwrap();
+    assert_eq!(legacy.bookmarks(), &[Bookmark::new("file:///legacy", None).unwrap()]);
+    let bookmarks_path = config.join("gtk-3.0/bookmarks");
+    std::fs::create_dir_all(bookmarks_path.parent().unwrap()).unwrap();
+    std::fs::write(&bookmarks_path, BOOKMARKS).unwrap();
+
+    let mut bookmarks = Bookmarks::load().unwrap();
+    let uris: Vec<&str> = bookmarks.bookmarks().iter().map(Bookmark::uri).collect();
+    assert_eq!(uris, vec!["file:///home/user/My%20Projects", "file:///tmp", "sftp://server/srv/share"]);
+    let projects = &bookmarks.bookmarks()[0];
+    assert_eq!(projects.label(), Some("Projects and more"));
+    assert_eq!(projects.path(), Some(PathBuf::from("/home/user/My Projects")));
+    assert_eq!(bookmarks.bookmarks()[1].display_name(), "tmp");
+    assert_eq!(bookmarks.bookmarks()[2].display_name(), "Server");
+    // No backend for sftp
+    let files = bookmarks.files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path(), Some(PathBuf::from("/home/user/My Projects")));
+
+    // Editing
+    let spaced = home.join("with space#1");
+    let spaced_file = get_file_for_uri(&format!("file://{}", spaced.to_string_lossy())).unwrap();
+    let spaced_bookmark = Bookmark::for_file(&*spaced_file, None).unwrap();
+    assert_eq!(spaced_bookmark.uri(), format!("file://{}/with%20space%231", home.display()));
+    assert_eq!(spaced_bookmark.display_name(), "with space#1");
+    assert_eq!(spaced_bookmark.get_file().unwrap().path(), Some(spaced.clone()));
+    bookmarks.insert(spaced_bookmark.clone(), Some(0)).unwrap();
+    let error = bookmarks.insert(spaced_bookmark.clone(), None).unwrap_err();
+    assert!(matches!(error.kind(), IOErrorEnum::Exists));
+    bookmarks.rename("file:///tmp", Some("Scratch")).unwrap();
+    bookmarks.move_to("file:///tmp", 0).unwrap();
+    bookmarks.remove("sftp://server/srv/share").unwrap();
+    assert!(matches!(bookmarks.remove("file:///missing").unwrap_err().kind(), IOErrorEnum::NotFound));
+    bookmarks.save().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&bookmarks_path).unwrap(),
+        format!(
+            "file:///tmp Scratch\n{}\nfile:///home/user/My%20Projects Projects and more\n",
+            spaced_bookmark.uri()
+        )
+    );
+    assert_eq!(Bookmarks::load().unwrap(), bookmarks);
+
+    // Every bookmark stays on its own line
+    for uri in ["file:///a b", "file:///a\nfile:///b", ""] {
+        assert!(matches!(Bookmark::new(uri, None).unwrap_err().kind(), IOErrorEnum::InvalidArg), "{:?}", uri);
+    }
+    let multiline = Bookmark::new("file:///multi", Some("first\nfile:///injected second\r\n")).unwrap();
+    assert_eq!(multiline.label(), Some("first file:///injected second"));
+    bookmarks.insert(multiline, None).unwrap();
+    bookmarks.rename("file:///tmp", Some("Scratch\n")).unwrap();
+    bookmarks.save().unwrap();
+    assert_eq!(Bookmarks::load().unwrap(), bookmarks);
+    assert_eq!(std::fs::read_to_string(&bookmarks_path).unwrap().lines().count(), 4);
+    bookmarks.remove("file:///multi").unwrap();
+    bookmarks.save().unwrap();
+
+    // Change events carry the new contents
+    let monitor = PlacesMonitor::new();
+    let mut receiver = monitor.subscribe();
+    monitor.start(None).await.unwrap();
+    bookmarks.remove("file:///tmp").unwrap();
+    bookmarks.save().unwrap();
+    match next_event(&mut receiver).await {
+        PlacesEvent::BookmarksChanged(changed) => assert_eq!(changed, bookmarks),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    std::fs::write(config.join("user-dirs.dirs"), "XDG_MUSIC_DIR=\"$HOME/Musik\"\n").unwrap();
+    match next_event(&mut receiver).await {
+        PlacesEvent::UserDirsChanged(changed) => {
+            assert_eq!(changed.get_path(UserDirectory::Music), Some(home.join("Musik")))
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+
+    // A change while nobody listens does not end the monitoring
+    drop(receiver);
+    bookmarks.rename("file:///home/user/My%20Projects", Some("Unheard")).unwrap();
+    bookmarks.save().unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let mut receiver = monitor.subscribe();
+    bookmarks.rename("file:///home/user/My%20Projects", Some("Heard")).unwrap();
+    bookmarks.save().unwrap();
+    match next_event(&mut receiver).await {
+        PlacesEvent::BookmarksChanged(changed) => assert_eq!(changed, bookmarks),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    monitor.stop().await;
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use npio::backend::local::LocalBackend;
+use npio::{find_tagged_files, get_file_for_uri, register_backend, FileAttributeType, FileQueryInfoFlags, TagColor, Tags};
+
+#[test]
+fn test_tags() {
+    let mut tags = Tags::parse(" work, ,urgent,work,Red ");
+    assert_eq!(tags.to_string(), "work,urgent,Red");
+    assert_eq!(tags.color_label(), Some(TagColor::Red));
+    assert!(!tags.add("urgent").unwrap());
+    assert!(tags.add(" later ").unwrap());
+    assert!(tags.contains("later"));
+    assert!(tags.add("a,b").is_err());
+    assert!(tags.add("  ").is_err());
+
+    tags.set_color_label(Some(TagColor::Blue));
+    assert_eq!(tags.to_string(), "work,urgent,later,Blue");
+    tags.set_color_label(None);
+    assert_eq!(tags.color_label(), None);
+    assert!(tags.remove("work"));
+    assert!(!tags.remove("work"));
+    assert_eq!(tags.len(), 2);
+
+    assert_eq!(TagColor::from_name("purple"), Some(TagColor::Purple));
+    assert_eq!(Tags::from_list(["a", "b", "a"]).unwrap().to_string(), "a,b");
+}
+
+#[tokio::test]
+async fn test_local_tags() {
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_tags_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir_all(test_dir.join("sub")).await.unwrap();
+    let file_path = test_dir.join("file.txt");
+    let nested_path = test_dir.join("sub/nested.txt");
+    tokio::fs::write(&file_path, b"tags").await.unwrap();
+    tokio::fs::write(&nested_path, b"tags").await.unwrap();
+    let dir = get_file_for_uri(&format!("file://{}", test_dir.to_string_lossy())).unwrap();
+    let file = get_file_for_uri(&format!("file://{}", file_path.to_string_lossy())).unwrap();
+    let nested = get_file_for_uri(&format!("file://{}", nested_path.to_string_lossy())).unwrap();
+
+    assert!(file.query_tags(None).await.unwrap().is_empty());
+    file.set_tags(&Tags::from_list(["project", "draft"]).unwrap(), None).await.unwrap();
+    assert_eq!(file.query_tags(None).await.unwrap().to_string(), "project,draft");
+
+    // Stored in the freedesktop xattr and visible as such
+    let info = file.query_info("xattr::xdg.tags,tags::*", None).await.unwrap();
+    assert_eq!(info.get_attribute("xattr::xdg.tags"), Some(&FileAttributeType::String("project,draft".to_string())));
+    assert_eq!(
+        info.get_attribute("tags::list"),
+        Some(&FileAttributeType::Stringv(vec!["project".to_string(), "draft".to_string()]))
+    );
+    assert!(!info.has_attribute("tags::color-label"));
+
+    // Color labels and comments through attributes
+    file.set_attribute("tags::color-label", &FileAttributeType::String("green".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    file.set_attribute("tags::comment", &FileAttributeType::String("Needs review".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let info = file.query_info("tags::*,xattr::xdg.comment", None).await.unwrap();
+    assert_eq!(info.get_attribute("tags::color-label"), Some(&FileAttributeType::String("Green".to_string())));
+    assert_eq!(info.get_attribute("tags::comment"), Some(&FileAttributeType::String("Needs review".to_string())));
+    assert_eq!(info.get_attribute("xattr::xdg.comment"), Some(&FileAttributeType::String("Needs review".to_string())));
+    assert_eq!(file.query_tags(None).await.unwrap().to_string(), "project,draft,Green");
+    let err = file
+        .set_attribute("tags::color-label", &FileAttributeType::String("teal".to_string()), FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.kind(), npio::IOErrorEnum::InvalidArg));
+
+    // Searching under a directory
+    nested.set_tags(&Tags::from_list(["project"]).unwrap(), None).await.unwrap();
+    let names = |found: Vec<(npio::FileInfo, Box<dyn npio::File>)>| {
+        let mut names: Vec<String> = found.iter().map(|(_, file)| file.basename()).collect();
+        names.sort();
+        names
+    };
+    assert_eq!(names(find_tagged_files(&*dir, "project", false, None).await.unwrap()), vec!["file.txt"]);
+    assert_eq!(names(find_tagged_files(&*dir, "project", true, None).await.unwrap()), vec!["file.txt", "nested.txt"]);
+    assert_eq!(names(find_tagged_files(&*dir, "Green", true, None).await.unwrap()), vec!["file.txt"]);
+
+    // Removing everything removes the xattrs
+    file.set_tags(&Tags::new(), None).await.unwrap();
+    file.set_attribute("tags::comment", &FileAttributeType::Invalid, FileQueryInfoFlags::NONE, None)
+        .await
+        .unwrap();
+    let info = file.query_info("xattr::*,tags::*", None).await.unwrap();
+    assert!(!info.has_attribute("xattr::xdg.tags"));
+    assert!(!info.has_attribute("tags::comment"));
+
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}
+
+#[tokio::test]
+async fn test_find_tagged_files_skips_unreadable_directories() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let backend = Arc::new(LocalBackend::new());
+    register_backend(backend);
+
+    let test_dir = std::env::temp_dir().join("npio_tags_unreadable_test");
+    if test_dir.exists() {
+        tokio::fs::remove_dir_all(&test_dir).await.unwrap();
+    }
+    tokio::fs::create_dir_all(test_dir.join("a")).await.unwrap();
+    tokio::fs::create_dir_all(test_dir.join("b/locked")).await.unwrap();
+    tokio::fs::write(test_dir.join("a/first.txt"), b"tags").await.unwrap();
+    tokio::fs::write(test_dir.join("b/second.txt"), b"tags").await.unwrap();
+    for path in ["a/first.txt", "b/second.txt"] {
+        let file = get_file_for_uri(&format!("file://{}", test_dir.join(path).to_string_lossy())).unwrap();
+        file.set_tags(&Tags::from_list(["project"]).unwrap(), None).await.unwrap();
+    }
+    std::fs::set_permissions(test_dir.join("b/locked"), std::fs::Permissions::from_mode(0o000)).unwrap();
+
+    let dir = get_file_for_uri(&format!("file://{}", test_dir.to_string_lossy())).unwrap();
+    let mut names: Vec<String> = find_tagged_files(&*dir, "project", true, None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|(_, file)| file.basename())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["first.txt", "second.txt"]);
+
+    // The directory searched itself still has to be readable
+    let locked = get_file_for_uri(&format!("file://{}", test_dir.join("b/locked").to_string_lossy())).unwrap();
+    if unsafe { libc::geteuid() } != 0 {
+        let err = find_tagged_files(&*locked, "project", true, None).await.unwrap_err();
+        assert!(matches!(err.kind(), npio::IOErrorEnum::PermissionDenied));
+    }
+
+    std::fs::set_permissions(test_dir.join("b/locked"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    tokio::fs::remove_dir_all(&test_dir).await.ok();
+}